@@ -0,0 +1,54 @@
+use std::io::{Read, Seek, SeekFrom};
+
+/// Caps on what is read from a file that may be hostile, so a forged count
+/// or size cannot make a reader allocate or loop without bound.
+pub const MAX_PHNUM: usize = 512;
+pub const MAX_SEGMENT: u64 = 1 << 20;
+/// Java class files share the fat magic; their version number is never this
+/// small.
+pub const MAX_ARCHS: u32 = 20;
+pub const MAX_COMMANDS: u64 = 1 << 20;
+pub const MAX_SIGNATURE: u64 = 32 << 20;
+/// Nesting of requirement expressions and ASN.1 elements.
+pub const MAX_DEPTH: usize = 32;
+
+/// `len` bytes at `offset`, refusing reads over `cap`.
+pub fn read_at<R: Read + Seek>(file: &mut R, offset: u64, len: u64, cap: u64) -> Result<Vec<u8>, String> {
+    if len > cap {
+        return Err(format!("{} bytes at {:#x} exceed the {} byte cap", len, offset, cap));
+    }
+    let mut buf = vec![0u8; len as usize];
+    file.seek(SeekFrom::Start(offset)).map_err(|err| err.to_string())?;
+    file.read_exact(&mut buf).map_err(|err| err.to_string())?;
+    Ok(buf)
+}
+
+pub fn u16_at(b: &[u8], at: usize, big_endian: bool) -> u16 {
+    let bytes = [b[at], b[at + 1]];
+    if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+}
+
+pub fn u32_at(b: &[u8], at: usize, big_endian: bool) -> u32 {
+    let bytes = b[at..at + 4].try_into().unwrap();
+    if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+}
+
+pub fn u64_at(b: &[u8], at: usize, big_endian: bool) -> u64 {
+    let bytes = b[at..at + 8].try_into().unwrap();
+    if big_endian { u64::from_be_bytes(bytes) } else { u64::from_le_bytes(bytes) }
+}
+
+pub fn be32(b: &[u8], at: usize) -> u32 {
+    u32_at(b, at, true)
+}
+
+pub fn be64(b: &[u8], at: usize) -> u64 {
+    u64_at(b, at, true)
+}
+
+/// The NUL-terminated string at `at`, if it is terminated.
+pub fn c_string(bytes: &[u8], at: usize) -> Option<String> {
+    let rest = bytes.get(at..)?;
+    let end = rest.iter().position(|&b| b == 0)?;
+    Some(String::from_utf8_lossy(&rest[..end]).into_owned())
+}
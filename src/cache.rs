@@ -0,0 +1,634 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File, Metadata};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use crate::elf::{self, ElfInfo};
+use crate::export::json_escape;
+use crate::json;
+use crate::package::PackageInfo;
+use crate::sha256;
+use crate::signature::{self, SignatureInfo};
+use crate::toml::{Entry, Value};
+
+/// Bumped whenever the record layout changes; older files are ignored.
+const VERSION: i64 = 4;
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+/// Identifies the contents of a file without reading it. A binary replaced
+/// in place (new inode) or rewritten (new size, mtime or ctime) gets a new
+/// key. mtime alone is not enough: `touch -d` or `utimensat` can put it
+/// back after a same-size rewrite, while ctime can only be set by the kernel.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct FileKey {
+    dev: u64,
+    inode: u64,
+    size: u64,
+    /// Seconds and nanoseconds since the epoch.
+    mtime: Option<(u64, u32)>,
+    /// Inode change time, the same way; `None` off Unix.
+    ctime: Option<(u64, u32)>,
+}
+
+impl FileKey {
+    pub fn of(path: &str) -> Option<FileKey> {
+        let meta = fs::metadata(path).ok()?;
+        let (dev, inode) = device_inode(&meta);
+        Some(FileKey {
+            dev,
+            inode,
+            size: meta.len(),
+            mtime: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| (d.as_secs(), d.subsec_nanos())),
+            ctime: change_time(&meta),
+        })
+    }
+}
+
+#[cfg(unix)]
+fn device_inode(meta: &Metadata) -> (u64, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (meta.dev(), meta.ino())
+}
+
+#[cfg(not(unix))]
+fn device_inode(_meta: &Metadata) -> (u64, u64) {
+    (0, 0)
+}
+
+#[cfg(unix)]
+extern "C" {
+    fn geteuid() -> u32;
+}
+
+/// A file the daemon may trust: owned by its effective user and writable by
+/// no one else.
+#[cfg(unix)]
+fn check_owner(meta: &Metadata) -> Result<(), String> {
+    use std::os::unix::fs::MetadataExt;
+    let euid = unsafe { geteuid() };
+    if meta.uid() != euid {
+        return Err(format!("owned by uid {}, not {}", meta.uid(), euid));
+    }
+    if meta.mode() & 0o022 != 0 {
+        return Err(format!("writable by group or others (mode {:o})", meta.mode() & 0o777));
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn restrict(path: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn restrict(_path: &Path) -> std::io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_owner(_meta: &Metadata) -> Result<(), String> {
+    Ok(())
+}
+
+#[cfg(unix)]
+fn change_time(meta: &Metadata) -> Option<(u64, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.ctime() as u64, meta.ctime_nsec() as u32))
+}
+
+#[cfg(not(unix))]
+fn change_time(_meta: &Metadata) -> Option<(u64, u32)> {
+    None
+}
+
+/// Lookup counters, shared with the IPC thread for `STATUS`.
+#[derive(Default)]
+pub struct CacheStats {
+    hash_hits: AtomicU64,
+    hash_misses: AtomicU64,
+    sig_hits: AtomicU64,
+    sig_misses: AtomicU64,
+    entries: AtomicUsize,
+    capacity: AtomicUsize,
+}
+
+impl CacheStats {
+    fn record(hits: &AtomicU64, misses: &AtomicU64, hit: bool) {
+        if hit { hits } else { misses }.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// `Cache: 812/4096 entries, hash hits 40/42 (95%), signature hits ...`
+    pub fn describe(&self) -> String {
+        let rate = |hits: &AtomicU64, misses: &AtomicU64| {
+            let hits = hits.load(Ordering::Relaxed);
+            let total = hits + misses.load(Ordering::Relaxed);
+            match total {
+                0 => "0/0".to_string(),
+                _ => format!("{}/{} ({:.0}%)", hits, total, hits as f64 * 100.0 / total as f64),
+            }
+        };
+        format!(
+            "Cache: {}/{} entries, hash hits {}, signature hits {}\n",
+            self.entries.load(Ordering::Relaxed),
+            self.capacity.load(Ordering::Relaxed),
+            rate(&self.hash_hits, &self.hash_misses),
+            rate(&self.sig_hits, &self.sig_misses),
+        )
+    }
+}
+
+/// What is known about one version of a file. `None` means not looked up
+/// yet; `Some(None)` that the lookup found nothing (unreadable, unsigned).
+struct Record {
+    path: String,
+    hash: Option<Option<String>>,
+    sig: Option<Option<SignatureInfo>>,
+    /// Not saved: reading the ELF headers again is cheap.
+    elf: Option<Option<ElfInfo>>,
+    /// Tick of the last lookup, for LRU eviction.
+    used: u64,
+}
+
+/// Digests, signatures and ELF headers of the executables rules have looked
+/// at, keyed by `FileKey` so a replaced binary is looked up again. Holds at
+/// most `capacity` files, dropping the least recently used, and can be saved
+/// to and loaded from disk so a restarted daemon skips the hashing and
+/// signature parsing it already did.
+pub struct FileCache {
+    records: HashMap<FileKey, Record>,
+    capacity: usize,
+    tick: u64,
+    /// Something worth saving changed since the last `save`.
+    dirty: bool,
+    stats: Arc<CacheStats>,
+}
+
+impl FileCache {
+    pub fn new(capacity: usize) -> FileCache {
+        let stats = CacheStats::default();
+        stats.capacity.store(capacity, Ordering::Relaxed);
+        FileCache {
+            records: HashMap::new(),
+            capacity: capacity.max(1),
+            tick: 0,
+            dirty: false,
+            stats: Arc::new(stats),
+        }
+    }
+
+    /// The cache saved at `path`. A missing file gives an empty cache; so do
+    /// records whose file has changed since, which are dropped. A cached
+    /// digest or signature decides what allow rules match, so a file someone
+    /// else owns or could have written is refused.
+    pub fn load(path: &Path, capacity: usize) -> Result<FileCache, String> {
+        let mut cache = FileCache::new(capacity);
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(cache),
+            Err(err) => return Err(format!("failed to read cache {}: {}", path.display(), err)),
+        };
+        // Checked on the open handle, so the file read is the one checked.
+        let meta = file.metadata().map_err(|err| format!("failed to read cache {}: {}", path.display(), err))?;
+        check_owner(&meta).map_err(|err| format!("{}: {}, ignored", path.display(), err))?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)
+            .map_err(|err| format!("failed to read cache {}: {}", path.display(), err))?;
+        let mut lines = content.lines().enumerate();
+        let version = lines
+            .next()
+            .and_then(|(_, header)| json::parse(header).ok())
+            .and_then(|entries| field(&entries, "zen-cache").and_then(|v| v.as_u64().ok()));
+        if version != Some(VERSION as u64) {
+            return Err(format!("{}: not a version {} zen cache, ignored", path.display(), VERSION));
+        }
+        // Lines are saved least recently used first.
+        for (idx, line) in lines {
+            let (key, mut record) = json::parse(line)
+                .and_then(|entries| read_record(&entries))
+                .map_err(|err| format!("{}: line {}: {}", path.display(), idx + 1, err))?;
+            if FileKey::of(&record.path).as_ref() != Some(&key) {
+                cache.dirty = true;
+                continue;
+            }
+            cache.tick += 1;
+            record.used = cache.tick;
+            cache.records.insert(key, record);
+            cache.evict();
+        }
+        cache.stats.entries.store(cache.records.len(), Ordering::Relaxed);
+        Ok(cache)
+    }
+
+    /// Writes the cache to `path` through a temporary file and a rename, so
+    /// a reader or a crash never sees half a file. Does nothing when nothing
+    /// changed since the last save.
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        let mut records: Vec<(&FileKey, &Record)> = self.records.iter().collect();
+        records.sort_by_key(|(_, record)| record.used);
+        let mut out = format!("{{\"zen-cache\":{}}}\n", VERSION);
+        for (key, record) in records {
+            out.push_str(&write_record(key, record));
+            out.push('\n');
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|err| format!("failed to create {}: {}", dir.display(), err))?;
+        }
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        // 0600 whatever the umask, or `load` would refuse the file.
+        fs::write(&tmp, out)
+            .and_then(|()| restrict(&tmp))
+            .and_then(|()| fs::rename(&tmp, path))
+            .map_err(|err| {
+                let _ = fs::remove_file(&tmp);
+                format!("failed to write cache {}: {}", path.display(), err)
+            })?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn stats(&self) -> Arc<CacheStats> {
+        self.stats.clone()
+    }
+
+    /// SHA-256 of `path`, hashing it unless this version of the file was
+    /// seen before.
+    pub fn hash(&mut self, path: &str) -> Option<&str> {
+        let key = self.touch(path)?;
+        let record = self.records.get_mut(&key)?;
+        CacheStats::record(&self.stats.hash_hits, &self.stats.hash_misses, record.hash.is_some());
+        if record.hash.is_none() {
+            record.hash = Some(sha256::file_hex(path).ok());
+            self.dirty = true;
+        }
+        record.hash.as_ref()?.as_deref()
+    }
+
+    /// Who signed or packaged `path`, reading its code signature or asking
+    /// the package manager unless this version of the file was seen before.
+    pub fn signature(&mut self, path: &str) -> Option<&SignatureInfo> {
+        let key = self.touch(path)?;
+        let record = self.records.get_mut(&key)?;
+        CacheStats::record(&self.stats.sig_hits, &self.stats.sig_misses, record.sig.is_some());
+        if record.sig.is_none() {
+            record.sig = Some(signature::get_signature_info(path));
+            self.dirty = true;
+        }
+        record.sig.as_ref()?.as_ref()
+    }
+
+    /// The ELF build-id, loader and libraries of `path`; `None` when it is
+    /// not an ELF file.
+    pub fn elf(&mut self, path: &str) -> Option<&ElfInfo> {
+        let key = self.touch(path)?;
+        let record = self.records.get_mut(&key)?;
+        record.elf.get_or_insert_with(|| elf::inspect(path)).as_ref()
+    }
+
+    /// The digest of `path` if it is already known; never reads the file.
+    pub fn peek_hash(&self, path: &str) -> Option<&str> {
+        self.records.get(&FileKey::of(path)?)?.hash.as_ref()?.as_deref()
+    }
+
+    /// The ELF headers of `path` if they were already read.
+    pub fn peek_elf(&self, path: &str) -> Option<&ElfInfo> {
+        self.records.get(&FileKey::of(path)?)?.elf.as_ref()?.as_ref()
+    }
+
+    /// The signature of `path` if it is already known.
+    pub fn peek_signature(&self, path: &str) -> Option<&SignatureInfo> {
+        self.records.get(&FileKey::of(path)?)?.sig.as_ref()?.as_ref()
+    }
+
+    /// The key of `path`'s current version, with a record for it that is
+    /// now the most recently used.
+    fn touch(&mut self, path: &str) -> Option<FileKey> {
+        let key = FileKey::of(path)?;
+        self.tick += 1;
+        let tick = self.tick;
+        match self.records.get_mut(&key) {
+            Some(record) => record.used = tick,
+            None => {
+                self.records.insert(
+                    key.clone(),
+                    Record {
+                        path: path.to_string(),
+                        hash: None,
+                        sig: None,
+                        elf: None,
+                        used: tick,
+                    },
+                );
+                self.evict();
+                self.stats.entries.store(self.records.len(), Ordering::Relaxed);
+            }
+        }
+        Some(key)
+    }
+
+    fn evict(&mut self) {
+        while self.records.len() > self.capacity {
+            let Some(oldest) = self.records.iter().min_by_key(|(_, r)| r.used).map(|(k, _)| k.clone()) else {
+                return;
+            };
+            self.records.remove(&oldest);
+            self.dirty = true;
+        }
+    }
+}
+
+/// Where the daemon keeps its cache unless `--cache-file` says otherwise:
+/// `$XDG_CACHE_HOME/zen/cache.jsonl`, or `~/.cache/zen/cache.jsonl`.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("zen").join("cache.jsonl"))
+}
+
+// One JSON object per file:
+//
+//   {"path":"/usr/bin/tac","dev":2049,"inode":1234,"size":43200,"mtime":[1700000000,0],
+//    "ctime":[1700000000,0],"hash":"3e40...","sig":{"package":{"manager":"dpkg","name":"coreutils",...}}}
+//
+// and on macOS, `"sig":{"team":"EQHXZ8M8AV","authorities":["Developer ID
+// Application: ...",...],"identifier":"com.google.Chrome","runtime":true,
+// "cdhashes":["5619...",...]}`.
+//
+// `hash` and `sig` are left out until looked up; an unreadable file has
+// `"hash":""` and an unsigned one `"sig":false`. JSON numbers only go up to
+// i64, so the u64 fields are stored as their i64 bit pattern.
+
+fn write_record(key: &FileKey, record: &Record) -> String {
+    let string = |s: &str| format!("\"{}\"", json_escape(s));
+    let mut fields = vec![
+        format!("\"path\":{}", string(&record.path)),
+        format!("\"dev\":{}", key.dev as i64),
+        format!("\"inode\":{}", key.inode as i64),
+        format!("\"size\":{}", key.size as i64),
+    ];
+    for (name, time) in [("mtime", key.mtime), ("ctime", key.ctime)] {
+        if let Some((secs, nanos)) = time {
+            fields.push(format!("\"{}\":[{},{}]", name, secs as i64, nanos));
+        }
+    }
+    if let Some(hash) = &record.hash {
+        fields.push(format!("\"hash\":{}", string(hash.as_deref().unwrap_or(""))));
+    }
+    match &record.sig {
+        None => {}
+        Some(None) => fields.push("\"sig\":false".to_string()),
+        Some(Some(sig)) => {
+            let mut sig_fields = Vec::new();
+            if let Some(team) = &sig.team_id {
+                sig_fields.push(format!("\"team\":{}", string(team)));
+            }
+            if !sig.authorities.is_empty() {
+                let names: Vec<String> = sig.authorities.iter().map(|a| string(a)).collect();
+                sig_fields.push(format!("\"authorities\":[{}]", names.join(",")));
+            }
+            if let Some(identifier) = &sig.identifier {
+                sig_fields.push(format!("\"identifier\":{}", string(identifier)));
+            }
+            if let Some(requirement) = &sig.requirement {
+                sig_fields.push(format!("\"requirement\":{}", string(requirement)));
+            }
+            if sig.hardened_runtime {
+                sig_fields.push("\"runtime\":true".to_string());
+            }
+            if sig.adhoc {
+                sig_fields.push("\"adhoc\":true".to_string());
+            }
+            if !sig.cdhashes.is_empty() {
+                let hashes: Vec<String> = sig.cdhashes.iter().map(|h| string(h)).collect();
+                sig_fields.push(format!("\"cdhashes\":[{}]", hashes.join(",")));
+            }
+            if let Some(pkg) = &sig.package {
+                let mut pkg_fields = vec![
+                    format!("\"manager\":{}", string(pkg.manager)),
+                    format!("\"name\":{}", string(&pkg.name)),
+                    format!("\"version\":{}", string(&pkg.version)),
+                ];
+                if let Some(vendor) = &pkg.vendor {
+                    pkg_fields.push(format!("\"vendor\":{}", string(vendor)));
+                }
+                pkg_fields.push(format!("\"tampered\":{}", pkg.tampered));
+                sig_fields.push(format!("\"package\":{{{}}}", pkg_fields.join(",")));
+            }
+            fields.push(format!("\"sig\":{{{}}}", sig_fields.join(",")));
+        }
+    }
+    format!("{{{}}}", fields.join(","))
+}
+
+fn read_record(entries: &[Entry]) -> Result<(FileKey, Record), String> {
+    let int = |key: &str| -> Result<u64, String> {
+        match field(entries, key) {
+            Some(Value::Int(n)) => Ok(*n as u64),
+            _ => Err(format!("missing or bad '{}'", key)),
+        }
+    };
+    let time = |key: &str| -> Result<Option<(u64, u32)>, String> {
+        let bad = || format!("bad '{}'", key);
+        match field(entries, key) {
+            None => Ok(None),
+            Some(Value::Array(parts)) => match parts.as_slice() {
+                [Value::Int(secs), Value::Int(nanos)] => {
+                    Ok(Some((*secs as u64, u32::try_from(*nanos).map_err(|_| bad())?)))
+                }
+                _ => Err(bad()),
+            },
+            Some(_) => Err(bad()),
+        }
+    };
+    let key = FileKey {
+        dev: int("dev")?,
+        inode: int("inode")?,
+        size: int("size")?,
+        mtime: time("mtime")?,
+        ctime: time("ctime")?,
+    };
+    let hash = match field(entries, "hash") {
+        None => None,
+        Some(value) => Some(Some(value.as_str()?.to_string()).filter(|h| !h.is_empty())),
+    };
+    let sig = match field(entries, "sig") {
+        None => None,
+        Some(Value::Bool(false)) => Some(None),
+        Some(Value::Table(sig)) => Some(Some(read_signature(sig)?)),
+        Some(_) => return Err("bad 'sig'".to_string()),
+    };
+    let path = field(entries, "path").ok_or("missing 'path'")?.as_str()?.to_string();
+    Ok((key, Record { path, hash, sig, elf: None, used: 0 }))
+}
+
+fn read_signature(entries: &[Entry]) -> Result<SignatureInfo, String> {
+    let text = |entries: &[Entry], key: &str| -> Result<Option<String>, String> {
+        field(entries, key).map(|v| v.as_str().map(str::to_string)).transpose()
+    };
+    let package = match field(entries, "package") {
+        None => None,
+        Some(Value::Table(pkg)) => {
+            let manager = match text(pkg, "manager")?.as_deref() {
+                Some("dpkg") => "dpkg",
+                Some("rpm") => "rpm",
+                _ => return Err("bad package manager".to_string()),
+            };
+            Some(PackageInfo {
+                manager,
+                name: text(pkg, "name")?.ok_or("missing package name")?,
+                version: text(pkg, "version")?.ok_or("missing package version")?,
+                vendor: text(pkg, "vendor")?,
+                tampered: field(pkg, "tampered").map(Value::as_bool).transpose()?.unwrap_or(false),
+            })
+        }
+        Some(_) => return Err("bad 'package'".to_string()),
+    };
+    let list = |key: &str| -> Result<Vec<String>, String> {
+        match field(entries, key) {
+            None => Ok(Vec::new()),
+            Some(Value::Array(items)) => items.iter().map(|n| n.as_str().map(str::to_string)).collect(),
+            Some(_) => Err(format!("bad '{}'", key)),
+        }
+    };
+    let flag = |key: &str| field(entries, key).map(Value::as_bool).transpose().map(Option::unwrap_or_default);
+    Ok(SignatureInfo {
+        team_id: text(entries, "team")?,
+        authorities: list("authorities")?,
+        identifier: text(entries, "identifier")?,
+        requirement: text(entries, "requirement")?,
+        hardened_runtime: flag("runtime")?,
+        adhoc: flag("adhoc")?,
+        cdhashes: list("cdhashes")?,
+        package,
+    })
+}
+
+fn field<'a>(entries: &'a [Entry], key: &str) -> Option<&'a Value> {
+    entries.iter().find(|e| e.key == key).map(|e| &e.value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("zen-cache-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn replaced_file_is_hashed_again() {
+        let path = temp("replaced");
+        let path_str = path.to_str().unwrap();
+        let mut cache = FileCache::new(DEFAULT_CAPACITY);
+        fs::write(&path, b"abc").unwrap();
+        let first = cache.hash(path_str).map(str::to_string);
+        assert_eq!(first.as_deref(), Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"));
+        let replacement = path.with_extension("new");
+        fs::write(&replacement, b"abcd").unwrap();
+        fs::rename(&replacement, &path).unwrap();
+        assert_eq!(cache.peek_hash(path_str), None);
+        assert_ne!(cache.hash(path_str).map(str::to_string), first);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn rewrite_with_restored_mtime_is_hashed_again() {
+        let path = temp("restored");
+        let path_str = path.to_str().unwrap();
+        fs::write(&path, b"abc").unwrap();
+        let mut cache = FileCache::new(DEFAULT_CAPACITY);
+        let first = cache.hash(path_str).map(str::to_string);
+        let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+        // Same inode, same size, mtime put back: only ctime moves.
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&path, b"xyz").unwrap();
+        fs::File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+        assert_eq!(cache.peek_hash(path_str), None);
+        assert_ne!(cache.hash(path_str).map(str::to_string), first);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saved_cache_loads_back_in_lru_order() {
+        let files: Vec<PathBuf> = (0..3).map(|i| temp(&format!("file{}", i))).collect();
+        for (i, file) in files.iter().enumerate() {
+            fs::write(file, [i as u8]).unwrap();
+        }
+        let names: Vec<&str> = files.iter().map(|f| f.to_str().unwrap()).collect();
+        let saved = temp("saved");
+
+        let mut cache = FileCache::new(2);
+        cache.hash(names[0]);
+        cache.hash(names[1]);
+        cache.hash(names[0]);
+        cache.hash(names[2]); // evicts file1, the least recently used
+        assert!(cache.peek_hash(names[1]).is_none());
+        cache.save(&saved).unwrap();
+
+        // file0 changes on disk, so only file2 survives the load.
+        fs::write(&files[0], b"changed").unwrap();
+        let mut loaded = FileCache::load(&saved, 2).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.peek_hash(names[2]), cache.peek_hash(names[2]));
+        loaded.hash(names[2]);
+        assert!(loaded.stats().describe().contains("hash hits 1/1 (100%)"));
+
+        for file in files.iter().chain([&saved]) {
+            fs::remove_file(file).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_a_cache_others_can_write() {
+        use std::os::unix::fs::PermissionsExt;
+        let saved = temp("writable");
+        fs::write(&saved, format!("{{\"zen-cache\":{}}}\n", VERSION)).unwrap();
+        fs::set_permissions(&saved, fs::Permissions::from_mode(0o600)).unwrap();
+        assert!(FileCache::load(&saved, 2).is_ok());
+        for mode in [0o620, 0o602, 0o666] {
+            fs::set_permissions(&saved, fs::Permissions::from_mode(mode)).unwrap();
+            let err = FileCache::load(&saved, 2).err().unwrap_or_default();
+            assert!(err.ends_with(&format!("writable by group or others (mode {:o}), ignored", mode)), "{}", err);
+        }
+        fs::remove_file(&saved).unwrap();
+    }
+
+    #[test]
+    fn code_signature_round_trips() {
+        let key = FileKey { dev: u64::MAX, inode: 7, size: 3, mtime: Some((1, 2)), ctime: Some((3, 4)) };
+        let sig = SignatureInfo {
+            team_id: Some("ABCDE12345".to_string()),
+            authorities: vec!["Developer ID Application: Zen \"Test\"".to_string(), "Root CA".to_string()],
+            identifier: Some("dev.zen.sample".to_string()),
+            requirement: Some("identifier \"dev.zen.sample\" and anchor apple generic".to_string()),
+            hardened_runtime: true,
+            adhoc: false,
+            cdhashes: vec!["5619489a6c40e0b149604eed4b498c5dc67b48ef".to_string()],
+            package: None,
+        };
+        let record = Record { path: "/x".to_string(), hash: None, sig: Some(Some(sig.clone())), elf: None, used: 0 };
+        let line = write_record(&key, &record);
+        let (read_key, read) = read_record(&json::parse(&line).unwrap()).unwrap();
+        assert!(read_key == key);
+        assert_eq!(format!("{:?}", read.sig), format!("{:?}", Some(Some(sig))));
+    }
+}
@@ -1,6 +1,111 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+use crate::pattern::Pattern;
+use crate::rulefile::{self, Item};
+use crate::schedule;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Name,
+    Path,
+    Hash,
+    Uid,
+    Ppid,
+    Arg,
+    Team,
+    Authority,
+    /// The installed package that owns the executable (dpkg or rpm).
+    Package,
+    Vendor,
+    /// The GNU build-id note of an ELF executable.
+    BuildId,
+}
+
+#[derive(Clone)]
+pub enum Op {
+    /// `key=value`: the whole field equals the value (for `arg`, any one argument).
+    Equals,
+    /// `arg~value`: the joined command line contains the value.
+    Contains,
+    /// `name~` / `path~`: the whole field matches a glob.
+    Glob(Pattern),
+    /// `re:KEY:REGEX`: the field (for `arg`, the joined command line) matches.
+    Regex(Pattern),
+}
+
+/// Which process a condition looks at.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+    Process,
+    /// `parent:KEY=VALUE`: the direct parent in the process tree.
+    Parent,
+    /// `ancestor:KEY=VALUE`: any process above it in the tree.
+    Ancestor,
+}
+
+#[derive(Clone)]
+pub struct Condition {
+    pub scope: Scope,
+    pub field: Field,
+    pub op: Op,
+    pub value: String,
+}
+
+/// A `rule:` line. Matches only when every condition holds.
+#[derive(Clone)]
+pub struct Rule {
+    pub conditions: Vec<Condition>,
+    /// The line as `policy` reports it, e.g. `rule:name=cc & ancestor:name=cargo`.
+    pub text: String,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum LimitAction {
+    Alert,
+    Renice,
+    Quarantine,
+    Kill,
+}
+
+#[derive(Clone, Copy)]
+pub enum RamMax {
+    Bytes(u64),
+    Percent(f64),
+}
+
+/// A `limit:` line: a resource budget for the processes its selector matches.
+#[derive(Clone)]
+pub struct Limit {
+    pub selector: Rule,
+    /// Percent of total CPU, the same scale as `--cpu-threshold`.
+    pub cpu_max: Option<f64>,
+    pub ram_max: Option<RamMax>,
+    pub action: LimitAction,
+}
+
+/// What a `.toml` / `.json` allowlist can say about a rule besides the rule
+/// itself. In the line format only `comment` is set, from the comment lines
+/// right above the rule.
+#[derive(Clone, Default)]
+pub struct Meta {
+    pub comment: Option<String>,
+    pub added_by: Option<String>,
+    /// `YYYY-MM-DD`, local time. From the next day on the rule is ignored.
+    pub expires: Option<String>,
+    /// `low`, `medium`, `high` or `critical`; reported with every match.
+    pub severity: Option<String>,
+}
+
+/// Where an effective rule line came from.
+#[derive(Clone)]
+pub struct Source {
+    pub file: String,
+    pub line: usize,
+    pub text: String,
+    pub meta: Meta,
+}
 
 #[derive(Default, Clone)]
 pub struct Allowlist {
@@ -12,6 +117,32 @@ pub struct Allowlist {
     pub args: Vec<String>,
     pub teams: HashSet<String>,
     pub authorities: HashSet<String>,
+    /// `package:` and `vendor:` rules; they never match a file whose digest
+    /// no longer matches its package.
+    pub packages: HashSet<String>,
+    pub vendors: HashSet<String>,
+    /// `buildid:` rules, lowercase hex. Anyone can stamp a copied build-id on
+    /// a binary, so `--check-config` warns when one is used alone.
+    pub build_ids: HashSet<String>,
+    /// `rule:` conjunctions. A plain `KEY:VALUE` line is the one-condition case.
+    pub rules: Vec<Rule>,
+    /// `limit:` budgets. They never allow a process by themselves.
+    pub limits: Vec<Limit>,
+    /// `quarantine:KEY:VALUE` rules. A match is frozen instead of allowed.
+    pub quarantine: Option<Box<Allowlist>>,
+    /// `deny:KEY:VALUE` rules. A match is never allowed, whatever else matches.
+    pub deny: Option<Box<Allowlist>>,
+    /// Every file read to build this allowlist, the root first.
+    pub files: Vec<PathBuf>,
+    /// The first occurrence of each rule line, in load order.
+    pub sources: Vec<Source>,
+    /// Where each canonical rule (see `apply_rule`) was first defined.
+    pub origins: HashMap<String, Source>,
+    /// Rules left out because their `expires` date has passed.
+    pub expired: Vec<Source>,
+    /// The earliest `expires` among the rules still in force; once it has
+    /// passed the allowlist must be reloaded.
+    pub next_expiry: Option<String>,
 }
 
 impl Allowlist {
@@ -24,70 +155,697 @@ impl Allowlist {
             && self.args.is_empty()
             && self.teams.is_empty()
             && self.authorities.is_empty()
+            && self.packages.is_empty()
+            && self.vendors.is_empty()
+            && self.build_ids.is_empty()
+            && self.rules.is_empty()
+            && self.limits.is_empty()
+            && self.quarantine.is_none()
+            && self.deny.is_none()
+    }
+
+    /// True when any rule, including `deny:` and `quarantine:` ones, needs
+    /// the process tree.
+    pub fn has_lineage(&self) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.conditions.iter().any(|c| c.scope != Scope::Process))
+            || self.quarantine.as_ref().is_some_and(|q| q.has_lineage())
+            || self.deny.as_ref().is_some_and(|d| d.has_lineage())
+    }
+
+    /// True when any rule, including `deny:` and `quarantine:` ones, looks
+    /// at the owning package.
+    pub fn has_package_rules(&self) -> bool {
+        !self.packages.is_empty()
+            || !self.vendors.is_empty()
+            || self
+                .rules
+                .iter()
+                .any(|rule| rule.conditions.iter().any(|c| matches!(c.field, Field::Package | Field::Vendor)))
+            || self.quarantine.as_ref().is_some_and(|q| q.has_package_rules())
+            || self.deny.as_ref().is_some_and(|d| d.has_package_rules())
+    }
+
+    /// The effective rules, one per line with the file and line they came from.
+    pub fn provenance(&self) -> String {
+        let width = self
+            .sources
+            .iter()
+            .map(|s| s.file.len() + s.line.to_string().len() + 1)
+            .max()
+            .unwrap_or(0);
+        let mut out = String::new();
+        for source in &self.sources {
+            let at = format!("{}:{}", source.file, source.line);
+            out.push_str(&format!("  {:<width$}  {}\n", at, source.text, width = width));
+        }
+        out.push_str(&format!("{} rules from {} files\n", self.sources.len(), self.files.len()));
+        if !self.expired.is_empty() {
+            out.push_str(&format!("{} expired rules ignored:\n", self.expired.len()));
+            for source in &self.expired {
+                let at = format!("{}:{}", source.file, source.line);
+                out.push_str(&format!(
+                    "  {:<width$}  {}  (expired {})\n",
+                    at,
+                    source.text,
+                    source.meta.expires.as_deref().unwrap_or("-"),
+                    width = width
+                ));
+            }
+        }
+        out
+    }
+
+    /// True once the earliest `expires` date of a rule in force has passed.
+    pub fn has_expired_rules(&self) -> bool {
+        self.next_expiry.as_ref().is_some_and(|date| *date < schedule::today())
+    }
+}
+
+/// `profiles/NAME.txt` (or `.toml` / `.json`, see `with_extension`), unless
+/// `name` already looks like a path.
+pub fn profile_path(name: &str) -> PathBuf {
+    if name.contains('/') || has_extension(name) {
+        PathBuf::from(name)
+    } else {
+        with_extension(Path::new("profiles"), name)
     }
 }
 
+fn has_extension(name: &str) -> bool {
+    [".txt", ".toml", ".json"].iter().any(|ext| name.ends_with(ext))
+}
+
+/// `dir/NAME.txt`, or `NAME.toml` / `NAME.json` when only that one exists.
+fn with_extension(dir: &Path, name: &str) -> PathBuf {
+    let txt = dir.join(format!("{}.txt", name));
+    if txt.exists() {
+        return txt;
+    }
+    ["toml", "json"]
+        .iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.exists())
+        .unwrap_or(txt)
+}
+
+/// Loads `path` and everything it pulls in with `include:` / `extends:`.
 pub fn load_allowlist(path: &Path) -> Result<Allowlist, String> {
-    let content = fs::read_to_string(path)
-        .map_err(|err| format!("failed to read allowlist {}: {}", path.display(), err))?;
+    let mut allowlist = Allowlist::default();
+    let mut loader = Loader::new();
+    loader.load(path, &mut allowlist)?;
+
+    if allowlist.is_empty() {
+        return Err("allowlist is empty".to_string());
+    }
+
+    Ok(allowlist)
+}
+
+/// Resolves an `include:` / `extends:` target next to the including file:
+/// `base` is `base.txt` (or `.toml` / `.json`) in the same directory,
+/// anything with a `/` or an extension is a path relative to it.
+fn include_path(from: &Path, target: &str) -> PathBuf {
+    let dir = from.parent().unwrap_or(Path::new(""));
+    let target = expand_home(target);
+    if target.contains('/') || has_extension(&target) {
+        dir.join(target)
+    } else {
+        with_extension(dir, &target)
+    }
+}
+
+/// What `--check-config` needs from a load: every rule line, duplicates
+/// included, and every error instead of only the first.
+pub struct Checked {
+    pub lines: Vec<Source>,
+    pub expired: Vec<Source>,
+    /// `(file, line, message)`; line 0 when the error is about a whole file.
+    pub errors: Vec<(String, usize, String)>,
+}
 
+/// Loads like `load_allowlist` but keeps going past bad lines.
+pub fn check_allowlist(path: &Path) -> Checked {
     let mut allowlist = Allowlist::default();
-    for (idx, line) in content.lines().enumerate() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
+    let mut loader = Loader {
+        lenient: true,
+        ..Loader::new()
+    };
+    if let Err(err) = loader.load(path, &mut allowlist) {
+        loader.errors.push((path.display().to_string(), 0, err));
+    }
+    if loader.errors.is_empty() && allowlist.is_empty() {
+        loader.errors.push((path.display().to_string(), 0, "allowlist is empty".to_string()));
+    }
+    Checked {
+        lines: loader.lines,
+        expired: allowlist.expired,
+        errors: loader.errors,
+    }
+}
+
+#[derive(Default)]
+struct Loader {
+    /// Files being loaded, outermost first, to report include cycles.
+    stack: Vec<PathBuf>,
+    /// Files already merged; a file included twice is merged once.
+    done: HashSet<PathBuf>,
+    /// Rule lines already recorded in `Allowlist::sources`.
+    seen: HashSet<String>,
+    /// Record bad lines in `errors` and carry on instead of failing.
+    lenient: bool,
+    errors: Vec<(String, usize, String)>,
+    /// Every rule line in load order, duplicates included.
+    lines: Vec<Source>,
+    /// `YYYY-MM-DD`, to compare `expires` dates against.
+    today: String,
+}
+
+impl Loader {
+    fn new() -> Loader {
+        Loader {
+            today: schedule::today(),
+            ..Loader::default()
         }
+    }
 
+    /// Fails only on the first bad line, or in lenient mode when `path`
+    /// itself cannot be read or closes an include cycle.
+    fn load(&mut self, path: &Path, allowlist: &mut Allowlist) -> Result<(), String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read allowlist {}: {}", path.display(), err))?;
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        if let Some(start) = self.stack.iter().position(|p| *p == canonical) {
+            let chain: Vec<String> = self.stack[start..]
+                .iter()
+                .chain(std::iter::once(&canonical))
+                .map(|p| p.display().to_string())
+                .collect();
+            return Err(format!("include cycle: {}", chain.join(" -> ")));
+        }
+        if !self.done.insert(canonical.clone()) {
+            return Ok(());
+        }
+        allowlist.files.push(path.to_path_buf());
+
+        // Errors inside included files name the file; the root keeps the
+        // plain line-number messages.
+        let nested = !self.stack.is_empty();
+        let items = rulefile::read(path, &content)
+            .map_err(|err| if nested { format!("{}: {}", path.display(), err) } else { err })?;
+        self.stack.push(canonical);
+        let mut extends = false;
+        for item in &items {
+            if let Err(err) = self.line(path, item, &mut extends, allowlist) {
+                if !self.lenient {
+                    return Err(if nested { format!("{}: {}", path.display(), err) } else { err });
+                }
+                self.errors.push((path.display().to_string(), item.line, err));
+            }
+        }
+        self.stack.pop();
+        Ok(())
+    }
+
+    fn line(&mut self, path: &Path, item: &Item, extends: &mut bool, allowlist: &mut Allowlist) -> Result<(), String> {
+        let (line_no, line) = (item.line, item.text.as_str());
         let (key, value) = line
             .split_once(':')
-            .ok_or_else(|| format!("invalid allowlist line {}: {}", idx + 1, line))?;
+            .ok_or_else(|| format!("invalid allowlist line {}: {}", line_no, line))?;
         let key = key.trim().to_lowercase();
         let value = value.trim();
         if value.is_empty() {
-            return Err(format!("empty value on line {}", idx + 1));
+            return Err(format!("empty value on line {}", line_no));
         }
 
-        match key.as_str() {
-            "name" => {
-                allowlist.names.insert(value.to_string());
-            }
-            "path" => {
-                allowlist.paths.insert(value.to_string());
+        if key == "include" || key == "extends" {
+            if key == "extends" && std::mem::replace(extends, true) {
+                return Err(format!("second extends on line {}", line_no));
             }
-            "hash" => {
-                allowlist.hashes.insert(value.to_lowercase());
+            return self.load(&include_path(path, value), allowlist);
+        }
+
+        let source = Source {
+            file: path.display().to_string(),
+            line: line_no,
+            text: format!("{}:{}", key, value),
+            meta: item.meta.clone(),
+        };
+        if let Some(expires) = &item.meta.expires {
+            if *expires < self.today {
+                allowlist.expired.push(source);
+                return Ok(());
             }
-            "uid" => {
-                let uid = value
-                    .parse::<u32>()
-                    .map_err(|_| format!("invalid uid on line {}", idx + 1))?;
-                allowlist.uids.insert(uid);
+            if allowlist.next_expiry.as_ref().is_none_or(|next| expires < next) {
+                allowlist.next_expiry = Some(expires.clone());
             }
-            "ppid" => {
-                let ppid = value
-                    .parse::<u32>()
-                    .map_err(|_| format!("invalid ppid on line {}", idx + 1))?;
-                allowlist.ppids.insert(ppid);
+        }
+        let canonical = apply_rule(allowlist, &key, value, line_no)?;
+        allowlist.origins.entry(canonical).or_insert_with(|| source.clone());
+        if self.lenient {
+            self.lines.push(source.clone());
+        }
+        if self.seen.insert(source.text.clone()) {
+            allowlist.sources.push(source);
+        }
+        Ok(())
+    }
+}
+
+/// The conditions one allow line stands for, so lines of different kinds
+/// can be compared. `None` for lines that allow nothing by themselves.
+pub fn line_conditions(text: &str) -> Option<Vec<Condition>> {
+    let (key, value) = text.split_once(':')?;
+    let field = match key {
+        "name" => Field::Name,
+        "path" => Field::Path,
+        "hash" => Field::Hash,
+        "uid" => Field::Uid,
+        "ppid" => Field::Ppid,
+        "team" => Field::Team,
+        "authority" => Field::Authority,
+        "package" => Field::Package,
+        "vendor" => Field::Vendor,
+        "buildid" => Field::BuildId,
+        "arg" => Field::Arg,
+        "rule" | "parent" | "ancestor" | "name~" | "path~" | "re" => {
+            let mut scratch = Allowlist::default();
+            apply_rule(&mut scratch, key, value, 0).ok()?;
+            return scratch.rules.pop().map(|rule| rule.conditions);
+        }
+        _ => return None,
+    };
+    let (op, value) = match field {
+        Field::Path => (Op::Equals, expand_home(value)),
+        Field::Hash | Field::BuildId => (Op::Equals, value.to_lowercase()),
+        Field::Team | Field::Authority => (Op::Equals, value.split('#').next().unwrap_or(value).trim().to_string()),
+        // A plain `arg:` searches the joined command line.
+        Field::Arg => (Op::Contains, value.to_string()),
+        _ => (Op::Equals, value.to_string()),
+    };
+    Some(vec![Condition {
+        scope: Scope::Process,
+        field,
+        op,
+        value,
+    }])
+}
+
+/// Adds one `KEY:VALUE` line and returns its canonical text, the form
+/// `policy` reports when the line matches.
+fn apply_rule(allowlist: &mut Allowlist, key: &str, value: &str, line_no: usize) -> Result<String, String> {
+    let canonical = match key {
+        "name" => {
+            allowlist.names.insert(value.to_string());
+            format!("name:{}", value)
+        }
+        "path" => {
+            let path = expand_home(value);
+            allowlist.paths.insert(path.clone());
+            format!("path:{}", path)
+        }
+        "name~" | "path~" => {
+            let (field, value) = if key == "name~" {
+                (Field::Name, value.to_string())
+            } else {
+                (Field::Path, expand_home(value))
+            };
+            let glob = Pattern::glob(&value).map_err(|e| format!("{} on line {}", e, line_no))?;
+            let text = format!("{}:{}", key, value);
+            allowlist.rules.push(Rule {
+                conditions: vec![Condition {
+                    scope: Scope::Process,
+                    field,
+                    op: Op::Glob(glob),
+                    value,
+                }],
+                text: text.clone(),
+            });
+            text
+        }
+        "re" => {
+            let (field, regex) = value
+                .split_once(':')
+                .ok_or_else(|| format!("invalid re rule on line {}", line_no))?;
+            let field_name = field.trim().to_lowercase();
+            let field = match field_name.as_str() {
+                "name" => Field::Name,
+                "path" => Field::Path,
+                "arg" => Field::Arg,
+                other => return Err(format!("re: only supports name, path and arg on line {}: {}", line_no, other)),
+            };
+            let regex = regex.trim();
+            let compiled = Pattern::regex(regex).map_err(|e| format!("{} on line {}", e, line_no))?;
+            let text = format!("re:{}:{}", field_name, regex);
+            allowlist.rules.push(Rule {
+                conditions: vec![Condition {
+                    scope: Scope::Process,
+                    field,
+                    op: Op::Regex(compiled),
+                    value: regex.to_string(),
+                }],
+                text: text.clone(),
+            });
+            text
+        }
+        "hash" => {
+            let hash = value.to_lowercase();
+            allowlist.hashes.insert(hash.clone());
+            format!("hash:{}", hash)
+        }
+        "uid" => {
+            let uid = value
+                .parse::<u32>()
+                .map_err(|_| format!("invalid uid on line {}", line_no))?;
+            allowlist.uids.insert(uid);
+            format!("uid:{}", uid)
+        }
+        "ppid" => {
+            let ppid = value
+                .parse::<u32>()
+                .map_err(|_| format!("invalid ppid on line {}", line_no))?;
+            allowlist.ppids.insert(ppid);
+            format!("ppid:{}", ppid)
+        }
+        "arg" => {
+            allowlist.args.push(value.to_string());
+            format!("arg:{}", value)
+        }
+        "team" => {
+            // If there's a comment like team:EQHXZ8M8AV # Google, strip it
+            let v = value.split('#').next().unwrap_or(value).trim();
+            allowlist.teams.insert(v.to_string());
+            format!("team:{}", v)
+        }
+        "authority" => {
+            let v = value.split('#').next().unwrap_or(value).trim();
+            allowlist.authorities.insert(v.to_string());
+            format!("authority:{}", v)
+        }
+        "package" => {
+            allowlist.packages.insert(value.to_string());
+            format!("package:{}", value)
+        }
+        "vendor" => {
+            allowlist.vendors.insert(value.to_string());
+            format!("vendor:{}", value)
+        }
+        "buildid" => {
+            let id = value.to_lowercase();
+            allowlist.build_ids.insert(id.clone());
+            format!("buildid:{}", id)
+        }
+        "rule" => {
+            let rule = parse_rule(value, line_no)?;
+            let text = rule.text.clone();
+            allowlist.rules.push(rule);
+            text
+        }
+        "limit" => {
+            let limit = parse_limit(value, line_no)?;
+            let text = format!("limit:{}", value);
+            allowlist.limits.push(limit);
+            text
+        }
+        "parent" | "ancestor" => {
+            let condition = parse_condition(&format!("{}:{}", key, value), line_no)?;
+            let text = format!("{}:{}", key, value);
+            allowlist.rules.push(Rule {
+                conditions: vec![condition],
+                text: text.clone(),
+            });
+            text
+        }
+        "quarantine" => {
+            let target = allowlist.quarantine.get_or_insert_with(Box::default);
+            format!("quarantine:{}", apply_nested(target, key, value, line_no)?)
+        }
+        "deny" => {
+            let target = allowlist.deny.get_or_insert_with(Box::default);
+            format!("deny:{}", apply_nested(target, key, value, line_no)?)
+        }
+        _ => return Err(format!("unknown key on line {}: {}", line_no, key)),
+    };
+    Ok(canonical)
+}
+
+/// Parses `name=node & path=/usr/local/bin/node & ancestor:name=cargo`.
+fn parse_rule(value: &str, line_no: usize) -> Result<Rule, String> {
+    let conditions = value
+        .split('&')
+        .map(|part| parse_condition(part, line_no))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Rule {
+        conditions,
+        text: format!("rule:{}", value),
+    })
+}
+
+/// Parses `name=Google Chrome & cpu_max=80% & ram_max=500MB & action=renice`.
+/// `cpu_max`, `ram_max` and `action` set the budget; every other term is a
+/// selector condition as in `rule:`.
+fn parse_limit(value: &str, line_no: usize) -> Result<Limit, String> {
+    let mut conditions = Vec::new();
+    let mut cpu_max = None;
+    let mut ram_max = None;
+    let mut action = LimitAction::Alert;
+    for part in value.split('&') {
+        let (key, raw) = part.split_once('=').unwrap_or(("", ""));
+        let raw = raw.trim();
+        match key.trim().to_lowercase().as_str() {
+            "cpu_max" => {
+                let pct = raw.trim_end_matches('%').trim().parse::<f64>().ok().filter(|v| *v > 0.0);
+                cpu_max = Some(pct.ok_or_else(|| format!("invalid cpu_max on line {}", line_no))?);
             }
-            "arg" => {
-                allowlist.args.push(value.to_string());
+            "ram_max" => {
+                ram_max = Some(parse_ram(raw).ok_or_else(|| format!("invalid ram_max on line {}", line_no))?);
             }
-            "team" => {
-                // If there's a comment like team:EQHXZ8M8AV # Google, strip it
-                let v = value.split('#').next().unwrap_or(value).trim();
-                allowlist.teams.insert(v.to_string());
+            "action" => {
+                action = match raw.to_lowercase().as_str() {
+                    "alert" => LimitAction::Alert,
+                    "renice" => LimitAction::Renice,
+                    "quarantine" => LimitAction::Quarantine,
+                    "kill" => LimitAction::Kill,
+                    _ => return Err(format!("invalid action on line {}: {}", line_no, raw)),
+                };
             }
-            "authority" => {
-                let v = value.split('#').next().unwrap_or(value).trim();
-                allowlist.authorities.insert(v.to_string());
+            _ => conditions.push(parse_condition(part, line_no)?),
+        }
+    }
+    if cpu_max.is_none() && ram_max.is_none() {
+        return Err(format!("limit needs cpu_max or ram_max on line {}", line_no));
+    }
+    if conditions.is_empty() {
+        return Err(format!("limit needs at least one condition on line {}", line_no));
+    }
+    Ok(Limit {
+        selector: Rule {
+            conditions,
+            text: format!("limit:{}", value),
+        },
+        cpu_max,
+        ram_max,
+        action,
+    })
+}
+
+/// `20%`, `500MB`, `2G`, `512k` or plain bytes.
+fn parse_ram(raw: &str) -> Option<RamMax> {
+    let raw = raw.trim();
+    if let Some(pct) = raw.strip_suffix('%') {
+        return pct.trim().parse().ok().filter(|v: &f64| *v > 0.0).map(RamMax::Percent);
+    }
+    let upper = raw.to_uppercase();
+    let digits = upper.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let factor: u64 = match upper[digits.len()..].trim_end_matches('B') {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    let n: u64 = digits.trim().parse().ok()?;
+    n.checked_mul(factor).filter(|v| *v > 0).map(RamMax::Bytes)
+}
+
+/// Parses one `[parent:|ancestor:]KEY=VALUE` or `KEY~VALUE` condition.
+fn parse_condition(part: &str, line_no: usize) -> Result<Condition, String> {
+    let part = part.trim();
+    let split = part
+        .find(['=', '~'])
+        .ok_or_else(|| format!("invalid condition on line {}: {}", line_no, part))?;
+    let key = part[..split].trim().to_lowercase();
+    let (scope, key) = match key.split_once(':') {
+        Some(("parent", key)) => (Scope::Parent, key.trim()),
+        Some(("ancestor", key)) => (Scope::Ancestor, key.trim()),
+        Some((scope, _)) => return Err(format!("unknown scope on line {}: {}", line_no, scope)),
+        None => (Scope::Process, key.as_str()),
+    };
+    let equals = part[split..].starts_with('=');
+    let raw = part[split + 1..].trim();
+    if raw.is_empty() {
+        return Err(format!("empty value on line {}", line_no));
+    }
+
+    let field = match key {
+        "name" => Field::Name,
+        "path" => Field::Path,
+        "hash" => Field::Hash,
+        "uid" => Field::Uid,
+        "ppid" => Field::Ppid,
+        "arg" => Field::Arg,
+        "team" => Field::Team,
+        "authority" => Field::Authority,
+        "package" => Field::Package,
+        "vendor" => Field::Vendor,
+        "buildid" => Field::BuildId,
+        _ => return Err(format!("unknown key on line {}: {}", line_no, key)),
+    };
+    let value = match field {
+        Field::Path => expand_home(raw),
+        Field::Uid | Field::Ppid => {
+            if !equals {
+                return Err(format!("{} only supports = on line {}", key, line_no));
             }
-            _ => return Err(format!("unknown key on line {}: {}", idx + 1, key)),
+            raw.parse::<u32>()
+                .map_err(|_| format!("invalid {} on line {}", key, line_no))?
+                .to_string()
+        }
+        Field::Hash | Field::BuildId => raw.to_lowercase(),
+        _ => raw.to_string(),
+    };
+    let op = match field {
+        _ if equals => Op::Equals,
+        Field::Name | Field::Path => {
+            Op::Glob(Pattern::glob(&value).map_err(|e| format!("{} on line {}", e, line_no))?)
+        }
+        Field::Arg => Op::Contains,
+        // Identities are all or nothing: `team~E` would allow every team
+        // with an E in it.
+        _ => return Err(format!("{} only supports = on line {}", key, line_no)),
+    };
+    Ok(Condition { scope, field, op, value })
+}
+
+/// Expands a leading `~` and every `$HOME` using the daemon's own `HOME`.
+fn expand_home(value: &str) -> String {
+    let Ok(home) = std::env::var("HOME") else {
+        return value.to_string();
+    };
+    let home = home.trim_end_matches('/');
+    let value = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", home, rest),
+        _ => value.to_string(),
+    };
+    value.replace("$HOME", home)
+}
+
+/// Applies the `KEY:VALUE` part of a `quarantine:` or `deny:` line.
+fn apply_nested(target: &mut Allowlist, kind: &str, value: &str, line_no: usize) -> Result<String, String> {
+    let (inner_key, inner_value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("invalid {} rule on line {}", kind, line_no))?;
+    let inner_key = inner_key.trim().to_lowercase();
+    if inner_key == "limit" {
+        return Err(format!("limit cannot be nested in {} on line {}", kind, line_no));
+    }
+    if inner_key == "quarantine" || inner_key == "deny" {
+        return Err(format!("nested {} on line {}", inner_key, line_no));
+    }
+    if inner_value.trim().is_empty() {
+        return Err(format!("empty value on line {}", line_no));
+    }
+    apply_rule(target, &inner_key, inner_value.trim(), line_no)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A scratch directory holding `files`, removed by the caller.
+    fn profiles(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("zen-config-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
         }
+        dir
     }
 
-    if allowlist.is_empty() {
-        return Err("allowlist is empty".to_string());
+    #[test]
+    fn includes_merge_once_with_provenance() {
+        let dir = profiles(
+            "include",
+            &[
+                ("base.txt", "name:zsh\nname:vim\n"),
+                ("extra.toml", "[[rules]]\nmatch = \"name:htop\"\n"),
+                ("dev.txt", "extends: base\ninclude: extra.toml\ninclude: base.txt\nname:zsh\nname:cargo\n"),
+            ],
+        );
+        let allowlist = load_allowlist(&dir.join("dev.txt")).unwrap();
+        assert_eq!(allowlist.files.len(), 3);
+        for name in ["zsh", "vim", "htop", "cargo"] {
+            assert!(allowlist.names.contains(name), "{}", name);
+        }
+        // `name:zsh` is reported where it first appeared, in base.txt.
+        let zsh = allowlist.sources.iter().find(|s| s.text == "name:zsh").unwrap();
+        assert!(zsh.file.ends_with("base.txt") && zsh.line == 1);
+        assert_eq!(allowlist.sources.len(), 4);
+        fs::remove_dir_all(&dir).unwrap();
     }
 
-    Ok(allowlist)
+    #[test]
+    fn include_cycles_and_errors_name_the_file() {
+        let dir = profiles(
+            "cycle",
+            &[
+                ("a.txt", "name:a\ninclude: b\n"),
+                ("b.txt", "name:b\ninclude: a\n"),
+                ("bad.txt", "name:ok\nbogus:1\n"),
+                ("root.txt", "include: bad\n"),
+                ("ok.txt", "name:ok\n"),
+                ("twice.txt", "extends: ok\nextends: bad\n"),
+            ],
+        );
+        let err = load_allowlist(&dir.join("a.txt")).err().unwrap();
+        assert!(err.contains("include cycle:") && err.ends_with("a.txt"), "{}", err);
+        assert!(err.contains("b.txt -> "), "{}", err);
+        let err = load_allowlist(&dir.join("root.txt")).err().unwrap();
+        assert!(err.contains("bad.txt: unknown key on line 2: bogus"), "{}", err);
+        let err = load_allowlist(&dir.join("twice.txt")).err().unwrap();
+        assert_eq!(err, "second extends on line 2");
+        assert!(load_allowlist(&dir.join("missing.txt")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn check_collects_every_error() {
+        let dir = profiles("check", &[("many.txt", "name:zsh\nbogus:1\nuid:abc\nname:zsh\ninclude: nowhere\n")]);
+        let checked = check_allowlist(&dir.join("many.txt"));
+        let lines: Vec<usize> = checked.errors.iter().map(|(_, line, _)| *line).collect();
+        assert_eq!(lines, vec![2, 3, 5]);
+        // Duplicates are kept for the linter.
+        assert_eq!(checked.lines.len(), 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn identity_conditions_reject_contains() {
+        for key in ["hash", "buildid", "team", "authority", "package", "vendor", "uid", "ppid"] {
+            let err = parse_rule(&format!("name=cc & {}~1", key), 7).err();
+            assert_eq!(err, Some(format!("{} only supports = on line 7", key)), "{}", key);
+        }
+        assert!(parse_rule("team=EQHXZ8M8AV & vendor=Debian", 1).is_ok());
+    }
+
+    #[test]
+    fn contains_and_globs_where_they_apply() {
+        let rule = parse_rule("name~node* & path~/usr/**/node & arg~--inspect", 1).unwrap();
+        assert!(matches!(rule.conditions[0].op, Op::Glob(_)));
+        assert!(matches!(rule.conditions[1].op, Op::Glob(_)));
+        assert!(matches!(rule.conditions[2].op, Op::Contains));
+        assert!(parse_rule("ancestor:team~EQ", 1).is_err());
+    }
 }
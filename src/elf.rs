@@ -0,0 +1,350 @@
+use std::fs::File;
+use std::io::{Read, Seek};
+
+use crate::binary::{self, c_string, read_at, MAX_PHNUM, MAX_SEGMENT};
+
+const PT_LOAD: u32 = 1;
+const PT_DYNAMIC: u32 = 2;
+const PT_INTERP: u32 = 3;
+const PT_NOTE: u32 = 4;
+const PT_GNU_STACK: u32 = 0x6474_e551;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const DT_NEEDED: u64 = 1;
+const DT_STRTAB: u64 = 5;
+const DT_STRSZ: u64 = 10;
+const DT_RPATH: u64 = 15;
+const DT_RUNPATH: u64 = 29;
+const NT_GNU_BUILD_ID: u32 = 3;
+
+/// Directories a distribution installs its dynamic loaders in.
+const LOADER_DIRS: [&str; 7] = ["/lib", "/lib64", "/lib32", "/libx32", "/usr/lib", "/usr/lib64", "/usr/lib32"];
+
+/// What an ELF executable says about itself. The build-id the linker stamps
+/// into it names one build, but unlike a macOS Team ID nothing signs it:
+/// whoever links a binary picks its id, so it is not a trust signal.
+#[derive(Clone, Debug, Default)]
+pub struct ElfInfo {
+    /// The GNU build-id note as lowercase hex.
+    pub build_id: Option<String>,
+    /// `PT_INTERP`, the dynamic loader; `None` for static executables.
+    pub interpreter: Option<String>,
+    /// `DT_NEEDED` libraries, in link order.
+    pub needed: Vec<String>,
+    pub rpath: Vec<String>,
+    pub runpath: Vec<String>,
+    pub traits: Vec<Trait>,
+}
+
+/// Header features rarely seen in binaries a distribution ships.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trait {
+    /// A segment that is both writable and executable, stack included.
+    RwxSegment,
+    /// No section header table, as left behind by packers such as UPX.
+    NoSectionHeaders,
+    /// A loader outside the distribution's library directories.
+    NonstandardInterpreter,
+}
+
+impl Trait {
+    /// The tag used in exports.
+    pub fn tag(self) -> &'static str {
+        match self {
+            Trait::RwxSegment => "rwx-segment",
+            Trait::NoSectionHeaders => "no-section-headers",
+            Trait::NonstandardInterpreter => "nonstandard-interpreter",
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            Trait::RwxSegment => "segmento escribible y ejecutable",
+            Trait::NoSectionHeaders => "sin section headers (¿empaquetado?)",
+            Trait::NonstandardInterpreter => "intérprete no estándar",
+        }
+    }
+}
+
+/// The ELF identity of the file at `path`, or `None` when it is not an ELF
+/// file or cannot be read.
+pub fn inspect(path: &str) -> Option<ElfInfo> {
+    let mut file = File::open(path).ok()?;
+    parse(&mut file).ok()
+}
+
+/// Field widths and byte order from `e_ident`.
+#[derive(Clone, Copy)]
+struct Layout {
+    wide: bool,
+    big_endian: bool,
+}
+
+impl Layout {
+    fn u16(self, b: &[u8], at: usize) -> u64 {
+        u64::from(binary::u16_at(b, at, self.big_endian))
+    }
+
+    fn u32(self, b: &[u8], at: usize) -> u64 {
+        u64::from(binary::u32_at(b, at, self.big_endian))
+    }
+
+    fn u64(self, b: &[u8], at: usize) -> u64 {
+        binary::u64_at(b, at, self.big_endian)
+    }
+
+    /// A 32-bit field in ELF32 and a 64-bit one in ELF64.
+    fn word(self, b: &[u8], at32: usize, at64: usize) -> u64 {
+        if self.wide { self.u64(b, at64) } else { self.u32(b, at32) }
+    }
+}
+
+struct Segment {
+    kind: u32,
+    flags: u32,
+    offset: u64,
+    vaddr: u64,
+    filesz: u64,
+    align: u64,
+}
+
+pub fn parse<R: Read + Seek>(file: &mut R) -> Result<ElfInfo, String> {
+    let header = read_at(file, 0, 64, MAX_SEGMENT).or_else(|_| read_at(file, 0, 52, MAX_SEGMENT))?;
+    if !header.starts_with(b"\x7fELF") {
+        return Err("not an ELF file".to_string());
+    }
+    let layout = Layout {
+        wide: match header[4] {
+            1 => false,
+            2 => true,
+            _ => return Err("unknown ELF class".to_string()),
+        },
+        big_endian: match header[5] {
+            1 => false,
+            2 => true,
+            _ => return Err("unknown ELF byte order".to_string()),
+        },
+    };
+    if layout.wide && header.len() < 64 {
+        return Err("truncated ELF header".to_string());
+    }
+    let (phoff, shoff) = (layout.word(&header, 0x1c, 0x20), layout.word(&header, 0x20, 0x28));
+    let (phentsize, phnum, shnum) = if layout.wide {
+        (layout.u16(&header, 0x36), layout.u16(&header, 0x38), layout.u16(&header, 0x3c))
+    } else {
+        (layout.u16(&header, 0x2a), layout.u16(&header, 0x2c), layout.u16(&header, 0x30))
+    };
+    // Checked even without program headers: `chunks_exact(0)` panics.
+    let min_entsize = if layout.wide { 56 } else { 32 };
+    if phnum as usize > MAX_PHNUM || phentsize < min_entsize {
+        return Err("bad program header table".to_string());
+    }
+
+    let table = read_at(file, phoff, phentsize * phnum, MAX_SEGMENT)?;
+    let segments: Vec<Segment> = table
+        .chunks_exact(phentsize as usize)
+        .map(|ph| Segment {
+            kind: layout.u32(ph, 0) as u32,
+            flags: layout.u32(ph, if layout.wide { 4 } else { 24 }) as u32,
+            offset: layout.word(ph, 4, 8),
+            vaddr: layout.word(ph, 8, 16),
+            filesz: layout.word(ph, 16, 32),
+            align: layout.word(ph, 28, 48),
+        })
+        .collect();
+
+    let mut info = ElfInfo::default();
+    for segment in &segments {
+        match segment.kind {
+            PT_INTERP => {
+                let bytes = read_at(file, segment.offset, segment.filesz, MAX_SEGMENT)?;
+                info.interpreter = c_string(&bytes, 0);
+            }
+            PT_NOTE if info.build_id.is_none() => {
+                let notes = read_at(file, segment.offset, segment.filesz, MAX_SEGMENT)?;
+                info.build_id = build_id(layout, &notes, segment.align);
+            }
+            PT_DYNAMIC => read_dynamic(file, layout, segment, &segments, &mut info)?,
+            _ => {}
+        }
+    }
+
+    let writable_code = |s: &Segment| matches!(s.kind, PT_LOAD | PT_GNU_STACK) && s.flags & (PF_W | PF_X) == PF_W | PF_X;
+    if segments.iter().any(writable_code) {
+        info.traits.push(Trait::RwxSegment);
+    }
+    if shoff == 0 || shnum == 0 {
+        info.traits.push(Trait::NoSectionHeaders);
+    }
+    if info.interpreter.as_deref().is_some_and(|interp| !is_standard_interpreter(interp)) {
+        info.traits.push(Trait::NonstandardInterpreter);
+    }
+    Ok(info)
+}
+
+/// The descriptor of the `GNU` build-id note, if `notes` has one.
+fn build_id(layout: Layout, notes: &[u8], align: u64) -> Option<String> {
+    let align = if align == 8 { 8 } else { 4 };
+    let pad = |n: usize| n.div_ceil(align) * align;
+    let mut at = 0;
+    while at + 12 <= notes.len() {
+        let namesz = layout.u32(notes, at) as usize;
+        let descsz = layout.u32(notes, at + 4) as usize;
+        let kind = layout.u32(notes, at + 8) as u32;
+        let name_at = at + 12;
+        let desc_at = name_at.checked_add(pad(namesz))?;
+        let end = desc_at.checked_add(pad(descsz))?;
+        if desc_at + descsz > notes.len() {
+            return None;
+        }
+        if kind == NT_GNU_BUILD_ID && &notes[name_at..name_at + namesz] == b"GNU\0" {
+            return Some(notes[desc_at..desc_at + descsz].iter().map(|b| format!("{:02x}", b)).collect());
+        }
+        at = end;
+    }
+    None
+}
+
+fn read_dynamic<R: Read + Seek>(
+    file: &mut R,
+    layout: Layout,
+    dynamic: &Segment,
+    segments: &[Segment],
+    info: &mut ElfInfo,
+) -> Result<(), String> {
+    let table = read_at(file, dynamic.offset, dynamic.filesz, MAX_SEGMENT)?;
+    let size = if layout.wide { 16 } else { 8 };
+    let mut entries = Vec::new();
+    for entry in table.chunks_exact(size) {
+        let tag = layout.word(entry, 0, 0);
+        if tag == 0 {
+            break;
+        }
+        entries.push((tag, layout.word(entry, 4, 8)));
+    }
+    let lookup = |wanted: u64| entries.iter().find(|(tag, _)| *tag == wanted).map(|(_, value)| *value);
+    let (Some(strtab), Some(strsz)) = (lookup(DT_STRTAB), lookup(DT_STRSZ)) else {
+        return Ok(());
+    };
+    // DT_STRTAB is an address; the loadable segment that maps it gives the
+    // file offset.
+    let Some(offset) = segments
+        .iter()
+        .find(|s| s.kind == PT_LOAD && (s.vaddr..s.vaddr.saturating_add(s.filesz)).contains(&strtab))
+        .map(|s| s.offset + (strtab - s.vaddr))
+    else {
+        return Ok(());
+    };
+    let strings = read_at(file, offset, strsz, MAX_SEGMENT)?;
+    for (tag, value) in &entries {
+        let Some(string) = c_string(&strings, *value as usize) else {
+            continue;
+        };
+        match *tag {
+            DT_NEEDED => info.needed.push(string),
+            DT_RPATH => info.rpath.extend(string.split(':').map(str::to_string)),
+            DT_RUNPATH => info.runpath.extend(string.split(':').map(str::to_string)),
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// glibc (`ld-linux*.so.*`, `ld.so.*`, `ld64.so.*`) or musl
+/// (`ld-musl-*.so.1`) loaders in a system library directory.
+fn is_standard_interpreter(interp: &str) -> bool {
+    let Some((dir, file)) = interp.rsplit_once('/') else {
+        return false;
+    };
+    LOADER_DIRS.contains(&dir)
+        && ["ld-linux", "ld-musl-", "ld.so.", "ld64.so."].iter().any(|prefix| file.starts_with(prefix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A little-endian ELF64 with an RWX load segment, an interpreter, a
+    /// build-id note and a dynamic section, and no section headers.
+    fn sample(interp: &str) -> Vec<u8> {
+        let mut strtab = b"\0libc.so.6\0libz.so.1\0$ORIGIN/../lib:/opt/x\0".to_vec();
+        strtab.resize(48, 0);
+        let interp = format!("{}\0", interp).into_bytes();
+        let note = [&4u32.to_le_bytes()[..], &4u32.to_le_bytes(), &3u32.to_le_bytes(), b"GNU\0", &[0xde, 0xad, 0xbe, 0xef]].concat();
+        let dynamic: Vec<u8> = [(1u64, 1u64), (1, 11), (29, 21), (5, 0x1000 + 0x200), (10, 48), (0, 0)]
+            .iter()
+            .flat_map(|(tag, val)| [tag.to_le_bytes(), val.to_le_bytes()].concat())
+            .collect();
+
+        let mut elf = vec![0u8; 0x300];
+        elf[..4].copy_from_slice(b"\x7fELF");
+        elf[4] = 2;
+        elf[5] = 1;
+        elf[0x20..0x28].copy_from_slice(&64u64.to_le_bytes()); // e_phoff
+        elf[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+        elf[0x38..0x3a].copy_from_slice(&4u16.to_le_bytes());
+        let phdr = |kind: u32, flags: u32, offset: u64, vaddr: u64, size: u64, align: u64| {
+            [
+                &kind.to_le_bytes()[..],
+                &flags.to_le_bytes(),
+                &offset.to_le_bytes(),
+                &vaddr.to_le_bytes(),
+                &vaddr.to_le_bytes(),
+                &size.to_le_bytes(),
+                &size.to_le_bytes(),
+                &align.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let headers = [
+            phdr(PT_LOAD, 7, 0, 0x1000, 0x300, 0x1000),
+            phdr(PT_INTERP, 4, 0x120, 0x1120, interp.len() as u64, 1),
+            phdr(PT_NOTE, 4, 0x180, 0x1180, note.len() as u64, 4),
+            phdr(PT_DYNAMIC, 6, 0x240, 0x1240, dynamic.len() as u64, 8),
+        ]
+        .concat();
+        elf[64..64 + headers.len()].copy_from_slice(&headers);
+        elf[0x120..0x120 + interp.len()].copy_from_slice(&interp);
+        elf[0x180..0x180 + note.len()].copy_from_slice(&note);
+        elf[0x200..0x200 + strtab.len()].copy_from_slice(&strtab);
+        elf[0x240..0x240 + dynamic.len()].copy_from_slice(&dynamic);
+        elf
+    }
+
+    #[test]
+    fn reads_identity_and_traits() {
+        let info = parse(&mut Cursor::new(sample("/tmp/.x/ld.so"))).unwrap();
+        assert_eq!(info.build_id.as_deref(), Some("deadbeef"));
+        assert_eq!(info.interpreter.as_deref(), Some("/tmp/.x/ld.so"));
+        assert_eq!(info.needed, ["libc.so.6", "libz.so.1"]);
+        assert_eq!(info.runpath, ["$ORIGIN/../lib", "/opt/x"]);
+        assert!(info.rpath.is_empty());
+        assert_eq!(
+            info.traits,
+            [Trait::RwxSegment, Trait::NoSectionHeaders, Trait::NonstandardInterpreter]
+        );
+
+        let info = parse(&mut Cursor::new(sample("/lib64/ld-linux-x86-64.so.2"))).unwrap();
+        assert!(!info.traits.contains(&Trait::NonstandardInterpreter));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse(&mut Cursor::new(b"#!/bin/sh\necho hi\n".to_vec())).is_err());
+        let mut truncated = sample("/lib/ld-linux.so.2");
+        truncated.truncate(100);
+        assert!(parse(&mut Cursor::new(truncated)).is_err());
+    }
+
+    #[test]
+    fn rejects_short_program_header_entries() {
+        for (phentsize, phnum) in [(0u16, 0u16), (0, 4), (55, 4)] {
+            let mut elf = sample("/lib/ld-linux.so.2");
+            elf[0x36..0x38].copy_from_slice(&phentsize.to_le_bytes());
+            elf[0x38..0x3a].copy_from_slice(&phnum.to_le_bytes());
+            let result = parse(&mut Cursor::new(elf));
+            assert_eq!(result.err().as_deref(), Some("bad program header table"), "{} x {}", phentsize, phnum);
+        }
+    }
+}
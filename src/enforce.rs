@@ -0,0 +1,194 @@
+use std::time::{Duration, Instant};
+
+use crate::platform::{KillOutcome, ProcHandle, ProcessInfo, ProcessSource};
+
+/// One audited step of the ladder for one PID.
+pub struct Step<'a> {
+    pub info: &'a ProcessInfo,
+    pub action: &'static str,
+    pub verified: Option<bool>,
+}
+
+/// A target that got SIGTERM and gets SIGKILL if it is still alive once
+/// `deadline` passes. The daemon keeps these between scans instead of
+/// waiting for them.
+pub struct Pending {
+    pub info: ProcessInfo,
+    pub handle: ProcHandle,
+    pub deadline: Instant,
+    /// The allowlist line behind the verdict, audited with the last step.
+    pub rule: Option<String>,
+}
+
+/// How a pending target ended: it exited on its own or was escalated.
+pub struct Resolved {
+    pub pending: Pending,
+    pub action: &'static str,
+    pub verified: Option<bool>,
+}
+
+/// Runs the enforcement ladder over `targets` in the given order (callers
+/// pass children first). With `grace_ms` every target gets SIGTERM and is
+/// added to `pending` for `escalate` to finish; without it SIGKILL is sent
+/// right away. Every target is pinned before the first signal.
+pub fn run<'a>(
+    source: &dyn ProcessSource,
+    targets: &[&'a ProcessInfo],
+    grace_ms: Option<u64>,
+    rule: Option<&str>,
+    pending: &mut Vec<Pending>,
+) -> Vec<Step<'a>> {
+    let mut steps = Vec::new();
+    let mut pinned: Vec<(&ProcessInfo, ProcHandle)> = Vec::new();
+    for &info in targets {
+        match source.pin(info) {
+            Ok(handle) => pinned.push((info, handle)),
+            Err(_) => steps.push(step(info, "kill-failed", Some(false))),
+        }
+    }
+
+    let Some(grace) = grace_ms else {
+        for (info, handle) in pinned {
+            let (action, verified) = kill(source, &handle);
+            steps.push(step(info, action, verified));
+        }
+        return steps;
+    };
+
+    let deadline = Instant::now() + Duration::from_millis(grace);
+    for (info, handle) in pinned {
+        match source.terminate_pinned(&handle) {
+            Ok(KillOutcome::IdentityChanged) => steps.push(step(info, "kill-skipped", Some(false))),
+            Ok(outcome) => {
+                steps.push(step(info, "sigterm", Some(matches!(outcome, KillOutcome::Verified))));
+                pending.push(Pending {
+                    info: info.clone(),
+                    handle,
+                    deadline,
+                    rule: rule.map(str::to_string),
+                });
+            }
+            Err(_) => steps.push(step(info, "kill-failed", Some(false))),
+        }
+    }
+    steps
+}
+
+/// Resolves the pending targets that exited or whose grace period ended;
+/// the rest stay pending.
+pub fn escalate(source: &dyn ProcessSource, pending: &mut Vec<Pending>) -> Vec<Resolved> {
+    resolve(source, pending, false)
+}
+
+/// Resolves every pending target now, SIGKILLing survivors whatever their
+/// deadline. Used when the daemon exits.
+pub fn finish(source: &dyn ProcessSource, pending: &mut Vec<Pending>) -> Vec<Resolved> {
+    resolve(source, pending, true)
+}
+
+fn resolve(source: &dyn ProcessSource, pending: &mut Vec<Pending>, force: bool) -> Vec<Resolved> {
+    let now = Instant::now();
+    let mut resolved = Vec::new();
+    for target in std::mem::take(pending) {
+        let (action, verified) = if !source.is_alive(&target.handle) {
+            ("terminated", Some(target.handle.fd.is_some()))
+        } else if force || now >= target.deadline {
+            kill(source, &target.handle)
+        } else {
+            pending.push(target);
+            continue;
+        };
+        resolved.push(Resolved {
+            pending: target,
+            action,
+            verified,
+        });
+    }
+    resolved
+}
+
+fn kill(source: &dyn ProcessSource, handle: &ProcHandle) -> (&'static str, Option<bool>) {
+    match source.kill_pinned(handle) {
+        Ok(KillOutcome::Verified) => ("killed", Some(true)),
+        Ok(KillOutcome::Unverified) => ("killed", Some(false)),
+        Ok(KillOutcome::IdentityChanged) => ("kill-skipped", Some(false)),
+        Err(_) => ("kill-failed", Some(false)),
+    }
+}
+
+fn step<'a>(info: &'a ProcessInfo, action: &'static str, verified: Option<bool>) -> Step<'a> {
+    Step { info, action, verified }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::ReplaySource;
+
+    // Replayed processes ignore SIGTERM, so every survivor escalates.
+    const REPLAY: &str = "scan\n\
+        20\t1\t501\t0\t1024\tminer\t/tmp/miner\n\
+        21\t20\t501\t0\t1024\tworker\t/tmp/worker\n";
+
+    fn source() -> ReplaySource {
+        let source = ReplaySource::parse(REPLAY).unwrap();
+        source.list().unwrap();
+        source
+    }
+
+    #[test]
+    fn sigterm_returns_without_waiting() {
+        let source = source();
+        let miner = source.process(20).unwrap();
+        let worker = source.process(21).unwrap();
+        let mut pending = Vec::new();
+
+        let started = Instant::now();
+        let steps = run(&source, &[&worker, &miner], Some(60_000), Some("deny:name:miner"), &mut pending);
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let actions: Vec<&str> = steps.iter().map(|s| s.action).collect();
+        assert_eq!(actions, vec!["sigterm", "sigterm"]);
+        assert_eq!(pending.len(), 2);
+
+        // Still inside the grace period: nothing changes.
+        assert!(escalate(&source, &mut pending).is_empty());
+        assert_eq!(pending.len(), 2);
+
+        let resolved = finish(&source, &mut pending);
+        assert!(pending.is_empty());
+        let actions: Vec<(u32, &str)> = resolved.iter().map(|r| (r.pending.info.pid, r.action)).collect();
+        assert_eq!(actions, vec![(21, "killed"), (20, "killed")]);
+        assert_eq!(resolved[0].pending.rule.as_deref(), Some("deny:name:miner"));
+    }
+
+    #[test]
+    fn escalates_after_the_deadline_only() {
+        let source = source();
+        let miner = source.process(20).unwrap();
+        let mut pending = Vec::new();
+        run(&source, &[&miner], Some(0), None, &mut pending);
+
+        let resolved = escalate(&source, &mut pending);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].action, "killed");
+        assert_eq!(resolved[0].verified, Some(true));
+        assert!(pending.is_empty());
+
+        // Killed replay processes are gone: a second target exits on its own.
+        let mut pending = Vec::new();
+        run(&source, &[&miner], Some(0), None, &mut pending);
+        let resolved = escalate(&source, &mut pending);
+        assert_eq!(resolved[0].action, "terminated");
+    }
+
+    #[test]
+    fn without_grace_kills_at_once() {
+        let source = source();
+        let miner = source.process(20).unwrap();
+        let mut pending = Vec::new();
+        let steps = run(&source, &[&miner], None, None, &mut pending);
+        assert_eq!(steps.len(), 1);
+        assert_eq!(steps[0].action, "killed");
+        assert!(pending.is_empty());
+    }
+}
@@ -2,6 +2,8 @@ use std::fs::{File, OpenOptions};
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
 
+use crate::elf::ElfInfo;
+
 pub struct ExportConfig {
     pub csv_path: Option<PathBuf>,
     pub jsonl_path: Option<PathBuf>,
@@ -20,6 +22,8 @@ pub struct UnknownEvent<'a> {
     pub ppid: u32,
     pub name: &'a str,
     pub path: Option<&'a str>,
+    /// ELF identity of the executable, when it could be read. JSONL only.
+    pub elf: Option<&'a ElfInfo>,
 }
 
 pub struct AnomalyEvent<'a> {
@@ -51,6 +55,57 @@ pub struct AuditEvent<'a> {
     pub name: &'a str,
     pub path: Option<&'a str>,
     pub action: &'a str,
+    /// Whether the kill target's identity was re-checked; `None` when no
+    /// signal was sent.
+    pub verified: Option<bool>,
+    /// The allowlist line behind the verdict, with its file and line. JSONL only.
+    pub rule: Option<&'a str>,
+    /// ELF identity of the executable, when it could be read. JSONL only.
+    pub elf: Option<&'a ElfInfo>,
+}
+
+pub struct ExecEvent<'a> {
+    pub ts: u64,
+    pub pid: u32,
+    /// `None` when the process exited before it could be read.
+    pub uid: Option<u32>,
+    pub ppid: Option<u32>,
+    pub name: &'a str,
+    pub path: Option<&'a str>,
+    /// `exited` when only the event itself is known.
+    pub reason: Option<&'a str>,
+}
+
+/// One `--what-if` summary row: how often a missing rule would have fired.
+pub struct WhatIfEvent<'a> {
+    pub ts: u64,
+    pub rule: &'a str,
+    pub action: &'a str,
+    pub hits: u64,
+    pub pids: u64,
+    pub names: &'a str,
+    pub path: Option<&'a str>,
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+/// An allowlist profile switch, from the schedule or `SET_PROFILE`.
+pub struct ProfileEvent<'a> {
+    pub ts: u64,
+    pub from: &'a str,
+    pub to: &'a str,
+    pub path: &'a str,
+    pub trigger: &'a str,
+}
+
+/// A reload of the active profile from disk. `error` is set when the new
+/// files failed to load and the previous allowlist was kept.
+pub struct ReloadEvent<'a> {
+    pub ts: u64,
+    pub profile: &'a str,
+    pub path: &'a str,
+    pub trigger: &'a str,
+    pub error: Option<&'a str>,
 }
 
 pub struct SystemOverloadEvent<'a> {
@@ -102,8 +157,9 @@ impl Exporter {
             None,
             None,
             None,
+            None,
         )?;
-        self.write_json(event.ts, "unknown", event.pid, Some(event.uid), Some(event.ppid), event.name, event.path, None, None, None)?;
+        self.write_json(event.ts, "unknown", event.pid, Some(event.uid), Some(event.ppid), event.name, event.path, None, None, None, None, None, event.elf)?;
         Ok(())
     }
 
@@ -119,6 +175,7 @@ impl Exporter {
             Some(event.cpu_pct),
             Some(event.ram_pct),
             Some(event.reason),
+            None,
         )?;
         self.write_json(
             event.ts,
@@ -131,6 +188,9 @@ impl Exporter {
             Some(event.cpu_pct),
             Some(event.ram_pct),
             Some(event.reason),
+            None,
+            None,
+            None,
         )?;
         Ok(())
     }
@@ -147,6 +207,7 @@ impl Exporter {
             Some(event.cpu_pct),
             Some(event.ram_pct),
             None,
+            None,
         )?;
         self.write_json(
             event.ts,
@@ -159,6 +220,9 @@ impl Exporter {
             Some(event.cpu_pct),
             Some(event.ram_pct),
             None,
+            None,
+            None,
+            None,
         )?;
         Ok(())
     }
@@ -175,6 +239,7 @@ impl Exporter {
             None,
             None,
             Some(event.action),
+            event.verified,
         )?;
         self.write_json(
             event.ts,
@@ -187,10 +252,31 @@ impl Exporter {
             None,
             None,
             Some(event.action),
+            event.verified,
+            event.rule,
+            event.elf,
         )?;
         Ok(())
     }
 
+    pub fn write_exec(&mut self, event: &ExecEvent) -> Result<(), String> {
+        self.write_csv(
+            event.ts,
+            "exec",
+            Some(event.pid),
+            event.uid,
+            event.ppid,
+            Some(event.name),
+            event.path,
+            None,
+            None,
+            event.reason,
+            None,
+        )?;
+        self.write_json(event.ts, "exec", event.pid, event.uid, event.ppid, event.name, event.path, None, None, event.reason, None, None, None)?;
+        Ok(())
+    }
+
     pub fn write_system_overload(&mut self, event: &SystemOverloadEvent) -> Result<(), String> {
         self.write_csv(
             event.ts,
@@ -203,6 +289,7 @@ impl Exporter {
             Some(event.cpu_pct),
             Some(event.ram_pct),
             None,
+            None,
         )?;
         self.write_json(
             event.ts,
@@ -215,10 +302,128 @@ impl Exporter {
             Some(event.cpu_pct),
             Some(event.ram_pct),
             None,
+            None,
+            None,
+            None,
+        )?;
+        Ok(())
+    }
+
+    /// The CSV row keeps the fixed columns (rule in `reason`); counts and
+    /// timestamps are only in the JSONL record.
+    pub fn write_what_if(&mut self, event: &WhatIfEvent) -> Result<(), String> {
+        self.write_csv(
+            event.ts,
+            "what-if",
+            None,
+            None,
+            None,
+            Some(event.names),
+            event.path,
+            None,
+            None,
+            Some(event.rule),
+            None,
+        )?;
+        let Some(writer) = self.jsonl.as_mut() else {
+            return Ok(());
+        };
+        let mut line = String::new();
+        line.push('{');
+        line.push_str(&format!("\"ts\":{},", event.ts));
+        line.push_str("\"kind\":\"what-if\",");
+        line.push_str(&format!("\"rule\":\"{}\",", json_escape(event.rule)));
+        line.push_str(&format!("\"action\":\"{}\",", json_escape(event.action)));
+        line.push_str(&format!("\"hits\":{},", event.hits));
+        line.push_str(&format!("\"pids\":{},", event.pids));
+        line.push_str(&format!("\"names\":\"{}\",", json_escape(event.names)));
+        line.push_str(&json_opt_str("path", event.path));
+        line.push_str(&format!("\"first_seen\":{},", event.first_seen));
+        line.push_str(&format!("\"last_seen\":{}", event.last_seen));
+        line.push_str("}\n");
+        writer.write_all(line.as_bytes()).map_err(|err| err.to_string())
+    }
+
+    /// The CSV row carries the new profile as `name` and the trigger as
+    /// `reason`; the previous profile is only in the JSONL record.
+    pub fn write_profile(&mut self, event: &ProfileEvent) -> Result<(), String> {
+        self.write_csv(
+            event.ts,
+            "profile",
+            None,
+            None,
+            None,
+            Some(event.to),
+            Some(event.path),
+            None,
+            None,
+            Some(event.trigger),
+            None,
         )?;
+        let Some(writer) = self.jsonl.as_mut() else {
+            return Ok(());
+        };
+        let mut line = String::new();
+        line.push('{');
+        line.push_str(&format!("\"ts\":{},", event.ts));
+        line.push_str("\"kind\":\"profile\",");
+        line.push_str(&format!("\"from\":\"{}\",", json_escape(event.from)));
+        line.push_str(&format!("\"to\":\"{}\",", json_escape(event.to)));
+        line.push_str(&format!("\"path\":\"{}\",", json_escape(event.path)));
+        line.push_str(&format!("\"trigger\":\"{}\"", json_escape(event.trigger)));
+        line.push_str("}\n");
+        writer.write_all(line.as_bytes()).map_err(|err| err.to_string())
+    }
+
+    /// The CSV row carries the trigger as `reason`, followed by the error
+    /// when the reload was rejected.
+    pub fn write_reload(&mut self, event: &ReloadEvent) -> Result<(), String> {
+        let reason = match event.error {
+            Some(error) => format!("{}: {}", event.trigger, error),
+            None => event.trigger.to_string(),
+        };
+        self.write_csv(
+            event.ts,
+            "reload",
+            None,
+            None,
+            None,
+            Some(event.profile),
+            Some(event.path),
+            None,
+            None,
+            Some(&reason),
+            None,
+        )?;
+        let Some(writer) = self.jsonl.as_mut() else {
+            return Ok(());
+        };
+        let mut line = String::new();
+        line.push('{');
+        line.push_str(&format!("\"ts\":{},", event.ts));
+        line.push_str("\"kind\":\"reload\",");
+        line.push_str(&format!("\"profile\":\"{}\",", json_escape(event.profile)));
+        line.push_str(&format!("\"path\":\"{}\",", json_escape(event.path)));
+        line.push_str(&format!("\"trigger\":\"{}\",", json_escape(event.trigger)));
+        line.push_str(&format!("\"ok\":{}", event.error.is_none()));
+        if let Some(error) = event.error {
+            line.push_str(&format!(",\"error\":\"{}\"", json_escape(error)));
+        }
+        line.push_str("}\n");
+        writer.write_all(line.as_bytes()).map_err(|err| err.to_string())
+    }
+
+    pub fn flush(&mut self) -> Result<(), String> {
+        if let Some(writer) = self.csv.as_mut() {
+            writer.flush().map_err(|err| err.to_string())?;
+        }
+        if let Some(writer) = self.jsonl.as_mut() {
+            writer.flush().map_err(|err| err.to_string())?;
+        }
         Ok(())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn write_csv(
         &mut self,
         ts: u64,
@@ -231,13 +436,14 @@ impl Exporter {
         cpu: Option<f64>,
         ram: Option<f64>,
         reason: Option<&str>,
+        verified: Option<bool>,
     ) -> Result<(), String> {
         let Some(writer) = self.csv.as_mut() else {
             return Ok(());
         };
         if !self.csv_has_header {
             writer
-                .write_all(b"ts,kind,pid,uid,ppid,name,path,cpu,ram,reason\n")
+                .write_all(b"ts,kind,pid,uid,ppid,name,path,cpu,ram,reason,verified\n")
                 .map_err(|err| err.to_string())?;
             self.csv_has_header = true;
         }
@@ -253,6 +459,7 @@ impl Exporter {
             opt_f64(cpu),
             opt_f64(ram),
             opt_str(reason),
+            opt_bool(verified),
         ];
         let mut line = String::new();
         for (idx, field) in fields.iter().enumerate() {
@@ -265,6 +472,7 @@ impl Exporter {
         writer.write_all(line.as_bytes()).map_err(|err| err.to_string())
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn write_json(
         &mut self,
         ts: u64,
@@ -277,6 +485,9 @@ impl Exporter {
         cpu: Option<f64>,
         ram: Option<f64>,
         reason: Option<&str>,
+        verified: Option<bool>,
+        rule: Option<&str>,
+        elf: Option<&ElfInfo>,
     ) -> Result<(), String> {
         let Some(writer) = self.jsonl.as_mut() else {
             return Ok(());
@@ -294,6 +505,15 @@ impl Exporter {
         line.push_str(&json_opt_f64("cpu", cpu));
         line.push_str(&json_opt_f64("ram", ram));
         line.push_str(&json_opt_str("reason", reason));
+        if let Some(v) = verified {
+            line.push_str(&format!("\"verified\":{},", v));
+        }
+        if rule.is_some() {
+            line.push_str(&json_opt_str("rule", rule));
+        }
+        if let Some(elf) = elf {
+            line.push_str(&json_elf(elf));
+        }
         if line.ends_with(',') {
             line.pop();
         }
@@ -309,6 +529,31 @@ pub fn now_ts() -> u64 {
         .unwrap_or(0)
 }
 
+/// UTC `YYYY-MM-DDTHH:MM:SSZ` for a UNIX timestamp.
+pub fn format_ts(ts: u64) -> String {
+    let days = (ts / 86400) as i64;
+    let secs = ts % 86400;
+    // Civil-from-days (Howard Hinnant), valid for the whole u64 second range we see.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
 fn open_append(path: &Path) -> Result<BufWriter<File>, String> {
     let file = OpenOptions::new()
         .create(true)
@@ -332,6 +577,10 @@ fn opt_f64(value: Option<f64>) -> String {
         .unwrap_or_default()
 }
 
+fn opt_bool(value: Option<bool>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
 fn opt_str(value: Option<&str>) -> String {
     value.unwrap_or_default().to_string()
 }
@@ -345,7 +594,7 @@ fn csv_escape(value: &str) -> String {
     }
 }
 
-fn json_escape(value: &str) -> String {
+pub fn json_escape(value: &str) -> String {
     value
         .replace('\\', "\\\\")
         .replace('"', "\\\"")
@@ -361,6 +610,32 @@ fn json_opt_str(key: &str, value: Option<&str>) -> String {
     }
 }
 
+/// The ELF fields of an event; empty lists and unknown values are left out.
+fn json_elf(elf: &ElfInfo) -> String {
+    let mut out = String::new();
+    if let Some(id) = elf.build_id.as_deref() {
+        out.push_str(&format!("\"build_id\":\"{}\",", json_escape(id)));
+    }
+    if let Some(interp) = elf.interpreter.as_deref() {
+        out.push_str(&format!("\"interp\":\"{}\",", json_escape(interp)));
+    }
+    let tags: Vec<&str> = elf.traits.iter().map(|t| t.tag()).collect();
+    for (key, values) in [("needed", &elf.needed), ("rpath", &elf.rpath), ("runpath", &elf.runpath)] {
+        if !values.is_empty() {
+            out.push_str(&json_str_list(key, values.iter().map(String::as_str)));
+        }
+    }
+    if !tags.is_empty() {
+        out.push_str(&json_str_list("elf_traits", tags.into_iter()));
+    }
+    out
+}
+
+fn json_str_list<'a>(key: &str, values: impl Iterator<Item = &'a str>) -> String {
+    let items: Vec<String> = values.map(|v| format!("\"{}\"", json_escape(v))).collect();
+    format!("\"{}\":[{}],", key, items.join(","))
+}
+
 fn json_opt_u32(key: &str, value: Option<u32>) -> String {
     match value {
         Some(v) => format!("\"{}\":{},", key, v),
@@ -1,14 +1,97 @@
 use std::io::{Read, Write};
 use std::os::unix::net::{UnixListener, UnixStream};
-use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
+use crate::cache::CacheStats;
 use crate::config::{self, Allowlist};
+use crate::export::{self, ProfileEvent, ReloadEvent};
+use crate::platform::{KillOutcome, ProcessSource};
+use crate::quarantine::{Quarantine, SharedAudit};
+use crate::schedule::{Schedule, WeekTime};
 
 const SOCKET_PATH: &str = "/tmp/zen.sock";
 
-pub fn start_server(allowlist: Arc<RwLock<Allowlist>>, self_pid: u32) {
+/// The profile currently loaded into the live allowlist.
+pub struct ActiveProfile {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Daemon state reachable from IPC commands.
+pub struct Context {
+    pub allowlist: Arc<RwLock<Allowlist>>,
+    pub source: Arc<dyn ProcessSource>,
+    pub quarantine: Arc<Mutex<Quarantine>>,
+    pub audit: SharedAudit,
+    pub cache: Arc<CacheStats>,
+    pub self_pid: u32,
+    pub profile: Mutex<ActiveProfile>,
+    /// The profile the daemon started with; the schedule falls back to it.
+    pub base_profile: String,
+    pub schedule: Option<Schedule>,
+}
+
+/// Loads `path` into the live allowlist and records the switch. The old
+/// allowlist stays in place if the new one fails to load.
+pub fn switch_profile(ctx: &Context, name: &str, path: &Path, trigger: &str) -> Result<(), String> {
+    // Held from the load to the swap, so a concurrent reload or switch
+    // cannot leave one profile's rules installed under another's name.
+    let mut profile = ctx.profile.lock().unwrap();
+    let new_allowlist = config::load_allowlist(path)?;
+    *ctx.allowlist
+        .write()
+        .map_err(|_| "failed to acquire write lock on allowlist".to_string())? = new_allowlist;
+    let from = std::mem::replace(
+        &mut *profile,
+        ActiveProfile {
+            name: name.to_string(),
+            path: path.to_path_buf(),
+        },
+    )
+    .name;
+    drop(profile);
+    if let Some(writer) = ctx.audit.lock().unwrap().as_mut() {
+        let _ = writer.write_profile(&ProfileEvent {
+            ts: export::now_ts(),
+            from: &from,
+            to: name,
+            path: &path.display().to_string(),
+            trigger,
+        });
+        let _ = writer.flush();
+    }
+    Ok(())
+}
+
+/// Re-reads the active profile and everything it includes. On failure the
+/// old allowlist stays in place; both outcomes are audited.
+pub fn reload(ctx: &Context, trigger: &str) -> Result<String, String> {
+    // Held from the load to the swap, as in `switch_profile`.
+    let profile = ctx.profile.lock().unwrap();
+    let (name, path) = (profile.name.clone(), profile.path.clone());
+    let result = config::load_allowlist(&path).and_then(|new_allowlist| {
+        *ctx.allowlist
+            .write()
+            .map_err(|_| "failed to acquire write lock on allowlist".to_string())? = new_allowlist;
+        Ok(())
+    });
+    drop(profile);
+    if let Some(writer) = ctx.audit.lock().unwrap().as_mut() {
+        let _ = writer.write_reload(&ReloadEvent {
+            ts: export::now_ts(),
+            profile: &name,
+            path: &path.display().to_string(),
+            trigger,
+            error: result.as_ref().err().map(|e| e.as_str()),
+        });
+        let _ = writer.flush();
+    }
+    result.map(|()| name)
+}
+
+pub fn start_server(ctx: Arc<Context>) {
     // Remove existing socket if it exists
     let _ = std::fs::remove_file(SOCKET_PATH);
 
@@ -25,8 +108,8 @@ pub fn start_server(allowlist: Arc<RwLock<Allowlist>>, self_pid: u32) {
             match stream {
                 Ok(mut stream) => {
                     let mut buffer = String::new();
-                    if let Ok(_) = stream.read_to_string(&mut buffer) {
-                        let response = handle_ipc_command(&buffer, &allowlist, self_pid);
+                    if stream.read_to_string(&mut buffer).is_ok() {
+                        let response = handle_ipc_command(&buffer, &ctx);
                         let _ = stream.write_all(response.as_bytes());
                     }
                 }
@@ -38,45 +121,88 @@ pub fn start_server(allowlist: Arc<RwLock<Allowlist>>, self_pid: u32) {
     });
 }
 
-fn handle_ipc_command(command: &str, allowlist_lock: &Arc<RwLock<Allowlist>>, self_pid: u32) -> String {
+fn handle_ipc_command(command: &str, ctx: &Context) -> String {
     let cmd = command.trim();
-    if cmd.starts_with("SET_PROFILE ") {
-        let profile_name = &cmd["SET_PROFILE ".len()..];
-        let path_str = if profile_name.contains('/') || profile_name.ends_with(".txt") {
-            profile_name.to_string()
-        } else {
-            format!("profiles/{}.txt", profile_name)
-        };
-        
-        let path = PathBuf::from(&path_str);
-        match config::load_allowlist(&path) {
-            Ok(new_allowlist) => {
-                if let Ok(mut lock) = allowlist_lock.write() {
-                    *lock = new_allowlist;
-                    format!("OK: Profile updated to {}\n", profile_name)
-                } else {
-                    "ERROR: Failed to acquire write lock on allowlist\n".to_string()
-                }
-            }
+    if let Some(profile_name) = cmd.strip_prefix("SET_PROFILE ") {
+        match switch_profile(ctx, profile_name, &config::profile_path(profile_name), "ipc") {
+            Ok(()) => format!("OK: Profile updated to {}\n", profile_name),
             Err(e) => format!("ERROR: Failed to load profile {}: {}\n", profile_name, e),
         }
     } else if cmd == "STATUS" {
-        let (rules, teams) = if let Ok(lock) = allowlist_lock.read() {
+        let (rules, teams) = if let Ok(lock) = ctx.allowlist.read() {
             (lock.names.len() + lock.paths.len(), lock.teams.len())
         } else {
             (0, 0)
         };
 
-        let mem_info = if let Ok(sample) = crate::platform::sample_process(self_pid) {
+        let mem_info = if let Ok(sample) = ctx.source.sample(ctx.self_pid) {
             format!("{:.2} MB", sample.rss_bytes as f64 / 1024.0 / 1024.0)
         } else {
             "Unknown".to_string()
         };
 
-        format!(
-            "OK: Zen Daemon is running\nPID: {}\nMemory (RSS): {}\nRules: {} names/paths, {} teams\n",
-            self_pid, mem_info, rules, teams
-        )
+        let quarantined = ctx.quarantine.lock().map(|q| q.len()).unwrap_or(0);
+        let profile = ctx.profile.lock().map(|p| p.name.clone()).unwrap_or_default();
+
+        let mut status = format!(
+            "OK: Zen Daemon is running\nPID: {}\nMemory (RSS): {}\nRules: {} names/paths, {} teams\nQuarantined: {}\nProfile: {}\n",
+            ctx.self_pid, mem_info, rules, teams, quarantined, profile
+        );
+        status.push_str(&ctx.cache.describe());
+        if let Some(schedule) = &ctx.schedule {
+            match schedule.next_change(WeekTime::now()) {
+                Some((at, next)) => status.push_str(&format!(
+                    "Next change: {} -> {}\n",
+                    at.label(),
+                    next.unwrap_or(&ctx.base_profile)
+                )),
+                None => status.push_str("Next change: none\n"),
+            }
+        }
+        if let Ok(lock) = ctx.allowlist.read() {
+            status.push_str("Effective rules:\n");
+            status.push_str(&lock.provenance());
+        }
+        status
+    } else if cmd == "RELOAD" {
+        match reload(ctx, "ipc") {
+            Ok(name) => format!("OK: Reloaded {}\n", name),
+            Err(e) => format!("ERROR: Reload failed, keeping previous allowlist: {}\n", e),
+        }
+    } else if let Some(pid) = cmd.strip_prefix("EXPLAIN ") {
+        let Ok(pid) = pid.trim().parse::<u32>() else {
+            return format!("ERROR: bad pid: {}\n", pid);
+        };
+        let Ok(allowlist) = ctx.allowlist.read() else {
+            return "ERROR: Failed to acquire read lock on allowlist\n".to_string();
+        };
+        match crate::policy::explain(ctx.source.as_ref(), &allowlist, pid) {
+            Ok(text) => format!("OK: {}", text),
+            Err(e) => format!("ERROR: {}\n", e),
+        }
+    } else if cmd == "QUARANTINE LIST" {
+        let list = ctx.quarantine.lock().unwrap().list();
+        format!("OK: pid name path method since\n{}", list)
+    } else if let Some(pid) = cmd.strip_prefix("RELEASE ") {
+        let Ok(pid) = pid.trim().parse::<u32>() else {
+            return format!("ERROR: bad pid: {}\n", pid);
+        };
+        let mut quarantine = ctx.quarantine.lock().unwrap();
+        match quarantine.release(pid, ctx.source.as_ref(), &ctx.audit) {
+            Ok(()) => format!("OK: released {}\n", pid),
+            Err(e) => format!("ERROR: {}\n", e),
+        }
+    } else if let Some(pid) = cmd.strip_prefix("KILL ") {
+        let Ok(pid) = pid.trim().parse::<u32>() else {
+            return format!("ERROR: bad pid: {}\n", pid);
+        };
+        let mut quarantine = ctx.quarantine.lock().unwrap();
+        match quarantine.kill(pid, ctx.source.as_ref(), &ctx.audit) {
+            Ok(KillOutcome::IdentityChanged) => format!("ERROR: pid {} changed identity, not killed\n", pid),
+            Ok(KillOutcome::Verified) => format!("OK: killed {} (verified)\n", pid),
+            Ok(KillOutcome::Unverified) => format!("OK: killed {}\n", pid),
+            Err(e) => format!("ERROR: {}\n", e),
+        }
     } else {
         format!("ERROR: Unknown command: {}\n", cmd)
     }
@@ -99,3 +225,97 @@ pub fn send_command(command: &str) -> Result<String, String> {
 
     Ok(response)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    use crate::platform::ReplaySource;
+
+    struct Fixture {
+        dir: PathBuf,
+        ctx: Context,
+    }
+
+    impl Fixture {
+        /// A context whose active profile is `dir/main.txt`, auditing to
+        /// `dir/audit.jsonl`.
+        fn new(name: &str) -> Fixture {
+            let dir = std::env::temp_dir().join(format!("zen-ipc-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("main.txt");
+            fs::write(&path, "name:zsh\n").unwrap();
+            let audit = export::Exporter::new(&export::ExportConfig {
+                csv_path: None,
+                jsonl_path: Some(dir.join("audit.jsonl")),
+            })
+            .unwrap();
+            let ctx = Context {
+                allowlist: Arc::new(RwLock::new(config::load_allowlist(&path).unwrap())),
+                source: Arc::new(ReplaySource::parse("scan\n").unwrap()),
+                quarantine: Arc::new(Mutex::new(Quarantine::default())),
+                audit: Arc::new(Mutex::new(audit)),
+                cache: Arc::new(CacheStats::default()),
+                self_pid: std::process::id(),
+                profile: Mutex::new(ActiveProfile {
+                    name: "main".to_string(),
+                    path,
+                }),
+                base_profile: "main".to_string(),
+                schedule: None,
+            };
+            Fixture { dir, ctx }
+        }
+
+        fn names(&self) -> Vec<String> {
+            let mut names: Vec<String> = self.ctx.allowlist.read().unwrap().names.iter().cloned().collect();
+            names.sort();
+            names
+        }
+
+        fn audit(&self) -> String {
+            fs::read_to_string(self.dir.join("audit.jsonl")).unwrap_or_default()
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    #[test]
+    fn reload_keeps_the_old_allowlist_on_parse_failure() {
+        let fx = Fixture::new("reload");
+        fs::write(fx.dir.join("main.txt"), "name:zsh\nbogus:1\n").unwrap();
+        assert!(reload(&fx.ctx, "watch").is_err());
+        assert_eq!(fx.names(), vec!["zsh"]);
+        assert!(fx.audit().contains("\"error\":\"unknown key on line 2: bogus\""), "{}", fx.audit());
+
+        fs::write(fx.dir.join("main.txt"), "name:zsh\nname:vim\n").unwrap();
+        assert_eq!(reload(&fx.ctx, "ipc"), Ok("main".to_string()));
+        assert_eq!(fx.names(), vec!["vim", "zsh"]);
+    }
+
+    #[test]
+    fn failed_switch_keeps_profile_and_allowlist() {
+        let fx = Fixture::new("switch");
+        let broken = fx.dir.join("broken.txt");
+        fs::write(&broken, "uid:root\n").unwrap();
+        assert!(switch_profile(&fx.ctx, "broken", &broken, "ipc").is_err());
+        assert_eq!(fx.ctx.profile.lock().unwrap().name, "main");
+        assert_eq!(fx.names(), vec!["zsh"]);
+        assert!(fx.audit().is_empty());
+
+        let other = fx.dir.join("other.txt");
+        fs::write(&other, "name:htop\n").unwrap();
+        switch_profile(&fx.ctx, "other", &other, "schedule").unwrap();
+        assert_eq!(fx.ctx.profile.lock().unwrap().name, "other");
+        assert_eq!(fx.names(), vec!["htop"]);
+        assert!(fx.audit().contains("\"from\":\"main\",\"to\":\"other\""), "{}", fx.audit());
+        // A reload now re-reads the new profile.
+        assert_eq!(reload(&fx.ctx, "ipc"), Ok("other".to_string()));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::toml::{Entry, Value};
+
+/// Parses a JSON document whose top level is an object into the same
+/// `Value` tree `toml::parse` builds. Objects become tables whose entries
+/// carry the line of their key; `null` is rejected, as TOML has no
+/// equivalent.
+pub fn parse(content: &str) -> Result<Vec<Entry>, String> {
+    let mut parser = Parser {
+        bytes: content.as_bytes(),
+        pos: 0,
+        line: 1,
+    };
+    parser.skip_ws();
+    if parser.peek() != Some(b'{') {
+        return Err("line 1: expected a JSON object".to_string());
+    }
+    let Value::Table(entries) = parser.value()? else {
+        unreachable!("an object parses to a table");
+    };
+    parser.skip_ws();
+    if parser.pos < parser.bytes.len() {
+        return Err(parser.error("unexpected data after the document"));
+    }
+    Ok(entries)
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    line: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("line {}: {}", self.line, message)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(b) = self.peek() {
+            match b {
+                b'\n' => self.line += 1,
+                b' ' | b'\t' | b'\r' => {}
+                _ => return,
+            }
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        self.skip_ws();
+        if self.peek() != Some(byte) {
+            return Err(self.error(&format!("expected '{}'", byte as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Value::Str),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => Err(self.error("null is not supported")),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end of document")),
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut entries: Vec<Entry> = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Value::Table(entries));
+        }
+        loop {
+            self.skip_ws();
+            let line = self.line;
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expected a quoted key"));
+            }
+            let key = self.string()?;
+            if entries.iter().any(|e| e.key == key) {
+                return Err(format!("line {}: duplicate key '{}'", line, key));
+            }
+            self.expect(b':')?;
+            let value = self.value()?;
+            entries.push(Entry { key, value, line });
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Value::Table(entries));
+                }
+                _ => return Err(self.error("expected ',' or '}'")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("expected ',' or ']'")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.pos += 1;
+        let mut out = Vec::new();
+        loop {
+            let Some(b) = self.peek() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += 1;
+            match b {
+                b'"' => return String::from_utf8(out).map_err(|_| self.error("invalid UTF-8 in string")),
+                b'\n' => return Err(self.error("unterminated string")),
+                b'\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    let c = match escaped {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    let mut buf = [0u8; 4];
+                    out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                }
+                b => out.push(b),
+            }
+        }
+    }
+
+    /// The four hex digits after `\u`, combining a surrogate pair when one
+    /// follows.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high).ok_or_else(|| self.error("invalid \\u escape"));
+        }
+        if self.bytes.get(self.pos..self.pos + 2) != Some(b"\\u") {
+            return Err(self.error("unpaired surrogate in \\u escape"));
+        }
+        self.pos += 2;
+        let low = self.hex4()?;
+        if !(0xDC00..0xE000).contains(&low) {
+            return Err(self.error("unpaired surrogate in \\u escape"));
+        }
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+            .ok_or_else(|| self.error("invalid \\u escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .bytes
+            .get(self.pos..self.pos + 4)
+            .and_then(|d| std::str::from_utf8(d).ok())
+            .and_then(|d| u32::from_str_radix(d, 16).ok())
+            .ok_or_else(|| self.error("invalid \\u escape"))?;
+        self.pos += 4;
+        Ok(digits)
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        if !self.bytes[self.pos..].starts_with(word.as_bytes()) {
+            return Err(self.error("unexpected character"));
+        }
+        self.pos += word.len();
+        Ok(value)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+        let text = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap_or_default();
+        if let Ok(n) = text.parse::<i64>() {
+            return Ok(Value::Int(n));
+        }
+        text.parse::<f64>()
+            .map(Value::Float)
+            .map_err(|_| self.error(&format!("invalid number '{}'", text)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_objects_with_key_lines() {
+        let entries = parse(
+            "{\n  \"extends\": \"base\",\n  \"n\": -12, \"f\": 1.5e2, \"ok\": false,\n  \
+             \"rules\": [\n    {\"match\": \"name:caf\\u00e9 \\ud83d\\ude00\"}\n  ]\n}\n",
+        )
+        .unwrap();
+        let keys: Vec<(&str, usize)> = entries.iter().map(|e| (e.key.as_str(), e.line)).collect();
+        assert_eq!(keys, vec![("extends", 2), ("n", 3), ("f", 3), ("ok", 3), ("rules", 4)]);
+        assert!(matches!(entries[1].value, Value::Int(-12)));
+        assert_eq!(entries[2].value.as_f64(), Ok(150.0));
+        assert_eq!(entries[3].value.as_bool(), Ok(false));
+        let Value::Array(rules) = &entries[4].value else { panic!("rules is not an array") };
+        let Value::Table(rule) = &rules[0] else { panic!("rule is not an object") };
+        assert_eq!((rule[0].value.as_str(), rule[0].line), (Ok("name:café 😀"), 5));
+    }
+
+    #[test]
+    fn reports_errors_with_their_line() {
+        for (content, error) in [
+            ("[1]", "line 1: expected a JSON object"),
+            ("{\"a\": null}", "line 1: null is not supported"),
+            ("{\"a\": 1,\n\"a\": 2}", "line 2: duplicate key 'a'"),
+            ("{\"a\": 1\n\n}", ""),
+            ("{\"a\": 1} x", "line 1: unexpected data after the document"),
+            ("{\"a\": \"\\ud83d\"}", "line 1: unpaired surrogate in \\u escape"),
+            ("{\n\"a\": [1 2]}", "line 2: expected ',' or ']'"),
+        ] {
+            let result = parse(content).err().unwrap_or_default();
+            assert_eq!(result, error, "{}", content);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use crate::cache::FileCache;
+use crate::export;
+use crate::platform::ProcessInfo;
+use crate::policy;
+
+/// Locations whose contents are rewritten freely; a path rule there would
+/// allow whatever lands at the same name next time.
+const UNSTABLE_PREFIXES: &[&str] = &["/tmp/", "/var/tmp/", "/dev/shm/", "/private/tmp/", "/private/var/folders/"];
+
+struct Proposal {
+    rule: String,
+    first_seen: u64,
+    count: u64,
+    names: HashSet<String>,
+}
+
+/// Turns observed processes into allowlist lines, one per distinct rule.
+#[derive(Default)]
+pub struct Learner {
+    proposals: Vec<Proposal>,
+    index: HashMap<String, usize>,
+    /// Rules in the file as last written.
+    written: usize,
+}
+
+impl Learner {
+    pub fn record(
+        &mut self,
+        info: &ProcessInfo,
+        cache: &mut FileCache,
+    ) {
+        let rule = propose_rule(info, cache);
+        let idx = *self.index.entry(rule.clone()).or_insert_with(|| {
+            self.proposals.push(Proposal {
+                rule,
+                first_seen: export::now_ts(),
+                count: 0,
+                names: HashSet::new(),
+            });
+            self.proposals.len() - 1
+        });
+        let proposal = &mut self.proposals[idx];
+        proposal.count += 1;
+        proposal.names.insert(info.name.clone());
+    }
+
+    pub fn len(&self) -> usize {
+        self.proposals.len()
+    }
+
+    /// True when rules were proposed since the last `write`.
+    pub fn has_new_rules(&self) -> bool {
+        self.proposals.len() > self.written
+    }
+
+    /// Writes the proposals in first-seen order, through a temporary file and
+    /// a rename so an interrupted run leaves the previous version. Comments go
+    /// on their own line because `load_allowlist` keeps trailing text as part
+    /// of the value. With nothing observed no file is written, as a
+    /// comments-only allowlist would not load.
+    pub fn write(&mut self, path: &Path, observed_secs: u64) -> Result<(), String> {
+        if self.proposals.is_empty() {
+            return Err(format!("no processes observed, {} not written", path.display()));
+        }
+        let mut out = format!(
+            "# Generado por zen --learn el {} ({} s observados, {} reglas)\n# Revisa cada línea antes de usarlo con --enforce.\n\n",
+            export::format_ts(export::now_ts()),
+            observed_secs,
+            self.proposals.len()
+        );
+        for proposal in &self.proposals {
+            let mut names: Vec<&str> = proposal.names.iter().map(|n| n.as_str()).collect();
+            names.sort_unstable();
+            out.push_str(&format!(
+                "# first-seen={} count={} ({})\n{}\n",
+                export::format_ts(proposal.first_seen),
+                proposal.count,
+                names.join(", "),
+                proposal.rule
+            ));
+        }
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, out).and_then(|()| fs::rename(&tmp, path)).map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            format!("failed to write {}: {}", path.display(), err)
+        })?;
+        self.written = self.proposals.len();
+        Ok(())
+    }
+}
+
+/// The narrowest rule that identifies `info`: its path when the location is
+/// stable, else the binary hash, else its signer, else its name.
+pub fn propose_rule(
+    info: &ProcessInfo,
+    cache: &mut FileCache,
+) -> String {
+    let Some(path) = &info.path else {
+        return format!("name:{}", info.name);
+    };
+    if is_stable_path(path) {
+        return format!("path:{}", path);
+    }
+
+    if let Some(hash) = cache.hash(path) {
+        return format!("hash:{}", hash);
+    }
+
+    if let Some(sig) = policy::verified_signature(info, cache) {
+        if let Some(team) = &sig.team_id {
+            return format!("team:{}", team);
+        }
+        if let Some(authority) = sig.authority() {
+            return format!("authority:{}", authority);
+        }
+    }
+
+    format!("name:{}", info.name)
+}
+
+fn is_stable_path(path: &str) -> bool {
+    !path.ends_with(" (deleted)") && !UNSTABLE_PREFIXES.iter().any(|p| path.starts_with(p))
+}
+
+/// Parses `90`, `90s`, `15m`, `1h` or `2d` into seconds.
+pub fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let (digits, unit) = match value.char_indices().last()? {
+        (idx, c) if c.is_ascii_alphabetic() => (&value[..idx], c),
+        _ => (value, 's'),
+    };
+    let n: u64 = digits.parse().ok()?;
+    let factor = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+    n.checked_mul(factor)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    fn info(pid: u32, name: &str, path: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            ppid: 1,
+            uid: 501,
+            name: name.to_string(),
+            path: path.map(str::to_string),
+            args: Vec::new(),
+            start_time: 0,
+        }
+    }
+
+    fn temp(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("zen-learn-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn never_writes_an_empty_allowlist() {
+        let path = temp("empty");
+        let mut learner = Learner::default();
+        assert!(learner.write(&path, 10).is_err());
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn written_file_loads_back() {
+        let path = temp("roundtrip");
+        let mut cache = FileCache::new(16);
+        let mut learner = Learner::default();
+        learner.record(&info(10, "zsh", Some("/bin/zsh")), &mut cache);
+        learner.record(&info(11, "zsh", Some("/bin/zsh")), &mut cache);
+        learner.record(&info(12, "kworker/0:1", None), &mut cache);
+        assert!(learner.has_new_rules());
+
+        learner.write(&path, 30).unwrap();
+        assert!(!learner.has_new_rules());
+        let allowlist = config::load_allowlist(&path).unwrap();
+        assert!(allowlist.paths.iter().any(|p| p == "/bin/zsh"));
+        assert!(allowlist.names.iter().any(|n| n == "kworker/0:1"));
+        assert!(fs::read_to_string(&path).unwrap().contains("count=2 (zsh)\npath:/bin/zsh\n"));
+
+        learner.record(&info(13, "zsh", Some("/bin/zsh")), &mut cache);
+        assert!(!learner.has_new_rules());
+        learner.record(&info(14, "vim", Some("/usr/bin/vim")), &mut cache);
+        assert!(learner.has_new_rules());
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn unstable_locations_are_not_path_rules() {
+        let mut cache = FileCache::new(16);
+        assert_eq!(propose_rule(&info(1, "x", Some("/tmp/x")), &mut cache), "name:x");
+        assert_eq!(propose_rule(&info(1, "x", Some("/opt/x (deleted)")), &mut cache), "name:x");
+        assert_eq!(propose_rule(&info(1, "x", Some("/opt/x")), &mut cache), "path:/opt/x");
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("90s"), Some(90));
+        assert_eq!(parse_duration("15m"), Some(900));
+        assert_eq!(parse_duration("1h"), Some(3600));
+        assert_eq!(parse_duration("2d"), Some(172_800));
+        assert_eq!(parse_duration("5w"), None);
+        assert_eq!(parse_duration("m"), None);
+    }
+}
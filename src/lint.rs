@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::config::{self, Condition, Field, Op, Source};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+pub struct Finding {
+    pub file: String,
+    /// 0 when the finding is about a whole file.
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    fn warn(source: &Source, message: String) -> Finding {
+        Finding {
+            file: source.file.clone(),
+            line: source.line,
+            severity: Severity::Warning,
+            message: format!("{}: {}", source.text, message),
+        }
+    }
+}
+
+/// One rule line split into the rule set it belongs to (`""`, `deny` or
+/// `quarantine`) and the conditions it stands for.
+struct Entry<'a> {
+    source: &'a Source,
+    section: &'a str,
+    conditions: Vec<Condition>,
+}
+
+/// Every syntax error in `path` and its includes, plus warnings for expired
+/// rules, duplicates, rules that can never match, overly broad allows and
+/// allows made redundant by a broader one. Sorted by file and line.
+pub fn check(path: &Path) -> Vec<Finding> {
+    let checked = config::check_allowlist(path);
+    let mut findings: Vec<Finding> = checked
+        .errors
+        .into_iter()
+        .map(|(file, line, message)| Finding {
+            file,
+            line,
+            severity: Severity::Error,
+            message,
+        })
+        .collect();
+
+    for source in &checked.expired {
+        let expires = source.meta.expires.as_deref().unwrap_or("-");
+        findings.push(Finding::warn(source, format!("expired on {}, ignored", expires)));
+    }
+
+    let mut first_seen: HashMap<&str, &Source> = HashMap::new();
+    let mut entries = Vec::new();
+    for source in &checked.lines {
+        if let Some(first) = first_seen.get(source.text.as_str()) {
+            findings.push(Finding::warn(source, format!("duplicate of {}:{}", first.file, first.line)));
+            continue;
+        }
+        first_seen.insert(&source.text, source);
+
+        let (section, rest) = match source.text.split_once(':') {
+            Some((section @ ("deny" | "quarantine"), rest)) => (section, rest),
+            _ => ("", source.text.as_str()),
+        };
+        if let Some(conditions) = config::line_conditions(rest) {
+            entries.push(Entry {
+                source,
+                section,
+                conditions,
+            });
+        }
+    }
+
+    for (idx, entry) in entries.iter().enumerate() {
+        if let Some(message) = never_matches(entry) {
+            findings.push(Finding::warn(entry.source, message));
+        }
+        if entry.section.is_empty() {
+            if let Some(message) = too_broad(&entry.conditions) {
+                findings.push(Finding::warn(entry.source, message));
+            }
+        }
+        let broader = entries
+            .iter()
+            .enumerate()
+            .find(|(other, broader)| *other != idx && shadows(broader, entry, *other < idx));
+        if let Some((_, broader)) = broader {
+            findings.push(Finding::warn(
+                entry.source,
+                format!(
+                    "already covered by {} ({}:{})",
+                    broader.source.text, broader.source.file, broader.source.line
+                ),
+            ));
+        }
+    }
+
+    findings.sort_by(|a, b| a.file.cmp(&b.file).then(a.line.cmp(&b.line)));
+    findings
+}
+
+/// The `--check-config` exit code: 2 with any error, 1 with only warnings
+/// and 0 when clean.
+pub fn exit_code(findings: &[Finding]) -> i32 {
+    match findings.iter().map(|f| f.severity).max() {
+        Some(Severity::Error) => 2,
+        Some(Severity::Warning) => 1,
+        None => 0,
+    }
+}
+
+fn never_matches(entry: &Entry) -> Option<String> {
+    for cond in &entry.conditions {
+        if !matches!(cond.op, Op::Equals) {
+            continue;
+        }
+        match cond.field {
+            Field::Hash if cond.value.len() != 64 || !cond.value.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                return Some("not a SHA-256 hex digest, can never match".to_string());
+            }
+            Field::BuildId if cond.value.len() % 2 != 0 || !cond.value.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                return Some("not a hex build-id, can never match".to_string());
+            }
+            // A deny for something not installed yet is still useful.
+            Field::Path if entry.section != "deny" && !Path::new(&cond.value).exists() => {
+                return Some(format!("{} does not exist, can never match", cond.value));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Single-condition allows that let through far more than they name.
+fn too_broad(conditions: &[Condition]) -> Option<String> {
+    let [cond] = conditions else {
+        return None;
+    };
+    match (cond.field, &cond.op) {
+        (Field::Uid, _) if cond.value == "0" => Some("allows every process running as root".to_string()),
+        (Field::Ppid, _) if cond.value == "1" => Some("allows everything started by init/launchd".to_string()),
+        (Field::Arg, Op::Equals | Op::Contains) if cond.value.chars().count() <= 1 => {
+            Some("one-character arg matches almost any command line".to_string())
+        }
+        // Anyone can stamp any build-id on a binary they build.
+        (Field::BuildId, _) => {
+            Some("build-id is not a trust signal; pair it with hash=, package= or path=".to_string())
+        }
+        (_, Op::Glob(pattern) | Op::Regex(pattern)) if pattern.is_match("") && pattern.is_match("/any/thing") => {
+            Some("pattern matches everything".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// True when `broader` is another line of the same rule set whose every
+/// condition already holds whenever `entry`'s conditions do. Of two
+/// equivalent lines only the one loaded later is shadowed.
+fn shadows(broader: &Entry, entry: &Entry, loaded_first: bool) -> bool {
+    if broader.section != entry.section {
+        return false;
+    }
+    let covers = |b: &Entry, e: &Entry| {
+        b.conditions
+            .iter()
+            .all(|bc| e.conditions.iter().any(|ec| implies(ec, bc)))
+    };
+    covers(broader, entry) && (loaded_first || !covers(entry, broader))
+}
+
+/// True when `cond` holding guarantees `other` holds.
+fn implies(cond: &Condition, other: &Condition) -> bool {
+    if cond.scope != other.scope || cond.field != other.field {
+        return false;
+    }
+    match (&cond.op, &other.op) {
+        (Op::Equals, Op::Equals) => cond.value == other.value,
+        (Op::Equals | Op::Contains, Op::Contains) => cond.value.contains(other.value.as_str()),
+        // An `arg=` names one argument while patterns see the joined line.
+        (Op::Equals, Op::Glob(pattern) | Op::Regex(pattern)) if cond.field != Field::Arg => {
+            pattern.is_match(&cond.value)
+        }
+        (Op::Glob(_), Op::Glob(_)) | (Op::Regex(_), Op::Regex(_)) => cond.value == other.value,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn lint(name: &str, content: &str) -> Vec<Finding> {
+        let path = std::env::temp_dir().join(format!("zen-lint-{}-{}.txt", name, std::process::id()));
+        fs::write(&path, content).unwrap();
+        let findings = check(&path);
+        fs::remove_file(&path).unwrap();
+        findings
+    }
+
+    fn messages(findings: &[Finding]) -> Vec<(usize, &str)> {
+        findings.iter().map(|f| (f.line, f.message.as_str())).collect()
+    }
+
+    #[test]
+    fn exit_code_follows_the_worst_finding() {
+        let clean = lint("clean", "name:zsh\nrule:name=cargo & uid=1000\n");
+        assert!(clean.is_empty(), "{:?}", messages(&clean));
+        assert_eq!(exit_code(&clean), 0);
+
+        let warned = lint("warned", "name:zsh\nuid:0\n");
+        assert_eq!(exit_code(&warned), 1);
+
+        let broken = lint("broken", "name:zsh\nuid:0\nbogus:1\n");
+        assert_eq!(exit_code(&broken), 2);
+        assert!(broken.iter().any(|f| f.severity == Severity::Error && f.line == 3));
+    }
+
+    #[test]
+    fn flags_duplicates_dead_and_broad_rules() {
+        let findings = lint("kinds", "name:zsh\nname:zsh\nhash:abc\nppid:1\narg:x\nbuildid:deadbeef\n");
+        let file = findings[0].file.clone();
+        assert_eq!(
+            messages(&findings),
+            vec![
+                (2, format!("name:zsh: duplicate of {}:1", file).as_str()),
+                (3, "hash:abc: not a SHA-256 hex digest, can never match"),
+                (4, "ppid:1: allows everything started by init/launchd"),
+                (5, "arg:x: one-character arg matches almost any command line"),
+                (6, "buildid:deadbeef: build-id is not a trust signal; pair it with hash=, package= or path="),
+            ]
+        );
+        let everything = lint("everything", "name~:**\n");
+        assert_eq!(messages(&everything), vec![(1, "name~:**: pattern matches everything")]);
+    }
+
+    #[test]
+    fn flags_rules_shadowed_by_broader_ones() {
+        let findings = lint(
+            "shadow",
+            "rule:name=cargo & uid=1000\nname:cargo\nname~:node*\nname:node18\ndeny:name:node18\nrule:name=vim & arg~--clean\nrule:name=vim & arg~--clean & uid=1000\n",
+        );
+        let shadowed: Vec<usize> = findings
+            .iter()
+            .filter(|f| f.message.contains("already covered by"))
+            .map(|f| f.line)
+            .collect();
+        // The deny is another rule set, so it shadows nothing.
+        assert_eq!(shadowed, vec![1, 4, 7]);
+        assert!(findings[0].message.contains("already covered by name:cargo"));
+    }
+}
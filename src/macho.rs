@@ -0,0 +1,643 @@
+// Code signatures are only looked up in Mach-O files off Linux.
+#![cfg_attr(target_os = "linux", allow(dead_code))]
+
+use std::fs::File;
+use std::io::{Read, Seek};
+
+use crate::binary::{self, be32, be64, c_string, read_at, MAX_ARCHS, MAX_COMMANDS, MAX_DEPTH, MAX_SIGNATURE};
+use crate::sha256::{self, Sha256};
+
+const FAT_MAGIC: u32 = 0xcafe_babe;
+const FAT_MAGIC_64: u32 = 0xcafe_babf;
+const MH_MAGIC: u32 = 0xfeed_face;
+const MH_MAGIC_64: u32 = 0xfeed_facf;
+const LC_CODE_SIGNATURE: u32 = 0x1d;
+const CPU_TYPE_X86_64: u32 = 0x0100_0007;
+const CPU_TYPE_ARM64: u32 = 0x0100_000c;
+
+const CSMAGIC_REQUIREMENT: u32 = 0xfade_0c00;
+const CSMAGIC_REQUIREMENTS: u32 = 0xfade_0c01;
+const CSMAGIC_CODEDIRECTORY: u32 = 0xfade_0c02;
+const CSMAGIC_EMBEDDED_SIGNATURE: u32 = 0xfade_0cc0;
+const CSMAGIC_BLOBWRAPPER: u32 = 0xfade_0b01;
+const CSSLOT_CODEDIRECTORY: u32 = 0;
+const CSSLOT_REQUIREMENTS: u32 = 2;
+const CSSLOT_ALTERNATE_CODEDIRECTORIES: u32 = 0x1000;
+const CSSLOT_SIGNATURESLOT: u32 = 0x1_0000;
+const DESIGNATED_REQUIREMENT: u32 = 3;
+const CS_ADHOC: u32 = 0x2;
+const CS_RUNTIME: u32 = 0x1_0000;
+const CS_HASHTYPE_SHA256: u8 = 2;
+/// The kernel identifies code by the first 20 bytes of its CodeDirectory's
+/// hash.
+const CDHASH_LEN: usize = 20;
+/// First CodeDirectory version with a team identifier.
+const CD_VERSION_TEAM: u32 = 0x2_0200;
+
+/// OIDs of the name attributes `codesign` shows.
+const OID_COMMON_NAME: &[u8] = &[0x55, 0x04, 0x03];
+const OID_ORGANIZATIONAL_UNIT: &[u8] = &[0x55, 0x04, 0x0b];
+
+/// What the embedded code signature of a Mach-O executable says, as
+/// `codesign -dv` would print it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CodeSignature {
+    /// The signing identifier, usually a bundle ID.
+    pub identifier: String,
+    pub team_id: Option<String>,
+    /// Common names of the certificate chain, signing certificate first.
+    /// Empty for ad-hoc signatures.
+    pub authorities: Vec<String>,
+    /// The designated requirement in `codesign -d -r-` syntax.
+    pub requirement: Option<String>,
+    pub hardened_runtime: bool,
+    pub adhoc: bool,
+    /// Hex cdhashes of the SHA-256 CodeDirectories, which tie this file to
+    /// the code the kernel validated in a running process.
+    pub cdhashes: Vec<String>,
+}
+
+/// The code signature of the file at `path`, or `None` when it is not a
+/// signed Mach-O file or cannot be read.
+pub fn inspect(path: &str) -> Option<CodeSignature> {
+    let mut file = File::open(path).ok()?;
+    parse(&mut file).ok().flatten()
+}
+
+/// `Ok(None)` for a Mach-O file without a signature. A universal binary is
+/// read through the slice for this machine's architecture when it is
+/// signed, otherwise through the first signed slice.
+pub fn parse<R: Read + Seek>(file: &mut R) -> Result<Option<CodeSignature>, String> {
+    let header = read_at(file, 0, 8, MAX_COMMANDS)?;
+    let magic = be32(&header, 0);
+    if magic != FAT_MAGIC && magic != FAT_MAGIC_64 {
+        return parse_slice(file, 0);
+    }
+    let count = be32(&header, 4);
+    if count == 0 || count > MAX_ARCHS {
+        return Err("not a Mach-O file".to_string());
+    }
+    let size = if magic == FAT_MAGIC_64 { 32 } else { 20 };
+    let table = read_at(file, 8, u64::from(count) * size, MAX_COMMANDS)?;
+    let mut slices: Vec<(u32, u64)> = table
+        .chunks_exact(size as usize)
+        .map(|arch| {
+            let offset = if magic == FAT_MAGIC_64 { be64(arch, 8) } else { u64::from(be32(arch, 8)) };
+            (be32(arch, 0), offset)
+        })
+        .collect();
+    let host = if cfg!(target_arch = "aarch64") { CPU_TYPE_ARM64 } else { CPU_TYPE_X86_64 };
+    slices.sort_by_key(|(cpu, _)| *cpu != host);
+    for (_, offset) in slices {
+        if let Some(signature) = parse_slice(file, offset)? {
+            return Ok(Some(signature));
+        }
+    }
+    Ok(None)
+}
+
+/// One thin Mach-O image starting at `base`.
+fn parse_slice<R: Read + Seek>(file: &mut R, base: u64) -> Result<Option<CodeSignature>, String> {
+    let header = read_at(file, base, 28, MAX_COMMANDS)?;
+    let big_endian = match u32::from_le_bytes(header[..4].try_into().unwrap()) {
+        MH_MAGIC | MH_MAGIC_64 => false,
+        _ if matches!(be32(&header, 0), MH_MAGIC | MH_MAGIC_64) => true,
+        _ => return Err("not a Mach-O file".to_string()),
+    };
+    let u32_at = |b: &[u8], at: usize| binary::u32_at(b, at, big_endian);
+    let header_size = if u32_at(&header, 0) == MH_MAGIC_64 { 32 } else { 28 };
+    let (ncmds, sizeofcmds) = (u32_at(&header, 16), u64::from(u32_at(&header, 20)));
+    if sizeofcmds > MAX_COMMANDS {
+        return Err("load commands too large".to_string());
+    }
+
+    let commands = read_at(file, base + header_size, sizeofcmds, MAX_COMMANDS)?;
+    let mut at = 0;
+    for _ in 0..ncmds {
+        if at + 8 > commands.len() {
+            return Err("truncated load commands".to_string());
+        }
+        let (cmd, cmdsize) = (u32_at(&commands, at), u32_at(&commands, at + 4) as usize);
+        if cmdsize < 8 || at + cmdsize > commands.len() {
+            return Err("bad load command size".to_string());
+        }
+        if cmd == LC_CODE_SIGNATURE && cmdsize >= 16 {
+            let (offset, size) = (u32_at(&commands, at + 8), u32_at(&commands, at + 12));
+            if u64::from(size) > MAX_SIGNATURE {
+                return Err("code signature too large".to_string());
+            }
+            let blob = read_at(file, base + u64::from(offset), u64::from(size), MAX_SIGNATURE)?;
+            return read_superblob(&blob).map(Some);
+        }
+        at += cmdsize;
+    }
+    Ok(None)
+}
+
+// Everything inside the signature is big-endian, whatever the image is:
+//
+//   SuperBlob  magic length count [type offset]*
+//   each blob  magic length ...
+//
+// Offsets are relative to the start of the enclosing blob.
+
+fn read_superblob(data: &[u8]) -> Result<CodeSignature, String> {
+    let superblob = blob(data, 0, CSMAGIC_EMBEDDED_SIGNATURE).ok_or("bad signature superblob")?;
+    let slots = index(superblob).ok_or("bad signature index")?;
+    let slot = |wanted: u32| slots.iter().find(|(kind, _)| *kind == wanted).map(|(_, offset)| *offset as usize);
+
+    let directories: Vec<&[u8]> = [CSSLOT_CODEDIRECTORY]
+        .into_iter()
+        .chain((0..5).map(|i| CSSLOT_ALTERNATE_CODEDIRECTORIES + i))
+        .filter_map(|kind| blob(superblob, slot(kind)?, CSMAGIC_CODEDIRECTORY))
+        .filter(|cd| cd.len() >= 44)
+        .collect();
+    let directory = *directories.first().ok_or("no code directory")?;
+    let (version, flags) = (be32(directory, 8), be32(directory, 12));
+    let mut signature = CodeSignature {
+        identifier: c_string(directory, be32(directory, 20) as usize).ok_or("bad signing identifier")?,
+        hardened_runtime: flags & CS_RUNTIME != 0,
+        adhoc: flags & CS_ADHOC != 0,
+        cdhashes: directories
+            .iter()
+            .filter(|cd| cd[37] == CS_HASHTYPE_SHA256)
+            .map(|cd| {
+                let mut sha = Sha256::new();
+                sha.update(cd);
+                sha256::hex(&sha.finish()[..CDHASH_LEN])
+            })
+            .collect(),
+        ..CodeSignature::default()
+    };
+    if version >= CD_VERSION_TEAM && directory.len() >= 52 {
+        let offset = be32(directory, 48) as usize;
+        if offset != 0 {
+            signature.team_id = c_string(directory, offset);
+        }
+    }
+
+    signature.requirement = slot(CSSLOT_REQUIREMENTS)
+        .and_then(|at| blob(superblob, at, CSMAGIC_REQUIREMENTS))
+        .and_then(|set| {
+            let (_, offset) = index(set)?.into_iter().find(|(kind, _)| *kind == DESIGNATED_REQUIREMENT)?;
+            let requirement = blob(set, offset as usize, CSMAGIC_REQUIREMENT)?;
+            // Only the expression form (kind 1) has ever been used.
+            if requirement.len() < 12 || be32(requirement, 8) != 1 {
+                return None;
+            }
+            Expression { bytes: &requirement[12..], at: 0, depth: 0 }.parse().map(|(text, _)| text)
+        });
+
+    // An empty CMS wrapper is how `codesign -s -` marks an ad-hoc signature.
+    let cms = slot(CSSLOT_SIGNATURESLOT)
+        .and_then(|at| blob(superblob, at, CSMAGIC_BLOBWRAPPER))
+        .map(|wrapper| &wrapper[8..])
+        .unwrap_or_default();
+    if cms.is_empty() {
+        signature.adhoc = true;
+    } else {
+        signature.authorities = authorities(cms).ok_or("bad CMS signature")?;
+    }
+    Ok(signature)
+}
+
+/// The blob at `at` in `data` if it has `magic` and fits.
+fn blob(data: &[u8], at: usize, magic: u32) -> Option<&[u8]> {
+    let header = data.get(at..at.checked_add(8)?)?;
+    if be32(header, 0) != magic {
+        return None;
+    }
+    let length = be32(header, 4) as usize;
+    if length < 8 {
+        return None;
+    }
+    data.get(at..at.checked_add(length)?)
+}
+
+/// The `(type, offset)` index that follows the magic, length and count of a
+/// SuperBlob or requirement set.
+fn index(blob: &[u8]) -> Option<Vec<(u32, u32)>> {
+    let count = be32(blob.get(..12)?, 8) as usize;
+    let table = blob.get(12..12usize.checked_add(count.checked_mul(8)?)?)?;
+    Some(table.chunks_exact(8).map(|entry| (be32(entry, 0), be32(entry, 4))).collect())
+}
+
+/// Decompiles a requirement expression the way `codesign -d -r-` prints it.
+struct Expression<'a> {
+    bytes: &'a [u8],
+    at: usize,
+    depth: usize,
+}
+
+/// How tightly an expression binds, for parenthesizing: `or` < `and` < the rest.
+const OR: u8 = 1;
+const AND: u8 = 2;
+const ATOM: u8 = 3;
+
+impl<'a> Expression<'a> {
+    fn parse(&mut self) -> Option<(String, u8)> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return None;
+        }
+        // The top byte holds flags for forward compatibility.
+        let text = match self.u32()? & 0x00ff_ffff {
+            0 => "never".to_string(),
+            1 => "always".to_string(),
+            2 => format!("identifier {}", self.string()?),
+            3 => "anchor apple".to_string(),
+            4 => {
+                let slot = self.slot()?;
+                format!("certificate {} = {}", slot, self.hash()?)
+            }
+            5 => {
+                let key = self.string()?;
+                format!("info[{}] = {}", key, self.string()?)
+            }
+            op @ (6 | 7) => {
+                let (bind, word) = if op == 6 { (AND, "and") } else { (OR, "or") };
+                let left = self.operand(bind)?;
+                let right = self.operand(bind)?;
+                self.depth -= 1;
+                return Some((format!("{} {} {}", left, word, right), bind));
+            }
+            8 => format!("cdhash {}", self.hash()?),
+            9 => format!("! {}", self.operand(ATOM)?),
+            10 => {
+                let key = self.string()?;
+                format!("info[{}]{}", key, self.matcher()?)
+            }
+            11 => {
+                let slot = self.slot()?;
+                let key = self.field_name()?;
+                format!("certificate {}[{}]{}", slot, key, self.matcher()?)
+            }
+            12 => format!("certificate {} trusted", self.slot()?),
+            13 => "anchor trusted".to_string(),
+            op @ (14 | 17 | 22) => {
+                let slot = self.slot()?;
+                let oid = oid_text(self.data()?)?;
+                let kind = match op {
+                    14 => "field",
+                    17 => "policy",
+                    _ => "timestamp",
+                };
+                format!("certificate {}[{}.{}]{}", slot, kind, oid, self.matcher()?)
+            }
+            15 => "anchor apple generic".to_string(),
+            16 => {
+                let key = self.string()?;
+                format!("entitlement[{}]{}", key, self.matcher()?)
+            }
+            18 => format!("anchor {}", self.string()?),
+            19 => format!("({})", self.string()?),
+            20 => format!("platform = {}", self.u32()?),
+            21 => "notarized".to_string(),
+            23 => "legacy".to_string(),
+            _ => return None,
+        };
+        self.depth -= 1;
+        Some((text, ATOM))
+    }
+
+    /// A sub-expression, parenthesized when it binds looser than `bind`.
+    fn operand(&mut self, bind: u8) -> Option<String> {
+        let (text, strength) = self.parse()?;
+        Some(if strength < bind { format!("({})", text) } else { text })
+    }
+
+    fn matcher(&mut self) -> Option<String> {
+        Some(match self.u32()? {
+            0 => " /* exists */".to_string(),
+            1 => format!(" = {}", self.string()?),
+            2 => format!(" ~ {}", self.string()?),
+            3 => format!(" = {}*", self.string()?),
+            4 => format!(" = *{}", self.string()?),
+            5 => format!(" < {}", self.string()?),
+            6 => format!(" > {}", self.string()?),
+            7 => format!(" <= {}", self.string()?),
+            8 => format!(" >= {}", self.string()?),
+            op @ 9..=13 => {
+                let word = ["=", "<", ">", "<=", ">="][op as usize - 9];
+                format!(" {} timestamp {}", word, self.u64()?)
+            }
+            14 => " absent".to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Certificate positions: 0 is the leaf, -1 the anchor, others count up
+    /// from the leaf.
+    fn slot(&mut self) -> Option<String> {
+        Some(match self.u32()? as i32 {
+            0 => "leaf".to_string(),
+            -1 => "root".to_string(),
+            n => n.to_string(),
+        })
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        let value = be32(self.bytes.get(self.at..self.at + 4)?, 0);
+        self.at += 4;
+        Some(value)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from(self.u32()?) << 32 | u64::from(self.u32()?))
+    }
+
+    /// A length-prefixed byte string, padded to four bytes.
+    fn data(&mut self) -> Option<&'a [u8]> {
+        let len = self.u32()? as usize;
+        let data = self.bytes.get(self.at..self.at.checked_add(len)?)?;
+        self.at += len.div_ceil(4) * 4;
+        Some(data)
+    }
+
+    /// Quoted unless it is a plain word, as `codesign` prints it.
+    fn string(&mut self) -> Option<String> {
+        self.word(|c| c.is_ascii_alphanumeric())
+    }
+
+    /// Certificate field names like `subject.OU` are left bare.
+    fn field_name(&mut self) -> Option<String> {
+        self.word(|c| c.is_ascii_alphanumeric() || c == '.')
+    }
+
+    fn word(&mut self, bare: fn(char) -> bool) -> Option<String> {
+        let text = String::from_utf8_lossy(self.data()?).into_owned();
+        if !text.is_empty() && text.chars().all(bare) {
+            Some(text)
+        } else {
+            Some(format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\"")))
+        }
+    }
+
+    fn hash(&mut self) -> Option<String> {
+        let hex: String = self.data()?.iter().map(|b| format!("{:02x}", b)).collect();
+        Some(format!("H\"{}\"", hex))
+    }
+}
+
+/// Dotted form of a DER-encoded OID.
+fn oid_text(bytes: &[u8]) -> Option<String> {
+    let mut arcs = Vec::new();
+    let mut value: u64 = 0;
+    for &byte in bytes {
+        value = value.checked_mul(128)? | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            arcs.push(value);
+            value = 0;
+        }
+    }
+    // The first subidentifier packs two arcs.
+    let first = *arcs.first()?;
+    let top = (first / 40).min(2);
+    arcs[0] = first - 40 * top;
+    arcs.insert(0, top);
+    Some(arcs.iter().map(u64::to_string).collect::<Vec<_>>().join("."))
+}
+
+// The CMS blob is a PKCS#7 ContentInfo holding SignedData:
+//
+//   SEQUENCE { OID signedData, [0] { SEQUENCE {
+//       INTEGER version, SET digestAlgorithms, SEQUENCE encapContentInfo,
+//       [0] certificates, [1] crls?, SET signerInfos } } }
+//
+// Apple's signer writes it in BER with indefinite lengths.
+
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_SET: u8 = 0x31;
+const TAG_OID: u8 = 0x06;
+const TAG_CONTEXT_0: u8 = 0xa0;
+
+/// One BER element: its tag, its contents, and what follows it.
+struct Element<'a> {
+    tag: u8,
+    contents: &'a [u8],
+    rest: &'a [u8],
+}
+
+fn element(bytes: &[u8], depth: usize) -> Option<Element<'_>> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let (&tag, after_tag) = bytes.split_first()?;
+    // High tag numbers never occur in certificates.
+    if tag & 0x1f == 0x1f {
+        return None;
+    }
+    let (&first, after_len) = after_tag.split_first()?;
+    if first == 0x80 {
+        // Indefinite length: children up to an end-of-contents marker.
+        let mut rest = after_len;
+        while !rest.starts_with(&[0, 0]) {
+            rest = element(rest, depth + 1)?.rest;
+        }
+        let len = after_len.len() - rest.len();
+        return Some(Element { tag, contents: &after_len[..len], rest: &rest[2..] });
+    }
+    let (len, body) = if first & 0x80 == 0 {
+        (first as usize, after_len)
+    } else {
+        let count = (first & 0x7f) as usize;
+        if count > 4 || after_len.len() < count {
+            return None;
+        }
+        let len = after_len[..count].iter().fold(0usize, |len, &b| len << 8 | b as usize);
+        (len, &after_len[count..])
+    };
+    if body.len() < len {
+        return None;
+    }
+    Some(Element { tag, contents: &body[..len], rest: &body[len..] })
+}
+
+/// The elements of a constructed value, in order.
+fn children(mut bytes: &[u8]) -> Option<Vec<Element<'_>>> {
+    let mut out = Vec::new();
+    while !bytes.is_empty() {
+        let child = element(bytes, 0)?;
+        bytes = child.rest;
+        out.push(child);
+    }
+    Some(out)
+}
+
+/// The child with `tag`, requiring it to be there.
+fn expect(bytes: &[u8], tag: u8) -> Option<&[u8]> {
+    let first = element(bytes, 0)?;
+    (first.tag == tag).then_some(first.contents)
+}
+
+struct Certificate<'a> {
+    serial: &'a [u8],
+    issuer: &'a [u8],
+    subject: &'a [u8],
+    name: Option<String>,
+}
+
+/// The certificate chain's common names from the signing certificate up.
+fn authorities(cms: &[u8]) -> Option<Vec<String>> {
+    let content_info = children(expect(cms, TAG_SEQUENCE)?)?;
+    let signed_data = match content_info.as_slice() {
+        [oid, explicit, ..] if oid.tag == TAG_OID && explicit.tag == TAG_CONTEXT_0 => {
+            children(expect(explicit.contents, TAG_SEQUENCE)?)?
+        }
+        _ => return None,
+    };
+    let Some(certificates) = signed_data.iter().skip(3).find(|e| e.tag == TAG_CONTEXT_0) else {
+        return Some(Vec::new());
+    };
+    let certificates: Vec<Certificate> = children(certificates.contents)?
+        .iter()
+        .map(|cert| certificate(cert.contents))
+        .collect::<Option<_>>()?;
+
+    // The leaf is the certificate the signer names by issuer and serial
+    // number, or else the one that issued no other. Then follow each issuer
+    // up to a self-signed root.
+    let signer = signed_data.last().filter(|e| e.tag == TAG_SET).and_then(|infos| signer_id(infos.contents));
+    let issued_another = |cert: &Certificate| {
+        certificates.iter().any(|other| other.issuer == cert.subject && other.subject != cert.subject)
+    };
+    let leaf = match signer {
+        Some((issuer, serial)) => certificates.iter().find(|cert| cert.issuer == issuer && cert.serial == serial),
+        None => None,
+    };
+    let Some(mut current) = leaf.or_else(|| certificates.iter().find(|cert| !issued_another(cert))) else {
+        return Some(Vec::new());
+    };
+    let mut chain = Vec::new();
+    for _ in 0..certificates.len() {
+        chain.push(current.name.clone().unwrap_or_else(|| "?".to_string()));
+        match certificates.iter().find(|cert| cert.subject == current.issuer && cert.subject != current.subject) {
+            Some(issuer) => current = issuer,
+            None => break,
+        }
+    }
+    Some(chain)
+}
+
+/// Issuer name and serial number of the first SignerInfo. Signers that name
+/// their certificate by key identifier give `None`.
+fn signer_id(infos: &[u8]) -> Option<(&[u8], &[u8])> {
+    let info = children(expect(infos, TAG_SEQUENCE)?)?;
+    let sid = info.get(1).filter(|sid| sid.tag == TAG_SEQUENCE)?;
+    let [issuer, serial] = children(sid.contents)?.try_into().ok()?;
+    (issuer.tag == TAG_SEQUENCE).then_some((issuer.contents, serial.contents))
+}
+
+fn certificate(der: &[u8]) -> Option<Certificate<'_>> {
+    let tbs = children(expect(der, TAG_SEQUENCE)?)?;
+    // The version is an optional [0] ahead of the serial number.
+    let fields = if tbs.first()?.tag == TAG_CONTEXT_0 { &tbs[1..] } else { &tbs[..] };
+    let [serial, _algorithm, issuer, _validity, subject, ..] = fields else {
+        return None;
+    };
+    if issuer.tag != TAG_SEQUENCE || subject.tag != TAG_SEQUENCE {
+        return None;
+    }
+    let attribute = |oid| name_attribute(subject.contents, oid);
+    Some(Certificate {
+        serial: serial.contents,
+        issuer: issuer.contents,
+        subject: subject.contents,
+        name: attribute(OID_COMMON_NAME).or_else(|| attribute(OID_ORGANIZATIONAL_UNIT)),
+    })
+}
+
+/// The first value of attribute `oid` in a distinguished name.
+fn name_attribute(name: &[u8], oid: &[u8]) -> Option<String> {
+    for rdn in children(name)? {
+        if rdn.tag != TAG_SET {
+            continue;
+        }
+        for pair in children(rdn.contents)? {
+            let [key, value] = children(pair.contents)?.try_into().ok()?;
+            if key.tag == TAG_OID && key.contents == oid {
+                return Some(directory_string(value.tag, value.contents));
+            }
+        }
+    }
+    None
+}
+
+/// BMPString is UTF-16; the other string types are read as UTF-8.
+fn directory_string(tag: u8, bytes: &[u8]) -> String {
+    if tag == 0x1e {
+        let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+        return String::from_utf16_lossy(&units);
+    }
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // Built by testdata/macho/make-samples.py.
+    const UNIVERSAL: &[u8] = include_bytes!("../testdata/macho/signed-universal");
+    const ADHOC: &[u8] = include_bytes!("../testdata/macho/adhoc-arm64");
+
+    // `shasum -a 256` of each slice's CodeDirectory, cut to 20 bytes.
+    const CDHASH_X86_64: &str = "5619489a6c40e0b149604eed4b498c5dc67b48ef";
+    const CDHASH_ARM64: &str = "878f8f980f8e1755b93fb74cfaa229c037c979f8";
+
+    fn developer_id(cdhash: &str) -> CodeSignature {
+        CodeSignature {
+            identifier: "dev.zen.sample".to_string(),
+            team_id: Some("ABCDE12345".to_string()),
+            authorities: vec![
+                "Developer ID Application: Zen Test (ABCDE12345)".to_string(),
+                "Zen Test Developer ID Certification Authority".to_string(),
+                "Zen Test Root CA".to_string(),
+            ],
+            requirement: Some(
+                "identifier \"dev.zen.sample\" and anchor apple generic \
+                 and certificate 1[field.1.2.840.113635.100.6.2.6] /* exists */ \
+                 and certificate leaf[field.1.2.840.113635.100.6.1.13] /* exists */ \
+                 and certificate leaf[subject.OU] = ABCDE12345"
+                    .to_string(),
+            ),
+            hardened_runtime: true,
+            adhoc: false,
+            cdhashes: vec![cdhash.to_string()],
+        }
+    }
+
+    #[test]
+    fn reads_every_slice_of_a_universal_binary() {
+        let host = if cfg!(target_arch = "aarch64") { CDHASH_ARM64 } else { CDHASH_X86_64 };
+        assert_eq!(parse(&mut Cursor::new(UNIVERSAL)).unwrap(), Some(developer_id(host)));
+        // x86_64 carries BER with indefinite lengths, arm64 plain DER.
+        for (arch, cdhash) in [CDHASH_X86_64, CDHASH_ARM64].into_iter().enumerate() {
+            let offset = be32(UNIVERSAL, 8 + 20 * arch + 8);
+            let slice = parse_slice(&mut Cursor::new(UNIVERSAL), u64::from(offset)).unwrap();
+            assert_eq!(slice, Some(developer_id(cdhash)), "slice {}", arch);
+        }
+    }
+
+    #[test]
+    fn reads_adhoc_and_unsigned_images() {
+        let adhoc = parse(&mut Cursor::new(ADHOC)).unwrap().unwrap();
+        assert_eq!(adhoc.identifier, "sample-adhoc");
+        assert!(adhoc.adhoc && !adhoc.hardened_runtime);
+        assert!(adhoc.team_id.is_none() && adhoc.authorities.is_empty() && adhoc.requirement.is_none());
+        assert_eq!(adhoc.cdhashes, ["057d5d80a7c18960d404e28af3a8cae5d6af90df"]);
+
+        // The same image with its load commands dropped.
+        let mut unsigned = ADHOC[..32].to_vec();
+        unsigned[16..24].fill(0);
+        assert_eq!(parse(&mut Cursor::new(unsigned)).unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(parse(&mut Cursor::new(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0".to_vec())).is_err());
+        // A Java class file shares the fat magic.
+        assert!(parse(&mut Cursor::new(b"\xca\xfe\xba\xbe\0\0\0\x34".to_vec())).is_err());
+        assert!(parse(&mut Cursor::new(&UNIVERSAL[..0x2200])).is_err());
+    }
+}
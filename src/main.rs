@@ -1,17 +1,35 @@
+mod binary;
+mod cache;
 mod config;
+mod elf;
+mod enforce;
 mod export;
 mod ipc;
+mod json;
+mod learn;
+mod lint;
+mod macho;
 mod monitor;
+mod package;
+mod pattern;
 mod platform;
 mod policy;
+mod quarantine;
+mod rulefile;
+mod schedule;
+mod settings;
+mod sha256;
+mod shutdown;
 mod signature;
+mod toml;
 mod tree;
+mod whatif;
 
 use std::collections::{BTreeMap, HashMap, HashSet};
 use std::env;
 use std::path::PathBuf;
-use std::sync::{Arc, RwLock};
-use std::time::Duration;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 
 struct ReportItem {
     pid: u32,
@@ -19,11 +37,27 @@ struct ReportItem {
     path: String,
     action: String,
     sig: Option<signature::SignatureInfo>,
+    elf: Option<elf::ElfInfo>,
 }
 
+/// Nice value applied by `limit:` rules with `action=renice`.
+const RENICE_VALUE: i32 = 19;
+/// How long a scheduled profile that failed to load waits before a retry.
+const SCHEDULE_RETRY: Duration = Duration::from_secs(60);
+
+/// How often the daemon writes its hash/signature cache back to disk.
+const CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
 struct Args {
     config_path: PathBuf,
     enforce: bool,
+    quarantine: bool,
+    term_grace_ms: Option<u64>,
+    kill_tree: bool,
+    what_if: Option<u64>,
+    learn_output: Option<PathBuf>,
+    learn_duration: Option<u64>,
+    learn: bool,
     stealth: bool,
     daemon: bool,
     interval_ms: u64,
@@ -37,10 +71,67 @@ struct Args {
     export_all_samples: bool,
     audit_log: Option<PathBuf>,
     profile: Option<String>,
-    set_profile: Option<String>,
+    schedule: Option<PathBuf>,
+    show_profile: Option<String>,
+    check_config: Option<PathBuf>,
+    convert_config: Option<PathBuf>,
+    explain: Option<u32>,
+    ipc_command: Option<String>,
     no_ignore_system: bool,
     show_help: bool,
     status: bool,
+    replay: Option<PathBuf>,
+    events: bool,
+    /// Where the daemon keeps hashes and signatures between runs.
+    cache_file: Option<PathBuf>,
+    cache_size: usize,
+    /// The `zen.toml` the values above were read from, if any.
+    settings: Option<PathBuf>,
+    print_effective_config: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Args {
+            config_path: PathBuf::from("allowlist.txt"),
+            enforce: false,
+            quarantine: false,
+            term_grace_ms: None,
+            kill_tree: false,
+            what_if: None,
+            learn_output: None,
+            learn_duration: None,
+            learn: false,
+            stealth: false,
+            daemon: false,
+            interval_ms: 1000,
+            cpu_threshold: 80.0,
+            ram_threshold: 20.0,
+            sustain_samples: 3,
+            sustain_seconds: None,
+            spike_delta: 30.0,
+            export_csv: None,
+            export_jsonl: None,
+            export_all_samples: false,
+            audit_log: None,
+            profile: None,
+            schedule: None,
+            show_profile: None,
+            check_config: None,
+            convert_config: None,
+            explain: None,
+            ipc_command: None,
+            no_ignore_system: false,
+            show_help: false,
+            status: false,
+            replay: None,
+            events: false,
+            cache_file: cache::default_path(),
+            cache_size: cache::DEFAULT_CAPACITY,
+            settings: None,
+            print_effective_config: false,
+        }
+    }
 }
 
 fn usage() -> &'static str {
@@ -50,10 +141,23 @@ Options:
   --daemon              Activar modo daemon (loop continuo)
   --status              Consultar estado y memoria del daemon activo
   --profile NAME        Cargar profiles/NAME.txt como allowlist
+  --schedule FILE       daemon: cambiar de perfil según horario (ver schedule.example)
+  --show-profile NAME   Mostrar las reglas efectivas de un perfil y de dónde vienen
+  --check-config FILE   Revisar una allowlist: errores y avisos (exit 0 ok, 1 avisos, 2 errores)
+  --convert-config FILE Reescribir una allowlist en TOML (default) o JSON según --output FILE
+  --explain PID         Qué regla permite o marca a PID (con --daemon: pregunta al daemon activo)
   --audit-log [FILE]    Log de eventos (default: audit.log)
   --no-ignore-system    No auto-detectar procesos de macOS
   --config FILE         allowlist file (default: allowlist.txt)
   --enforce             kill unknown processes
+  --term-grace MS       enforce: SIGTERM, esperar MS, luego SIGKILL
+  --kill-tree           enforce: aplicar a todo el subárbol (hijos primero)
+  --what-if [SECS]      daemon en seco durante SECS (default: 300): resumen de lo que se habría matado
+  --learn               observar y proponer una allowlist (requiere --output)
+  --duration DUR        learn: tiempo de observación, ej. 90s, 15m, 1h (default: 5m)
+  --output FILE         learn / convert-config: archivo de allowlist a escribir
+  --quarantine          congelar procesos desconocidos (cgroup v2 o SIGSTOP) en vez de matarlos
+  --events              [linux] revisar cada exec vía proc connector (fallback: polling)
   --stealth             monitor anomalies only (CPU/RAM)
   --interval MS         sample interval in ms (default: 1000)
   --cpu-threshold PCT   CPU anomaly threshold (default: 80)
@@ -65,32 +169,38 @@ Options:
   --export-jsonl [FILE] export JSONL (default: export.jsonl)
   --export-all-samples  export every sample in stealth mode
   --set-profile NAME    [IPC] Cambiar perfil en caliente (daemon mode)
+  --reload              [IPC] Releer del disco el perfil activo y sus includes
+  --quarantine-list     [IPC] Listar procesos en cuarentena
+  --release PID         [IPC] Liberar un proceso de la cuarentena
+  --kill PID            [IPC] Matar un proceso en cuarentena
+  --cache-file FILE     daemon: caché de hashes y firmas (default: ~/.cache/zen/cache.jsonl)
+  --no-cache-file       daemon: no leer ni guardar la caché en disco
+  --cache-size N        máximo de archivos en la caché (default: 4096)
+  --replay FILE         leer procesos de un archivo de replay en vez del SO
+  --settings FILE       leer opciones de FILE (default: ~/.config/zen/zen.toml o /etc/zen/zen.toml)
+  --no-FLAG, --FLAG=false  apagar un interruptor que zen.toml enciende (daemon, enforce, quarantine,
+                        kill-tree, learn, stealth, events, export-all-samples; no-ignore-system=false)
+  --print-effective-config  mostrar la configuración final (zen.toml + flags) y salir
   -h, --help            show help\n"
 }
 
 fn parse_args() -> Result<Args, String> {
     let args: Vec<String> = env::args().skip(1).collect();
-    let mut parsed = Args {
-        config_path: PathBuf::from("allowlist.txt"),
-        enforce: false,
-        stealth: false,
-        daemon: false,
-        interval_ms: 1000,
-        cpu_threshold: 80.0,
-        ram_threshold: 20.0,
-        sustain_samples: 3,
-        sustain_seconds: None,
-        spike_delta: 30.0,
-        export_csv: None,
-        export_jsonl: None,
-        export_all_samples: false,
-        audit_log: None,
-        profile: None,
-        set_profile: None,
-        no_ignore_system: false,
-        show_help: false,
-        status: false,
+    let mut parsed = Args::default();
+
+    // The settings file goes in first so any flag below overrides it.
+    let settings_path = match args.iter().position(|arg| arg == "--settings") {
+        Some(idx) => Some(PathBuf::from(args.get(idx + 1).ok_or("missing --settings value")?)),
+        None => settings::default_path(),
     };
+    if let Some(path) = settings_path {
+        let settings = settings::Settings::load(&path)?;
+        for entry in &settings.entries {
+            apply_setting(&mut parsed, &entry.key, &entry.value)
+                .map_err(|err| format!("{}: line {}: {}: {}", path.display(), entry.line, entry.key, err))?;
+        }
+        parsed.settings = Some(settings.path);
+    }
 
     let mut idx = 0usize;
     while idx < args.len() {
@@ -102,6 +212,38 @@ fn parse_args() -> Result<Args, String> {
                 idx += 1;
             }
             "--enforce" => parsed.enforce = true,
+            "--quarantine" => parsed.quarantine = true,
+            "--term-grace" => {
+                let value = args.get(idx + 1).ok_or("missing --term-grace value")?;
+                parsed.term_grace_ms = Some(value.parse().map_err(|_| "bad --term-grace")?);
+                idx += 1;
+            }
+            "--kill-tree" => parsed.kill_tree = true,
+            "--learn" => {
+                parsed.learn = true;
+                parsed.daemon = true;
+            }
+            "--duration" => {
+                let value = args.get(idx + 1).ok_or("missing --duration value")?;
+                parsed.learn_duration = Some(learn::parse_duration(value).ok_or("bad --duration")?);
+                idx += 1;
+            }
+            "--output" => {
+                let value = args.get(idx + 1).ok_or("missing --output value")?;
+                parsed.learn_output = Some(PathBuf::from(value));
+                idx += 1;
+            }
+            "--what-if" => {
+                let mut secs = 300;
+                if let Some(value) = args.get(idx + 1) {
+                    if !value.starts_with('-') {
+                        secs = value.parse().map_err(|_| "bad --what-if")?;
+                        idx += 1;
+                    }
+                }
+                parsed.what_if = Some(secs);
+                parsed.daemon = true;
+            }
             "--stealth" => parsed.stealth = true,
             "--interval" => {
                 let value = args.get(idx + 1).ok_or("missing --interval value")?;
@@ -161,6 +303,7 @@ fn parse_args() -> Result<Args, String> {
                 parsed.export_all_samples = true;
             }
             "--daemon" => parsed.daemon = true,
+            "--events" => parsed.events = true,
             "--no-ignore-system" => parsed.no_ignore_system = true,
             "--audit-log" => {
                 if let Some(value) = args.get(idx + 1) {
@@ -176,25 +319,262 @@ fn parse_args() -> Result<Args, String> {
             }
             "--profile" => {
                 let value = args.get(idx + 1).ok_or("missing --profile value")?;
-                parsed.config_path = PathBuf::from(format!("profiles/{}.txt", value));
+                parsed.config_path = config::profile_path(value);
                 parsed.profile = Some(value.clone());
                 idx += 1;
             }
+            "--explain" => {
+                let value = args.get(idx + 1).ok_or("missing --explain value")?;
+                parsed.explain = Some(value.parse().map_err(|_| format!("invalid --explain pid: {}", value))?);
+                idx += 1;
+            }
+            "--check-config" => {
+                let value = args.get(idx + 1).ok_or("missing --check-config value")?;
+                parsed.check_config = Some(PathBuf::from(value));
+                idx += 1;
+            }
+            "--convert-config" => {
+                let value = args.get(idx + 1).ok_or("missing --convert-config value")?;
+                parsed.convert_config = Some(PathBuf::from(value));
+                idx += 1;
+            }
+            "--show-profile" => {
+                let value = args.get(idx + 1).ok_or("missing --show-profile value")?;
+                parsed.show_profile = Some(value.clone());
+                idx += 1;
+            }
+            "--schedule" => {
+                let value = args.get(idx + 1).ok_or("missing --schedule value")?;
+                parsed.schedule = Some(PathBuf::from(value));
+                idx += 1;
+            }
             "--set-profile" => {
                 let value = args.get(idx + 1).ok_or("missing --set-profile value")?;
-                parsed.set_profile = Some(value.clone());
+                parsed.ipc_command = Some(format!("SET_PROFILE {}", value));
+                idx += 1;
+            }
+            "--quarantine-list" => parsed.ipc_command = Some("QUARANTINE LIST".to_string()),
+            "--reload" => parsed.ipc_command = Some("RELOAD".to_string()),
+            "--release" => {
+                let value = args.get(idx + 1).ok_or("missing --release value")?;
+                parsed.ipc_command = Some(format!("RELEASE {}", value));
+                idx += 1;
+            }
+            "--kill" => {
+                let value = args.get(idx + 1).ok_or("missing --kill value")?;
+                parsed.ipc_command = Some(format!("KILL {}", value));
                 idx += 1;
             }
             "--status" => parsed.status = true,
+            "--replay" => {
+                let value = args.get(idx + 1).ok_or("missing --replay value")?;
+                parsed.replay = Some(PathBuf::from(value));
+                idx += 1;
+            }
+            "--cache-file" => {
+                let value = args.get(idx + 1).ok_or("missing --cache-file value")?;
+                parsed.cache_file = Some(PathBuf::from(value));
+                idx += 1;
+            }
+            "--no-cache-file" => parsed.cache_file = None,
+            "--cache-size" => {
+                let value = args.get(idx + 1).ok_or("missing --cache-size value")?;
+                parsed.cache_size = value.parse().ok().filter(|n| *n > 0).ok_or("bad --cache-size")?;
+                idx += 1;
+            }
+            "--settings" => idx += 1,
+            "--print-effective-config" => parsed.print_effective_config = true,
             "-h" | "--help" => parsed.show_help = true,
-            _ => return Err(format!("unknown argument: {}", arg)),
+            _ => match switch_flag(arg) {
+                Some((name, on)) => apply_setting(&mut parsed, name, &toml::Value::Bool(on))?,
+                None => return Err(format!("unknown argument: {}", arg)),
+            },
         }
         idx += 1;
     }
 
+    if (parsed.term_grace_ms.is_some() || parsed.kill_tree) && !parsed.enforce && parsed.what_if.is_none() {
+        return Err("--term-grace and --kill-tree require --enforce or --what-if".to_string());
+    }
+    if parsed.learn_duration.is_some() && !parsed.learn {
+        return Err("--duration requires --learn".to_string());
+    }
+    if parsed.learn_output.is_some() && !parsed.learn && parsed.convert_config.is_none() {
+        return Err("--output requires --learn or --convert-config".to_string());
+    }
+    if parsed.learn && parsed.learn_output.is_none() {
+        return Err("--learn requires --output FILE".to_string());
+    }
+    if parsed.learn && (parsed.enforce || parsed.quarantine || parsed.what_if.is_some()) {
+        return Err("--learn only observes; drop --enforce, --quarantine and --what-if".to_string());
+    }
+    if parsed.schedule.is_some() && (!parsed.daemon || parsed.learn) {
+        return Err("--schedule requires --daemon and cannot be combined with --learn".to_string());
+    }
+    if let (Some(pid), true) = (parsed.explain, parsed.daemon) {
+        parsed.ipc_command = Some(format!("EXPLAIN {}", pid));
+    }
+    if parsed.enforce && parsed.quarantine {
+        return Err("--enforce and --quarantine are exclusive".to_string());
+    }
+
     Ok(parsed)
 }
 
+/// Switches `zen.toml` can turn on, which `--no-NAME` or `--NAME=false` turn
+/// back off from the command line.
+const SWITCHES: &[&str] = &[
+    "daemon",
+    "enforce",
+    "quarantine",
+    "kill-tree",
+    "learn",
+    "stealth",
+    "events",
+    "export-all-samples",
+    "no-ignore-system",
+];
+
+/// `--no-NAME`, `--NAME=true` or `--NAME=false` for a name in `SWITCHES`.
+fn switch_flag(arg: &str) -> Option<(&str, bool)> {
+    if let Some((flag, value)) = arg.split_once('=') {
+        let name = flag.strip_prefix("--").filter(|name| SWITCHES.contains(name))?;
+        return match value {
+            "true" => Some((name, true)),
+            "false" => Some((name, false)),
+            _ => None,
+        };
+    }
+    // `no-ignore-system` is already a negation; it takes `=false` instead.
+    let name = arg.strip_prefix("--no-").filter(|name| SWITCHES.contains(name) && !name.starts_with("no-"))?;
+    Some((name, false))
+}
+
+/// Applies one `zen.toml` entry to `parsed` the way the matching flag would.
+/// Switches take `true`/`false`; flags with an optional value take either a
+/// boolean or the value.
+fn apply_setting(parsed: &mut Args, key: &str, value: &toml::Value) -> Result<(), String> {
+    let optional_path = |default: &str| -> Result<Option<PathBuf>, String> {
+        match value {
+            toml::Value::Bool(on) => Ok(on.then(|| PathBuf::from(default))),
+            _ => Ok(Some(PathBuf::from(value.as_str()?))),
+        }
+    };
+    match key {
+        "config" => parsed.config_path = PathBuf::from(value.as_str()?),
+        "profile" => {
+            let name = value.as_str()?;
+            parsed.config_path = config::profile_path(name);
+            parsed.profile = Some(name.to_string());
+        }
+        "schedule" => parsed.schedule = Some(PathBuf::from(value.as_str()?)),
+        "daemon" => parsed.daemon = value.as_bool()?,
+        "enforce" => parsed.enforce = value.as_bool()?,
+        "quarantine" => parsed.quarantine = value.as_bool()?,
+        "term-grace" => parsed.term_grace_ms = Some(value.as_u64()?),
+        "kill-tree" => parsed.kill_tree = value.as_bool()?,
+        "what-if" => {
+            parsed.what_if = match value {
+                toml::Value::Bool(on) => on.then_some(300),
+                _ => Some(value.as_u64()?),
+            };
+            parsed.daemon |= parsed.what_if.is_some();
+        }
+        "learn" => {
+            parsed.learn = value.as_bool()?;
+            parsed.daemon |= parsed.learn;
+        }
+        "duration" => {
+            parsed.learn_duration = Some(match value {
+                toml::Value::Str(text) => learn::parse_duration(text).ok_or("expected a duration like 90s, 15m or 1h")?,
+                _ => value.as_u64()?,
+            })
+        }
+        "output" => parsed.learn_output = Some(PathBuf::from(value.as_str()?)),
+        "stealth" => parsed.stealth = value.as_bool()?,
+        "events" => parsed.events = value.as_bool()?,
+        "no-ignore-system" => parsed.no_ignore_system = value.as_bool()?,
+        "interval" => parsed.interval_ms = value.as_u64()?,
+        "cpu-threshold" => parsed.cpu_threshold = value.as_f64()?,
+        "ram-threshold" => parsed.ram_threshold = value.as_f64()?,
+        "sustain" => parsed.sustain_samples = u32::try_from(value.as_u64()?).map_err(|_| "too large")?,
+        "sustain-seconds" => parsed.sustain_seconds = Some(value.as_u64()?),
+        "spike-delta" => parsed.spike_delta = value.as_f64()?,
+        "export-csv" => parsed.export_csv = optional_path("export.csv")?,
+        "export-jsonl" => parsed.export_jsonl = optional_path("export.jsonl")?,
+        "export-all-samples" => parsed.export_all_samples = value.as_bool()?,
+        "audit-log" => parsed.audit_log = optional_path("audit.log")?,
+        "replay" => parsed.replay = Some(PathBuf::from(value.as_str()?)),
+        // `false` turns persistence off, like `--no-cache-file`.
+        "cache-file" => {
+            parsed.cache_file = match value {
+                toml::Value::Bool(false) => None,
+                _ => Some(PathBuf::from(value.as_str()?)),
+            }
+        }
+        "cache-size" => {
+            parsed.cache_size = usize::try_from(value.as_u64()?).ok().filter(|n| *n > 0).ok_or("expected a positive integer")?
+        }
+        "no-cache-file" => return Err("use cache-file = false".to_string()),
+        "status" | "explain" | "check-config" | "convert-config" | "show-profile" | "set-profile" | "reload" | "quarantine-list"
+        | "release" | "kill" | "settings" | "print-effective-config" | "help" => {
+            return Err("is a one-off command, only available as a flag".to_string())
+        }
+        _ => return Err("unknown setting".to_string()),
+    }
+    Ok(())
+}
+
+/// Every setting `zen` ended up with after reading `zen.toml` and the flags,
+/// written back as a `zen.toml` that reproduces it.
+fn effective_config(args: &Args) -> String {
+    let path = |p: &Option<PathBuf>| p.as_ref().map(|p| toml::quote(&p.display().to_string()));
+    let num = |n: Option<u64>| n.map(|n| n.to_string());
+    let lines = [
+        // `profile` first: it also sets `config`, which must be able to override it.
+        ("profile", args.profile.as_deref().map(toml::quote)),
+        ("config", Some(toml::quote(&args.config_path.display().to_string()))),
+        ("schedule", path(&args.schedule)),
+        ("daemon", Some(args.daemon.to_string())),
+        ("enforce", Some(args.enforce.to_string())),
+        ("quarantine", Some(args.quarantine.to_string())),
+        ("term-grace", num(args.term_grace_ms)),
+        ("kill-tree", Some(args.kill_tree.to_string())),
+        ("what-if", num(args.what_if)),
+        ("learn", Some(args.learn.to_string())),
+        ("duration", num(args.learn_duration)),
+        ("output", path(&args.learn_output)),
+        ("stealth", Some(args.stealth.to_string())),
+        ("events", Some(args.events.to_string())),
+        ("no-ignore-system", Some(args.no_ignore_system.to_string())),
+        ("interval", Some(args.interval_ms.to_string())),
+        ("cpu-threshold", Some(format!("{:?}", args.cpu_threshold))),
+        ("ram-threshold", Some(format!("{:?}", args.ram_threshold))),
+        ("sustain", Some(args.sustain_samples.to_string())),
+        ("sustain-seconds", num(args.sustain_seconds)),
+        ("spike-delta", Some(format!("{:?}", args.spike_delta))),
+        ("export-csv", path(&args.export_csv)),
+        ("export-jsonl", path(&args.export_jsonl)),
+        ("export-all-samples", Some(args.export_all_samples.to_string())),
+        ("audit-log", path(&args.audit_log)),
+        ("replay", path(&args.replay)),
+        ("cache-file", Some(path(&args.cache_file).unwrap_or_else(|| "false".to_string()))),
+        ("cache-size", Some(args.cache_size.to_string())),
+    ];
+
+    let mut out = match &args.settings {
+        Some(path) => format!("# settings: {}\n", path.display()),
+        None => "# settings: none\n".to_string(),
+    };
+    for (key, value) in lines {
+        match value {
+            Some(value) => out.push_str(&format!("{} = {}\n", key, value)),
+            None => out.push_str(&format!("# {} (unset)\n", key)),
+        }
+    }
+    out
+}
+
 fn main() {
     let args = match parse_args() {
         Ok(args) => args,
@@ -210,8 +590,37 @@ fn main() {
         return;
     }
 
-    if let Some(profile) = &args.set_profile {
-        match ipc::send_command(&format!("SET_PROFILE {}", profile)) {
+    if args.print_effective_config {
+        print!("{}", effective_config(&args));
+        return;
+    }
+
+    if let Some(path) = &args.check_config {
+        std::process::exit(print_lint(path));
+    }
+
+    if let Some(path) = &args.convert_config {
+        if let Err(err) = convert_config(path, args.learn_output.as_deref()) {
+            eprintln!("error: {}", err);
+            std::process::exit(2);
+        }
+        return;
+    }
+
+    if let Some(name) = &args.show_profile {
+        let path = config::profile_path(name);
+        match config::load_allowlist(&path) {
+            Ok(allowlist) => print!("Profile: {} ({})\n{}", name, path.display(), allowlist.provenance()),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(2);
+            }
+        }
+        return;
+    }
+
+    if let Some(command) = &args.ipc_command {
+        match ipc::send_command(command) {
             Ok(resp) => {
                 print!("{}", resp);
                 if resp.starts_with("ERROR") {
@@ -226,6 +635,17 @@ fn main() {
         return;
     }
 
+    let source: Arc<dyn platform::ProcessSource> = match &args.replay {
+        Some(path) => match platform::ReplaySource::load(path) {
+            Ok(replay) => Arc::new(replay),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(2);
+            }
+        },
+        None => Arc::new(platform::NativeSource),
+    };
+
     let export_config = export::ExportConfig {
         csv_path: args.export_csv.clone(),
         jsonl_path: args.export_jsonl.clone(),
@@ -248,28 +668,104 @@ fn main() {
             spike_delta: args.spike_delta,
             export_all_samples: args.export_all_samples,
         };
-        if let Err(err) = monitor::run_stealth(cfg, exporter.as_mut()) {
+        if let Err(err) = monitor::run_stealth(cfg, source.as_ref(), exporter.as_mut()) {
             eprintln!("error: {}", err);
             std::process::exit(2);
         }
         return;
     }
 
-    let allowlist = match config::load_allowlist(&args.config_path) {
-        Ok(allowlist) => allowlist,
+    // Learning starts from nothing so every observed process gets a proposal;
+    // `--config` is not even read.
+    let allowlist = if args.learn {
+        config::Allowlist::default()
+    } else {
+        match config::load_allowlist(&args.config_path) {
+            Ok(allowlist) => allowlist,
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(2);
+            }
+        }
+    };
+    report_expired(&allowlist);
+
+    let schedule = match args.schedule.as_deref().map(schedule::Schedule::load).transpose() {
+        Ok(schedule) => schedule,
         Err(err) => {
             eprintln!("error: {}", err);
             std::process::exit(2);
         }
     };
 
+    if let Some(pid) = args.explain {
+        match policy::explain(source.as_ref(), &allowlist, pid) {
+            Ok(text) => print!("{}", text),
+            Err(err) => {
+                eprintln!("error: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     if args.daemon {
-        run_daemon_loop(args, allowlist, exporter.as_mut());
+        run_daemon_loop(args, source, allowlist, schedule, exporter.as_mut());
     } else {
-        run_single_shot(&args, &allowlist, exporter.as_mut());
+        run_single_shot(&args, source.as_ref(), &allowlist, exporter.as_mut());
     }
 }
 
+/// Rewrites the allowlist at `path` as TOML, or as JSON when `output` ends
+/// in `.json`. Without `output` the TOML goes to stdout.
+fn convert_config(path: &std::path::Path, output: Option<&std::path::Path>) -> Result<(), String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read allowlist {}: {}", path.display(), err))?;
+    let format = output.map_or(rulefile::Format::Toml, rulefile::Format::of);
+    let converted = rulefile::convert(path, &content, format).map_err(|err| format!("{}: {}", path.display(), err))?;
+    match output {
+        Some(out) => {
+            std::fs::write(out, converted).map_err(|err| format!("failed to write {}: {}", out.display(), err))?;
+            println!("{} -> {}", path.display(), out.display());
+        }
+        None => print!("{}", converted),
+    }
+    Ok(())
+}
+
+/// Announces each rule left out because its `expires` date has passed.
+fn report_expired(allowlist: &config::Allowlist) {
+    for source in &allowlist.expired {
+        println!(
+            "\x1b[33m[EXPIRED]\x1b[0m {}:{} {} (expired {}), ignored",
+            source.file,
+            source.line,
+            source.text,
+            source.meta.expires.as_deref().unwrap_or("-")
+        );
+    }
+}
+
+/// Prints `--check-config` findings and returns `lint::exit_code`.
+fn print_lint(path: &std::path::Path) -> i32 {
+    let findings = lint::check(path);
+    for finding in &findings {
+        let (label, color) = match finding.severity {
+            lint::Severity::Error => ("error", "31"),
+            lint::Severity::Warning => ("warning", "33"),
+        };
+        let at = match finding.line {
+            0 => finding.file.clone(),
+            line => format!("{}:{}", finding.file, line),
+        };
+        println!("{}: \x1b[{}m{}\x1b[0m: {}", at, color, label, finding.message);
+    }
+    let errors = findings.iter().filter(|f| f.severity == lint::Severity::Error).count();
+    let warnings = findings.len() - errors;
+    println!("{}: {} errors, {} warnings", path.display(), errors, warnings);
+    lint::exit_code(&findings)
+}
+
 fn print_grouped_report(items: &[ReportItem]) {
     if items.is_empty() {
         return;
@@ -278,13 +774,13 @@ fn print_grouped_report(items: &[ReportItem]) {
     let mut groups: BTreeMap<String, Vec<&ReportItem>> = BTreeMap::new();
     for item in items {
         let key = match &item.sig {
-            Some(sig) => sig.display_name(),
+            Some(sig) => sig.display_name(policy::signature_verified(item.pid, sig)),
             None => "⚠️  SIN FIRMA (Unsigned / Desconocido)".to_string(),
         };
         groups.entry(key).or_default().push(item);
     }
 
-    let has_unsigned = items.iter().any(|item| item.sig.is_none());
+    let has_unsigned = items.iter().any(|item| item.sig.as_ref().is_none_or(|sig| sig.adhoc));
 
     if has_unsigned {
         println!("\n\x1b[1;35m💅 [INSPECTORA DE CHISMES]\x1b[0m \x1b[1;31m⚠️ ¡Escándalo! Hay gente sospechosa merodeando sin identificación...\x1b[0m");
@@ -310,8 +806,8 @@ fn print_grouped_report(items: &[ReportItem]) {
         for (i, item) in procs.iter().enumerate() {
             let is_last = i == procs.len() - 1;
             let connector = if is_last { " └─" } else { " ├─" };
-            let status_icon = if item.sig.is_some() { "✅" } else { "❌" };
-            let action_color = if item.action == "killed" { "\x1b[31m" } else { "\x1b[33m" };
+            let status_icon = if item.sig.as_ref().is_some_and(|sig| !sig.adhoc) { "✅" } else { "❌" };
+            let action_color = if item.action.starts_with("denied") || matches!(item.action.as_str(), "killed" | "terminated") { "\x1b[31m" } else { "\x1b[33m" };
             
             let file_name = std::path::Path::new(&item.path).file_name().unwrap_or_default().to_string_lossy().replace(' ', "+");
             let proc_name = item.name.replace(' ', "+");
@@ -327,13 +823,49 @@ fn print_grouped_report(items: &[ReportItem]) {
                 "{} {} [{}] \x1b[1m{}\x1b[0m \x1b[38;5;242m({})\x1b[0m -> {}{}\x1b[0m \x1b[34m[\x1b]8;;{}\x1b\\🔍 Investigar\x1b]8;;\x1b\\]\x1b[0m",
                 connector, status_icon, item.pid, item.name, item.path, action_color, item.action, proc_search_url
             );
+            if let Some(elf) = &item.elf {
+                let indent = if is_last { "   " } else { " │ " };
+                print_elf_details(indent, elf);
+            }
         }
     }
     println!();
 }
 
-fn run_single_shot(_args: &Args, allowlist: &config::Allowlist, mut exporter: Option<&mut export::Exporter>) {
-    let processes = match platform::list_processes() {
+/// One grey line with the ELF identity under a report entry, plus a red
+/// line when the header has unusual traits.
+fn print_elf_details(indent: &str, elf: &elf::ElfInfo) {
+    let mut parts = Vec::new();
+    if let Some(id) = &elf.build_id {
+        parts.push(format!("build-id {}", id));
+    }
+    match &elf.interpreter {
+        Some(interp) => parts.push(format!("interp {}", interp)),
+        None => parts.push("estático".to_string()),
+    }
+    if !elf.needed.is_empty() {
+        parts.push(format!("libs {}", elf.needed.join(", ")));
+    }
+    if !elf.rpath.is_empty() {
+        parts.push(format!("rpath {}", elf.rpath.join(":")));
+    }
+    if !elf.runpath.is_empty() {
+        parts.push(format!("runpath {}", elf.runpath.join(":")));
+    }
+    println!("{}    \x1b[38;5;242m{}\x1b[0m", indent, parts.join(" · "));
+    if !elf.traits.is_empty() {
+        let traits: Vec<&str> = elf.traits.iter().map(|t| t.describe()).collect();
+        println!("{}    \x1b[31m🚩 {}\x1b[0m", indent, traits.join(", "));
+    }
+}
+
+fn run_single_shot(
+    args: &Args,
+    source: &dyn platform::ProcessSource,
+    allowlist: &config::Allowlist,
+    mut exporter: Option<&mut export::Exporter>,
+) {
+    let processes = match source.list() {
         Ok(processes) => processes,
         Err(err) => {
             eprintln!("error: {}", err);
@@ -342,27 +874,18 @@ fn run_single_shot(_args: &Args, allowlist: &config::Allowlist, mut exporter: Op
     };
 
     let tree = tree::ProcTree::from_processes(processes);
-    let mut hash_cache = HashMap::new();
-    let mut sig_cache = HashMap::new();
+    let mut cache = cache::FileCache::new(args.cache_size);
     let mut report_items = Vec::new();
 
     for proc in tree.walk() {
-        if policy::is_allowed(proc, &allowlist, &mut hash_cache, &mut sig_cache) {
+        let verdict = policy::is_allowed(proc, allowlist, Some(&tree), &mut cache);
+        if verdict.is_allowed() {
             continue;
         }
 
         let path = proc.path.as_deref().unwrap_or("-").to_string();
-        let sig_info = proc.path.as_deref().and_then(|p| {
-            sig_cache.entry(p.to_string()).or_insert_with(|| signature::get_signature_info(p)).clone()
-        });
-        
-        report_items.push(ReportItem {
-            pid: proc.pid,
-            name: proc.name.clone(),
-            path,
-            action: "logged".to_string(),
-            sig: sig_info,
-        });
+        let sig_info = proc.path.as_deref().and_then(|p| cache.signature(p)).cloned();
+        let elf_info = proc.path.as_deref().and_then(|p| cache.elf(p)).cloned();
 
         if let Some(exporter) = exporter.as_deref_mut() {
             let ts = export::now_ts();
@@ -373,16 +896,79 @@ fn run_single_shot(_args: &Args, allowlist: &config::Allowlist, mut exporter: Op
                 ppid: proc.ppid,
                 name: &proc.name,
                 path: proc.path.as_deref(),
+                elf: elf_info.as_ref(),
             };
             let _ = exporter.write_unknown(&event);
         }
+
+        report_items.push(ReportItem {
+            pid: proc.pid,
+            name: proc.name.clone(),
+            path,
+            action: if verdict.is_denied() { "denied" } else { "logged" }.to_string(),
+            sig: sig_info,
+            elf: elf_info,
+        });
     }
 
     print_grouped_report(&report_items);
 }
 
-fn run_daemon_loop(args: Args, allowlist: config::Allowlist, mut exporter: Option<&mut export::Exporter>) {
-    let mut audit_writer = args.audit_log.as_ref().and_then(|path| {
+struct DaemonState {
+    self_pid: u32,
+    num_cpus: f64,
+    total_mem: f64,
+    reported_unknowns: HashSet<u32>,
+    /// Allowed processes already flagged for running a modified package file.
+    reported_tampered: HashSet<u32>,
+    /// CPU time and when it was sampled, per PID, for the next `measure`.
+    prev_cpu: HashMap<u32, (u64, Instant)>,
+    cache: cache::FileCache,
+    audit: quarantine::SharedAudit,
+    quarantine: Arc<Mutex<quarantine::Quarantine>>,
+    what_if: Option<whatif::Summary>,
+    learn: Option<learn::Learner>,
+    /// Consecutive over-budget samples per PID for `limit:` rules.
+    budget_strikes: HashMap<u32, u32>,
+    budget_sustain: u32,
+    /// Parent and name from the proc connector, for processes that exit
+    /// before `/proc` can be read.
+    event_procs: HashMap<u32, EventProc>,
+    /// SIGTERMed targets waiting for their `--term-grace` to run out.
+    escalations: Vec<enforce::Pending>,
+}
+
+#[derive(Default)]
+struct EventProc {
+    ppid: Option<u32>,
+    name: Option<String>,
+}
+
+struct Usage {
+    cpu_pct: f64,
+    ram_pct: f64,
+    rss_bytes: u64,
+}
+
+/// Where the quarantine is persisted. Only a live daemon owns the
+/// file: a replay's PIDs are not real processes, and `--what-if` and
+/// `--learn` may run next to a daemon whose frozen processes they must not
+/// restore on start or thaw on exit.
+fn quarantine_file(args: &Args) -> Option<PathBuf> {
+    if args.replay.is_some() || args.what_if.is_some() || args.learn {
+        return None;
+    }
+    quarantine::default_path()
+}
+
+fn run_daemon_loop(
+    args: Args,
+    source: Arc<dyn platform::ProcessSource>,
+    allowlist: config::Allowlist,
+    schedule: Option<schedule::Schedule>,
+    mut exporter: Option<&mut export::Exporter>,
+) {
+    let audit_writer = args.audit_log.as_ref().and_then(|path| {
         export::Exporter::new(&export::ExportConfig {
             csv_path: None,
             jsonl_path: Some(path.clone()),
@@ -391,14 +977,24 @@ fn run_daemon_loop(args: Args, allowlist: config::Allowlist, mut exporter: Optio
         .flatten()
     });
 
-
-    let num_cpus = platform::num_cpus().unwrap_or(1) as f64;
-    let total_mem = platform::total_mem_bytes().unwrap_or(1) as f64;
-
-    let mut reported_unknowns = HashSet::new();
-    let mut prev_cpu: HashMap<u32, u64> = HashMap::new();
-    let mut hash_cache = HashMap::new();
-    let mut sig_cache = HashMap::new();
+    let mut state = DaemonState {
+        self_pid: std::process::id(),
+        num_cpus: source.num_cpus().unwrap_or(1) as f64,
+        total_mem: source.total_mem_bytes().unwrap_or(1) as f64,
+        reported_unknowns: HashSet::new(),
+        reported_tampered: HashSet::new(),
+        prev_cpu: HashMap::new(),
+        cache: cache::FileCache::new(args.cache_size),
+        audit: Arc::new(Mutex::new(audit_writer)),
+        quarantine: Arc::new(Mutex::new(quarantine::Quarantine::with_file(quarantine_file(&args)))),
+        what_if: args.what_if.map(whatif::Summary::new),
+        learn: args.learn.then(learn::Learner::default),
+        budget_strikes: HashMap::new(),
+        budget_sustain: monitor::resolve_sustain_samples(args.interval_ms, args.sustain_samples, args.sustain_seconds)
+            .max(1),
+        event_procs: HashMap::new(),
+        escalations: Vec::new(),
+    };
 
     let logo = "\n\
         \x1b[1;33m            .                     \x1b[0m\n\
@@ -433,6 +1029,9 @@ fn run_daemon_loop(args: Args, allowlist: config::Allowlist, mut exporter: Optio
     } else {
         println!("\x1b[1m\x1b[34m[PROFILE]\x1b[0m Activo: \x1b[32mdefault (allowlist.txt)\x1b[0m");
     }
+    if let Some(path) = &args.schedule {
+        println!("\x1b[1m\x1b[34m[SCHEDULE]\x1b[0m Horario de perfiles: \x1b[32m{}\x1b[0m", path.display());
+    }
 
     if !allowlist.names.is_empty() || !allowlist.paths.is_empty() {
         println!("\x1b[1m\x1b[34m[RULES]\x1b[0m Procesos listados en perfil:");
@@ -445,16 +1044,123 @@ fn run_daemon_loop(args: Args, allowlist: config::Allowlist, mut exporter: Optio
         println!();
     }
 
-    println!("\x1b[1m\x1b[34m[IPC]\x1b[0m Listening on /tmp/zen.sock");
-    
-    let self_pid = std::process::id();
+    let events = if args.events {
+        match platform::ProcEvents::open() {
+            Ok(events) => {
+                println!("\x1b[1m\x1b[34m[EVENTS]\x1b[0m Suscrito al proc connector (exec en tiempo real)");
+                Some(events)
+            }
+            Err(e) => {
+                eprintln!("\x1b[33m[EVENTS]\x1b[0m {} — usando polling cada {} ms", e, args.interval_ms);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    state.cache = load_cache(&args);
+    match state.quarantine.lock().unwrap().restore(source.as_ref(), &state.audit) {
+        Ok(0) => {}
+        Ok(n) => println!("\x1b[1m\x1b[34m[QUARANTINE]\x1b[0m {} proceso(s) congelado(s) por una ejecución anterior", n),
+        Err(e) => eprintln!("\x1b[33m[QUARANTINE]\x1b[0m {}", e),
+    }
+    shutdown::install();
+
     let allowlist_arc = Arc::new(RwLock::new(allowlist));
-    ipc::start_server(allowlist_arc.clone(), self_pid);
+    let base_profile = args
+        .profile
+        .clone()
+        .unwrap_or_else(|| args.config_path.display().to_string());
+    let ctx = Arc::new(ipc::Context {
+        allowlist: allowlist_arc.clone(),
+        source: source.clone(),
+        quarantine: state.quarantine.clone(),
+        audit: state.audit.clone(),
+        cache: state.cache.stats(),
+        self_pid: state.self_pid,
+        profile: Mutex::new(ipc::ActiveProfile {
+            name: base_profile.clone(),
+            path: args.config_path.clone(),
+        }),
+        base_profile,
+        schedule,
+    });
+    // A replay is not the live system and a what-if run is only a rehearsal:
+    // leave the socket to a real daemon.
+    if args.replay.is_none() && args.what_if.is_none() {
+        println!("\x1b[1m\x1b[34m[IPC]\x1b[0m Listening on /tmp/zen.sock");
+        ipc::start_server(ctx.clone());
+    }
 
     println!("\x1b[32m\x1b[1m🛡️  Zen Daemon started. Monitoring processes...\x1b[0m\n");
+    if let Some(secs) = args.what_if {
+        println!(
+            "\x1b[1m\x1b[34m[WHAT-IF]\x1b[0m Modo en seco: nada se mata ni se congela durante {} s (Ctrl-C termina antes con el resumen)",
+            secs
+        );
+    }
+    let learn_secs = args.learn.then(|| args.learn_duration.unwrap_or(300));
+    if let Some(secs) = learn_secs {
+        println!("\x1b[1m\x1b[34m[LEARN]\x1b[0m Observando durante {} s; nada se reporta ni se mata", secs);
+    }
 
-    loop {
-        let processes = match platform::list_processes() {
+    let started = Instant::now();
+    let window = args.what_if.or(learn_secs).map(Duration::from_secs);
+    // Last profile the schedule asked for; a manual SET_PROFILE holds until
+    // the schedule moves on to a different one.
+    let mut scheduled: Option<Option<String>> = None;
+    // A profile that failed to load is retried, but not on every scan.
+    let mut schedule_retry: Option<Instant> = None;
+    // Day of the last reload attempted for expired rules.
+    let mut expiry_tried = String::new();
+    let mut cache_saved = Instant::now();
+    // Learning runs on an empty allowlist, so there is nothing to reload.
+    let mut watch = (!args.learn).then(|| platform::FileWatch::new(&allowlist_arc.read().unwrap().files));
+    if let Some(watch) = &watch {
+        let how = if watch.is_inotify() { "inotify" } else { "mtime" };
+        println!("\x1b[1m\x1b[34m[RELOAD]\x1b[0m Vigilando {} archivo(s) de configuración ({})", watch.files().len(), how);
+    }
+    while !shutdown::requested() && !source.is_exhausted() && window.is_none_or(|w| started.elapsed() < w) {
+        if let Some(schedule) = &ctx.schedule {
+            let target = schedule.active_at(schedule::WeekTime::now()).map(str::to_string);
+            if scheduled.as_ref() != Some(&target) && schedule_retry.is_none_or(|at| Instant::now() >= at) {
+                if apply_schedule(&args, &ctx, target.as_deref()) {
+                    scheduled = Some(target);
+                    schedule_retry = None;
+                } else {
+                    schedule_retry = Some(Instant::now() + SCHEDULE_RETRY);
+                }
+            }
+        }
+
+        if let Some(w) = watch.as_mut() {
+            // A rule reaching its `expires` date drops out through a reload,
+            // tried once a day so a broken file does not retry every scan.
+            let today = schedule::today();
+            let expiry = allowlist_arc.read().unwrap().has_expired_rules() && expiry_tried != today;
+            if expiry {
+                expiry_tried = today;
+            }
+            if w.changed() || expiry {
+                match ipc::reload(&ctx, if expiry { "expiry" } else { "watch" }) {
+                    Ok(name) => {
+                        println!("\x1b[1m\x1b[34m[RELOAD]\x1b[0m Perfil \x1b[32m{}\x1b[0m recargado", name);
+                        report_expired(&allowlist_arc.read().unwrap());
+                    }
+                    Err(e) => eprintln!("\x1b[31m[RELOAD]\x1b[0m Cambio rechazado, se mantiene la allowlist anterior: {}", e),
+                }
+            }
+            // A profile switch or a new include changes the set of files.
+            let files = allowlist_arc.read().unwrap().files.clone();
+            if files != w.files() {
+                *w = platform::FileWatch::new(&files);
+            }
+        }
+
+        resolve_escalations(source.as_ref(), &mut state, exporter.as_deref_mut(), false);
+
+        let processes = match source.list() {
             Ok(p) => p,
             Err(e) => {
                 eprintln!("error fetching processes: {}", e);
@@ -471,115 +1177,950 @@ fn run_daemon_loop(args: Args, allowlist: config::Allowlist, mut exporter: Optio
             alive_pids.insert(proc.pid);
 
             // 1. Self check
-            if proc.pid == self_pid {
+            if proc.pid == state.self_pid {
                 continue;
             }
 
-            // 2. macOS System process check
-            if !args.no_ignore_system && platform::is_system_process(proc) {
-                if let Ok(sample) = platform::sample_process(proc.pid) {
-                    if let Some(prev_ns) = prev_cpu.get(&proc.pid) {
-                        let delta_cpu = sample.cpu_ns.saturating_sub(*prev_ns) as f64;
-                        let interval_ns = (args.interval_ms as f64) * 1_000_000.0;
-                        let cpu_pct = if interval_ns > 0.0 && num_cpus > 0.0 {
-                            (delta_cpu / interval_ns / num_cpus) * 100.0
-                        } else {
-                            0.0
-                        };
-                        let ram_pct = if total_mem > 0.0 {
-                            (sample.rss_bytes as f64 / total_mem) * 100.0
-                        } else {
-                            0.0
-                        };
-
-                        if cpu_pct >= args.cpu_threshold || ram_pct >= args.ram_threshold {
-                            println!(
-                                "\x1b[33m[OVERLOAD]\x1b[0m pid={} name=\x1b[1m{}\x1b[0m cpu=\x1b[31m{:.2}%\x1b[0m ram=\x1b[31m{:.2}%\x1b[0m",
-                                proc.pid, proc.name, cpu_pct, ram_pct
-                            );
-                            
-                            let ts = export::now_ts();
-                            let event = export::SystemOverloadEvent {
-                                ts,
-                                pid: proc.pid,
-                                name: &proc.name,
-                                path: proc.path.as_deref(),
-                                cpu_pct,
-                                ram_pct,
-                            };
-                            
-                            if let Some(exp) = exporter.as_deref_mut() {
-                                let _ = exp.write_system_overload(&event);
-                            }
-                            if let Some(audit) = audit_writer.as_mut() {
-                                let _ = audit.write_system_overload(&event);
-                            }
-                        }
-                    }
-                    prev_cpu.insert(proc.pid, sample.cpu_ns);
+            // 2. System processes are skipped unless a deny or quarantine rule names them
+            if !args.no_ignore_system
+                && source.is_system(proc)
+                && !restricted(source.as_ref(), &mut state, &allowlist_arc, proc, Some(&tree))
+            {
+                check_system_load(&args, source.as_ref(), &mut state, proc, exporter.as_deref_mut());
+                continue;
+            }
+
+            // 3-5. Allowlist, report and enforce
+            if let Some(item) = check_process(
+                &args,
+                source.as_ref(),
+                &mut state,
+                &allowlist_arc,
+                proc,
+                Some(&tree),
+                exporter.as_deref_mut(),
+            ) {
+                unknowns_this_scan.push(item);
+            }
+
+            // 6. Per-process budgets from `limit:` rules
+            check_budget(&args, source.as_ref(), &mut state, &allowlist_arc, proc, &tree, exporter.as_deref_mut());
+        }
+
+        if !unknowns_this_scan.is_empty() {
+            print_grouped_report(&unknowns_this_scan);
+        }
+        if let Some(audit) = state.audit.lock().unwrap().as_mut() {
+            let _ = audit.flush();
+        }
+        if cache_saved.elapsed() >= CACHE_SAVE_INTERVAL {
+            save_cache(&args, &mut state.cache);
+            cache_saved = Instant::now();
+        }
+        // New rules go to disk right away, so a killed run keeps them.
+        if let (Some(learner), Some(output)) = (state.learn.as_mut(), &args.learn_output) {
+            if learner.has_new_rules() {
+                if let Err(e) = learner.write(output, started.elapsed().as_secs()) {
+                    eprintln!("\x1b[31m[LEARN]\x1b[0m {}", e);
                 }
-                continue; 
             }
+        }
 
-            // 3. Allowlist check
-            let is_allowed = {
-                let lock = allowlist_arc.read().unwrap();
-                policy::is_allowed(proc, &lock, &mut hash_cache, &mut sig_cache)
-            };
-            if is_allowed {
-                continue;
+        // Cleanup tracked PID state for processes that died
+        state.reported_unknowns.retain(|pid| alive_pids.contains(pid));
+        state.reported_tampered.retain(|pid| alive_pids.contains(pid));
+        state.prev_cpu.retain(|pid, _| alive_pids.contains(pid));
+        state.budget_strikes.retain(|pid, _| alive_pids.contains(pid));
+        state.event_procs.retain(|pid, _| alive_pids.contains(pid));
+        state.quarantine.lock().unwrap().retain_alive(&alive_pids, source.as_ref(), &state.audit);
+
+        match &events {
+            Some(events) => wait_for_events(&args, source.as_ref(), &mut state, &allowlist_arc, events, exporter.as_deref_mut()),
+            None => std::thread::sleep(Duration::from_millis(args.interval_ms)),
+        }
+    }
+
+    resolve_escalations(source.as_ref(), &mut state, exporter.as_deref_mut(), true);
+    let thawed = state.quarantine.lock().unwrap().thaw_all(source.as_ref(), &state.audit);
+    if thawed > 0 {
+        println!("\x1b[1m\x1b[34m[QUARANTINE]\x1b[0m {} proceso(s) descongelado(s) al salir", thawed);
+    }
+    if let Some(audit) = state.audit.lock().unwrap().as_mut() {
+        let _ = audit.flush();
+    }
+    save_cache(&args, &mut state.cache);
+
+    if let (Some(learner), Some(output)) = (state.learn.as_mut(), &args.learn_output) {
+        match learner.write(output, started.elapsed().as_secs()) {
+            Ok(()) => println!(
+                "\x1b[1m\x1b[34m[LEARN]\x1b[0m {} reglas escritas en \x1b[32m{}\x1b[0m",
+                learner.len(),
+                output.display()
+            ),
+            Err(e) => {
+                eprintln!("error: {}", e);
+                std::process::exit(1);
             }
+        }
+    }
 
-            // 4. Report & Log (Deduplicated)
-            if reported_unknowns.insert(proc.pid) {
-                let path = proc.path.as_deref().unwrap_or("-").to_string();
-                let action = if args.enforce { "killed" } else { "logged" };
-                
-                let sig_info = proc.path.as_deref().and_then(|p| {
-                    sig_cache.entry(p.to_string()).or_insert_with(|| signature::get_signature_info(p)).clone()
-                });
-                
-                unknowns_this_scan.push(ReportItem {
-                    pid: proc.pid,
-                    name: proc.name.clone(),
-                    path,
-                    action: action.to_string(),
-                    sig: sig_info,
-                });
-
-                let ts = export::now_ts();
-                let event = export::AuditEvent {
-                    ts,
-                    pid: proc.pid,
-                    uid: proc.uid,
-                    ppid: proc.ppid,
-                    name: &proc.name,
-                    path: proc.path.as_deref(),
-                    action,
-                };
+    if let Some(summary) = &mut state.what_if {
+        summary.end_after(started.elapsed().as_secs());
+        summary.print();
+        if let Some(exp) = exporter {
+            if let Err(e) = summary.export(exp) {
+                eprintln!("error exporting what-if summary: {}", e);
+            }
+        }
+    }
+}
 
-                if let Some(exp) = exporter.as_deref_mut() {
-                    let _ = exp.write_audit(&event);
+/// The cache saved by an earlier run at `--cache-file`, or an empty one when
+/// there is none or it cannot be used.
+fn load_cache(args: &Args) -> cache::FileCache {
+    let Some(path) = &args.cache_file else {
+        return cache::FileCache::new(args.cache_size);
+    };
+    match cache::FileCache::load(path, args.cache_size) {
+        Ok(cache) => {
+            println!(
+                "\x1b[1m\x1b[34m[CACHE]\x1b[0m {} archivo(s) en caché desde \x1b[32m{}\x1b[0m",
+                cache.len(),
+                path.display()
+            );
+            cache
+        }
+        Err(e) => {
+            eprintln!("\x1b[33m[CACHE]\x1b[0m {} — empezando con la caché vacía", e);
+            cache::FileCache::new(args.cache_size)
+        }
+    }
+}
+
+fn save_cache(args: &Args, cache: &mut cache::FileCache) {
+    if let Some(path) = &args.cache_file {
+        if let Err(e) = cache.save(path) {
+            eprintln!("\x1b[31m[CACHE]\x1b[0m {}", e);
+        }
+    }
+}
+
+/// Switches to the scheduled profile (`None` is the startup one) unless it is
+/// already active. False when the profile failed to load.
+fn apply_schedule(args: &Args, ctx: &ipc::Context, target: Option<&str>) -> bool {
+    let (name, path) = match target {
+        Some(name) => (name, config::profile_path(name)),
+        None => (ctx.base_profile.as_str(), args.config_path.clone()),
+    };
+    if ctx.profile.lock().unwrap().name == name {
+        return true;
+    }
+    match ipc::switch_profile(ctx, name, &path, "schedule") {
+        Ok(()) => {
+            println!(
+                "\x1b[1m\x1b[34m[SCHEDULE]\x1b[0m {} → perfil \x1b[32m{}\x1b[0m",
+                schedule::WeekTime::now().label(),
+                name
+            );
+            true
+        }
+        Err(e) => {
+            eprintln!(
+                "\x1b[31m[SCHEDULE]\x1b[0m No se pudo cargar el perfil {}: {} (reintento en {} s)",
+                name,
+                e,
+                SCHEDULE_RETRY.as_secs()
+            );
+            false
+        }
+    }
+}
+
+/// Sleeps until the next scan while handling fork and exec events as they
+/// arrive, so processes that live shorter than one interval are still checked.
+fn wait_for_events(
+    args: &Args,
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    allowlist: &RwLock<config::Allowlist>,
+    events: &platform::ProcEvents,
+    mut exporter: Option<&mut export::Exporter>,
+) {
+    let deadline = Instant::now() + Duration::from_millis(args.interval_ms);
+    loop {
+        let now = Instant::now();
+        if now >= deadline || shutdown::requested() {
+            return;
+        }
+        let batch = match events.recv(deadline - now) {
+            Ok(batch) => batch,
+            Err(e) => {
+                eprintln!("error reading proc events: {}", e);
+                std::thread::sleep(deadline - now);
+                return;
+            }
+        };
+
+        let mut unknowns = Vec::new();
+        for event in batch {
+            let (pid, is_exec) = match event {
+                platform::ProcEvent::Fork { pid, ppid } => {
+                    // The child runs the parent's image under the parent's name.
+                    let name = state.event_procs.get(&ppid).and_then(|p| p.name.clone());
+                    state.event_procs.insert(pid, EventProc { ppid: Some(ppid), name });
+                    (pid, false)
+                }
+                platform::ProcEvent::Comm { pid, comm } => {
+                    state.event_procs.entry(pid).or_default().name = Some(comm);
+                    continue;
                 }
-                if let Some(audit) = audit_writer.as_mut() {
-                    let _ = audit.write_audit(&event);
+                platform::ProcEvent::Exec { pid } => (pid, true),
+                platform::ProcEvent::Uid { pid } => (pid, false),
+                platform::ProcEvent::Exit { pid } => {
+                    state.reported_unknowns.remove(&pid);
+                    state.prev_cpu.remove(&pid);
+                    state.event_procs.remove(&pid);
+                    continue;
                 }
+            };
+            if pid == state.self_pid {
+                continue;
+            }
+            let Ok(proc) = source.process(pid) else {
+                // Gone before it could be read; the next scan will not see it either.
+                if is_exec {
+                    record_exited_exec(state, exporter.as_deref_mut(), pid);
+                }
+                continue;
+            };
 
-                // 5. Enforce 
-                if args.enforce {
-                    let _ = platform::kill_process(proc.pid);
+            if is_exec {
+                // A new image invalidates any earlier verdict for this PID.
+                state.reported_unknowns.remove(&pid);
+                if let Some(exp) = exporter.as_deref_mut() {
+                    let event = export::ExecEvent {
+                        ts: export::now_ts(),
+                        pid: proc.pid,
+                        uid: Some(proc.uid),
+                        ppid: Some(proc.ppid),
+                        name: &proc.name,
+                        path: proc.path.as_deref(),
+                        reason: None,
+                    };
+                    let _ = exp.write_exec(&event);
                 }
             }
+
+            if !args.no_ignore_system && source.is_system(&proc) && !restricted(source, state, allowlist, &proc, None) {
+                continue;
+            }
+            // A freshly exec'd process has no known children yet.
+            if let Some(item) = check_process(args, source, state, allowlist, &proc, None, exporter.as_deref_mut()) {
+                unknowns.push(item);
+            }
         }
 
-        if !unknowns_this_scan.is_empty() {
-            print_grouped_report(&unknowns_this_scan);
+        if !unknowns.is_empty() {
+            print_grouped_report(&unknowns);
         }
+    }
+}
 
-        // Cleanup tracked PID state for processes that died
-        reported_unknowns.retain(|pid| alive_pids.contains(pid));
-        prev_cpu.retain(|pid, _| alive_pids.contains(pid));
+/// Exports and audits an exec whose process exited before it could be read.
+/// Only the connector's PID, parent and name are known, so it is recorded as
+/// `exited` instead of being checked against the allowlist.
+fn record_exited_exec(state: &mut DaemonState, exporter: Option<&mut export::Exporter>, pid: u32) {
+    let seen = state.event_procs.get(&pid);
+    let name = seen.and_then(|p| p.name.as_deref()).unwrap_or("?");
+    println!("\x1b[33m[EXEC]\x1b[0m [{}] {} terminó antes de poder revisarlo", pid, name);
+    let event = export::ExecEvent {
+        ts: export::now_ts(),
+        pid,
+        uid: None,
+        ppid: seen.and_then(|p| p.ppid),
+        name,
+        path: None,
+        reason: Some("exited"),
+    };
+    if let Some(exp) = exporter {
+        let _ = exp.write_exec(&event);
+    }
+    if let Some(audit) = state.audit.lock().unwrap().as_mut() {
+        let _ = audit.write_exec(&event);
+    }
+}
+
+fn check_system_load(
+    args: &Args,
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    proc: &platform::ProcessInfo,
+    exporter: Option<&mut export::Exporter>,
+) {
+    if let Some(Usage { cpu_pct, ram_pct, .. }) = measure(source, state, proc.pid) {
+        if cpu_pct >= args.cpu_threshold || ram_pct >= args.ram_threshold {
+            println!(
+                "\x1b[33m[OVERLOAD]\x1b[0m pid={} name=\x1b[1m{}\x1b[0m cpu=\x1b[31m{:.2}%\x1b[0m ram=\x1b[31m{:.2}%\x1b[0m",
+                proc.pid, proc.name, cpu_pct, ram_pct
+            );
+
+            let ts = export::now_ts();
+            let event = export::SystemOverloadEvent {
+                ts,
+                pid: proc.pid,
+                name: &proc.name,
+                path: proc.path.as_deref(),
+                cpu_pct,
+                ram_pct,
+            };
+
+            if let Some(exp) = exporter {
+                let _ = exp.write_system_overload(&event);
+            }
+            if let Some(audit) = state.audit.lock().unwrap().as_mut() {
+                let _ = audit.write_system_overload(&event);
+            }
+        }
+    }
+}
+
+/// CPU since the previous sample of `pid` and current RAM. `None` on the
+/// first sample. The elapsed time is measured, not `--interval`: scans run
+/// late under load and exec events sample between scans.
+fn measure(source: &dyn platform::ProcessSource, state: &mut DaemonState, pid: u32) -> Option<Usage> {
+    let sample = source.sample(pid).ok()?;
+    let now = Instant::now();
+    let (prev_ns, prev_at) = state.prev_cpu.insert(pid, (sample.cpu_ns, now))?;
+    let delta_cpu = sample.cpu_ns.saturating_sub(prev_ns) as f64;
+    let interval_ns = now.duration_since(prev_at).as_nanos() as f64;
+    let cpu_pct = if interval_ns > 0.0 && state.num_cpus > 0.0 {
+        (delta_cpu / interval_ns / state.num_cpus) * 100.0
+    } else {
+        0.0
+    };
+    let ram_pct = if state.total_mem > 0.0 {
+        (sample.rss_bytes as f64 / state.total_mem) * 100.0
+    } else {
+        0.0
+    };
+    Some(Usage {
+        cpu_pct,
+        ram_pct,
+        rss_bytes: sample.rss_bytes,
+    })
+}
+
+/// Applies the matching `limit:` once a process stays over budget for the
+/// `--sustain` / `--sustain-seconds` window, then starts counting again.
+fn check_budget(
+    args: &Args,
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    allowlist: &RwLock<config::Allowlist>,
+    proc: &platform::ProcessInfo,
+    tree: &tree::ProcTree,
+    mut exporter: Option<&mut export::Exporter>,
+) {
+    // A frozen process keeps its memory; it already awaits a decision.
+    if state.learn.is_some() || state.quarantine.lock().unwrap().contains(proc.pid) {
+        return;
+    }
+    let limit = {
+        let lock = allowlist.read().unwrap();
+        match policy::limit_for(proc, &lock, Some(tree), &mut state.cache) {
+            Some(limit) => limit.clone(),
+            None => return,
+        }
+    };
+    let Some(usage) = measure(source, state, proc.pid) else { return };
+
+    let over_cpu = limit.cpu_max.is_some_and(|max| usage.cpu_pct >= max);
+    let over_ram = match limit.ram_max {
+        Some(config::RamMax::Bytes(max)) => usage.rss_bytes >= max,
+        Some(config::RamMax::Percent(max)) => usage.ram_pct >= max,
+        None => false,
+    };
+    if !over_cpu && !over_ram {
+        state.budget_strikes.remove(&proc.pid);
+        return;
+    }
+    let strikes = state.budget_strikes.entry(proc.pid).or_insert(0);
+    *strikes += 1;
+    if *strikes < state.budget_sustain {
+        return;
+    }
+    state.budget_strikes.remove(&proc.pid);
+
+    // An allowed process over budget is audited with the rule that let it run.
+    let rule = {
+        let lock = allowlist.read().unwrap();
+        let verdict = policy::is_allowed(proc, &lock, Some(tree), &mut state.cache);
+        verdict.rule().map(|m| m.describe())
+    };
+
+    let reason = match (over_cpu, over_ram) {
+        (true, true) => "cpu_max+ram_max",
+        (true, false) => "cpu_max",
+        _ => "ram_max",
+    };
+    let steps = match limit.action {
+        config::LimitAction::Alert => vec![enforce::Step { info: proc, action: "budget-alert", verified: None }],
+        _ if state.what_if.is_some() => {
+            let action = match limit.action {
+                config::LimitAction::Renice => "would-renice",
+                config::LimitAction::Quarantine => "would-quarantine",
+                _ => "would-kill",
+            };
+            vec![enforce::Step { info: proc, action, verified: None }]
+        }
+        config::LimitAction::Renice => {
+            let (action, verified) = match source.pin(proc).and_then(|h| source.renice(&h, RENICE_VALUE)) {
+                Ok(platform::KillOutcome::Verified) => ("reniced", Some(true)),
+                Ok(platform::KillOutcome::Unverified) => ("reniced", Some(false)),
+                Ok(platform::KillOutcome::IdentityChanged) => ("renice-skipped", Some(false)),
+                Err(_) => ("renice-failed", Some(false)),
+            };
+            vec![enforce::Step { info: proc, action, verified }]
+        }
+        config::LimitAction::Quarantine => {
+            let (action, verified) = quarantine_process(source, state, proc);
+            vec![enforce::Step { info: proc, action, verified }]
+        }
+        config::LimitAction::Kill => {
+            enforce::run(source, &[proc], args.term_grace_ms, rule.as_deref(), &mut state.escalations)
+        }
+    };
+
+    let action = steps.last().map(|s| s.action).unwrap_or("budget-alert");
+    println!(
+        "\x1b[33m[BUDGET]\x1b[0m pid={} name=\x1b[1m{}\x1b[0m cpu=\x1b[31m{:.2}%\x1b[0m ram=\x1b[31m{:.2}%\x1b[0m ({}) -> {}",
+        proc.pid, proc.name, usage.cpu_pct, usage.ram_pct, reason, action
+    );
+
+    let anomaly = export::AnomalyEvent {
+        ts: export::now_ts(),
+        pid: proc.pid,
+        name: &proc.name,
+        path: proc.path.as_deref(),
+        cpu_pct: usage.cpu_pct,
+        ram_pct: usage.ram_pct,
+        reason,
+    };
+    if let Some(exp) = exporter.as_deref_mut() {
+        let _ = exp.write_anomaly(&anomaly);
+    }
+    if let Some(audit) = state.audit.lock().unwrap().as_mut() {
+        let _ = audit.write_anomaly(&anomaly);
+    }
+    write_steps(state, exporter, &steps, rule.as_deref());
+}
+
+/// Audits SIGTERMed targets that exited or whose grace period ran out (and
+/// got SIGKILL); with `finish` every one left is resolved now.
+fn resolve_escalations(
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    mut exporter: Option<&mut export::Exporter>,
+    finish: bool,
+) {
+    if state.escalations.is_empty() {
+        return;
+    }
+    let resolved = if finish {
+        enforce::finish(source, &mut state.escalations)
+    } else {
+        enforce::escalate(source, &mut state.escalations)
+    };
+    for done in &resolved {
+        let info = &done.pending.info;
+        println!("\x1b[31m[ENFORCE]\x1b[0m [{}] {} -> {}", info.pid, info.name, done.action);
+        let step = enforce::Step { info, action: done.action, verified: done.verified };
+        write_steps(state, exporter.as_deref_mut(), &[step], done.pending.rule.as_deref());
+    }
+}
+
+/// Writes one audit event per step to the exporter and the audit log, citing
+/// `rule`, the allowlist line behind the verdict, if any.
+fn write_steps(
+    state: &mut DaemonState,
+    mut exporter: Option<&mut export::Exporter>,
+    steps: &[enforce::Step],
+    rule: Option<&str>,
+) {
+    for step in steps {
+        let elf = step.info.path.as_deref().and_then(|p| state.cache.elf(p)).cloned();
+        let event = export::AuditEvent {
+            ts: export::now_ts(),
+            pid: step.info.pid,
+            uid: step.info.uid,
+            ppid: step.info.ppid,
+            name: &step.info.name,
+            path: step.info.path.as_deref(),
+            action: step.action,
+            verified: step.verified,
+            rule,
+            elf: elf.as_ref(),
+        };
+        if let Some(exp) = exporter.as_deref_mut() {
+            let _ = exp.write_audit(&event);
+        }
+        if let Some(audit) = state.audit.lock().unwrap().as_mut() {
+            let _ = audit.write_audit(&event);
+        }
+    }
+}
+
+fn check_process(
+    args: &Args,
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    allowlist: &RwLock<config::Allowlist>,
+    proc: &platform::ProcessInfo,
+    tree: Option<&tree::ProcTree>,
+    exporter: Option<&mut export::Exporter>,
+) -> Option<ReportItem> {
+    // Learning records a proposal per process instance and never reports.
+    if let Some(learner) = state.learn.as_mut() {
+        if state.reported_unknowns.insert(proc.pid) {
+            learner.record(proc, &mut state.cache);
+        }
+        return None;
+    }
+
+    // 3. Allowlist check (deny rules win over quarantine rules, which win over allow rules)
+    let (verdict, uses_packages) = {
+        let lock = allowlist.read().unwrap();
+        // Exec events arrive between scans; rebuild just the ancestry chain.
+        let lineage;
+        let tree = match tree {
+            None if lock.has_lineage() => {
+                lineage = ancestry_tree(source, proc);
+                Some(&lineage)
+            }
+            tree => tree,
+        };
+        let verdict = policy::is_allowed(proc, &lock, tree, &mut state.cache);
+        (verdict, lock.has_package_rules())
+    };
+    if verdict.is_allowed() {
+        // With package rules in play every executable's digest is checked,
+        // even when some other rule lets it run.
+        if uses_packages && package_tampered(state, proc) && state.reported_tampered.insert(proc.pid) {
+            println!(
+                "\x1b[1m\x1b[31m[TAMPERED]\x1b[0m [{}] {} ({}) difiere del archivo instalado por su paquete",
+                proc.pid,
+                proc.name,
+                proc.path.as_deref().unwrap_or("-")
+            );
+            let rule = verdict.rule().map(|m| m.describe());
+            let step = enforce::Step { info: proc, action: "tampered", verified: None };
+            write_steps(state, exporter, &[step], rule.as_deref());
+        }
+        return None;
+    }
+    let denied = verdict.is_denied();
+    let quarantine_rule = matches!(verdict, policy::Verdict::Quarantined(_));
+    let rule = verdict.rule().map(|m| m.describe());
+
+    // 4. Report & Log (Deduplicated)
+    if !state.reported_unknowns.insert(proc.pid) {
+        return None;
+    }
+
+    let path = proc.path.as_deref().unwrap_or("-").to_string();
+
+    let targets = match tree {
+        Some(tree) if args.kill_tree => {
+            // Descendants the policy allows, and system ones, keep running.
+            let lock = allowlist.read().unwrap();
+            let self_pid = state.self_pid;
+            tree.subtree_children_first(proc.pid)
+                .into_iter()
+                .filter(|p| p.pid != self_pid)
+                .filter(|p| {
+                    p.pid == proc.pid
+                        || !((!args.no_ignore_system && source.is_system(p))
+                            || policy::is_allowed(p, &lock, Some(tree), &mut state.cache).is_allowed())
+                })
+                .collect()
+        }
+        _ => vec![proc],
+    };
+
+    // 5. Enforce: every target is pinned first so no signal can hit a recycled PID.
+    let mut steps = if let Some(summary) = state.what_if.as_mut() {
+        let would_quarantine = quarantine_rule || args.quarantine;
+        let targets = if would_quarantine { vec![proc] } else { targets };
+        let action = if would_quarantine { "would-quarantine" } else { "would-kill" };
+        targets
+            .into_iter()
+            .map(|info| {
+                summary.record(info, action);
+                enforce::Step { info, action, verified: None }
+            })
+            .collect()
+    } else if quarantine_rule || args.quarantine {
+        let (action, verified) = quarantine_process(source, state, proc);
+        vec![enforce::Step { info: proc, action, verified }]
+    } else if args.enforce {
+        enforce::run(source, &targets, args.term_grace_ms, rule.as_deref(), &mut state.escalations)
+    } else {
+        vec![enforce::Step { info: proc, action: "logged", verified: None }]
+    };
+    if denied {
+        // Observe-only deny hits are audited as `denied`; otherwise the deny
+        // verdict is audited ahead of whatever enforcement did.
+        if steps.len() == 1 && steps[0].action == "logged" {
+            steps[0].action = "denied";
+        } else {
+            steps.insert(0, enforce::Step { info: proc, action: "denied", verified: None });
+        }
+    }
+    if package_tampered(state, proc) {
+        steps.insert(0, enforce::Step { info: proc, action: "tampered", verified: None });
+    }
+
+    let sig_info = proc.path.as_deref().and_then(|p| state.cache.signature(p)).cloned();
+    let elf_info = proc.path.as_deref().and_then(|p| state.cache.elf(p)).cloned();
+
+    let mut action = "logged";
+    for step in &steps {
+        if step.info.pid == proc.pid {
+            action = step.action;
+        }
+        // Descendants handled here must not be reported again later in the walk.
+        state.reported_unknowns.insert(step.info.pid);
+    }
+    write_steps(state, exporter, &steps, rule.as_deref());
+
+    let action = if denied && action != "denied" {
+        format!("denied/{}", action)
+    } else {
+        action.to_string()
+    };
+
+    Some(ReportItem {
+        pid: proc.pid,
+        name: proc.name.clone(),
+        path,
+        action,
+        sig: sig_info,
+        elf: elf_info,
+    })
+}
+
+/// True when the executable belongs to an installed package but no longer
+/// has the digest the package recorded.
+fn package_tampered(state: &mut DaemonState, proc: &platform::ProcessInfo) -> bool {
+    let Some(path) = &proc.path else {
+        return false;
+    };
+    state
+        .cache
+        .signature(path)
+        .and_then(|sig| sig.package.as_ref())
+        .is_some_and(|pkg| pkg.tampered)
+}
+
+/// True when a deny or quarantine rule matches `proc`. Without a scan tree
+/// the ancestry chain is rebuilt when such rules look at it.
+fn restricted(
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    allowlist: &RwLock<config::Allowlist>,
+    proc: &platform::ProcessInfo,
+    tree: Option<&tree::ProcTree>,
+) -> bool {
+    let lock = allowlist.read().unwrap();
+    if lock.deny.is_none() && lock.quarantine.is_none() {
+        return false;
+    }
+    let lineage;
+    let tree = match tree {
+        None if lock.has_lineage() => {
+            lineage = ancestry_tree(source, proc);
+            Some(&lineage)
+        }
+        tree => tree,
+    };
+    policy::restriction(proc, &lock, tree, &mut state.cache).is_some()
+}
+
+/// `proc` and the ancestors the source can still resolve.
+fn ancestry_tree(source: &dyn platform::ProcessSource, proc: &platform::ProcessInfo) -> tree::ProcTree {
+    let mut chain = vec![proc.clone()];
+    let mut ppid = proc.ppid;
+    while ppid != 0 && !chain.iter().any(|p| p.pid == ppid) {
+        let Ok(parent) = source.process(ppid) else { break };
+        ppid = parent.ppid;
+        chain.push(parent);
+    }
+    tree::ProcTree::from_processes(chain)
+}
+
+fn quarantine_process(
+    source: &dyn platform::ProcessSource,
+    state: &mut DaemonState,
+    proc: &platform::ProcessInfo,
+) -> (&'static str, Option<bool>) {
+    let Ok(handle) = source.pin(proc) else {
+        return ("quarantine-failed", Some(false));
+    };
+    let Ok(freeze) = source.freeze(&handle) else {
+        return ("quarantine-failed", Some(false));
+    };
+    let action = match freeze {
+        platform::Freeze::Cgroup { .. } => "quarantined-cgroup",
+        platform::Freeze::Stop => "quarantined-sigstop",
+    };
+    let verified = Some(handle.fd.is_some());
+    state.quarantine.lock().unwrap().insert(quarantine::Entry {
+        handle,
+        name: proc.name.clone(),
+        uid: proc.uid,
+        ppid: proc.ppid,
+        freeze,
+        since: export::now_ts(),
+    });
+    (action, verified)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs the daemon over `replay` with `allowlist` and returns the JSONL
+    /// events it exported.
+    fn replay_daemon(name: &str, allowlist: &str, replay: &str, setup: impl FnOnce(&mut Args)) -> Vec<String> {
+        let dir = env::temp_dir().join(format!("zen-replay-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("allowlist.txt");
+        std::fs::write(&config, allowlist).unwrap();
+        let events = dir.join("events.jsonl");
+
+        let mut args = Args {
+            config_path: config.clone(),
+            daemon: true,
+            interval_ms: 1,
+            replay: Some(dir.join("replay")),
+            cache_file: None,
+            ..Args::default()
+        };
+        setup(&mut args);
+        let source = platform::ReplaySource::parse(replay).unwrap();
+        let allowlist = config::load_allowlist(&config).unwrap();
+        let mut exporter = export::Exporter::new(&export::ExportConfig {
+            csv_path: None,
+            jsonl_path: Some(events.clone()),
+        })
+        .unwrap()
+        .unwrap();
+        run_daemon_loop(args, Arc::new(source), allowlist, None, Some(&mut exporter));
+        exporter.flush().unwrap();
+
+        let lines = std::fs::read_to_string(&events).unwrap().lines().map(str::to_string).collect();
+        let _ = std::fs::remove_dir_all(&dir);
+        lines
+    }
+
+    fn audits(lines: &[String], pid: u32) -> Vec<&str> {
+        let pid = format!("\"pid\":{},", pid);
+        lines
+            .iter()
+            .filter(|l| l.contains("\"kind\":\"audit\"") && l.contains(&pid))
+            .map(String::as_str)
+            .collect()
+    }
+
+    #[test]
+    fn replay_reports_an_unknown_once_per_lifetime() {
+        let replay = "scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            20\t10\t501\t0\t1024\tminer\t/tmp/miner\n\
+            scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            20\t10\t501\t0\t1024\tminer\t/tmp/miner\n\
+            scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            20\t10\t501\t0\t1024\tminer\t/tmp/miner\n";
+        let lines = replay_daemon("dedup", "name:zsh\n", replay, |_| {});
+
+        let miner = audits(&lines, 20);
+        assert_eq!(miner.len(), 2, "{:?}", lines);
+        assert!(miner.iter().all(|l| l.contains("\"logged\"")));
+        assert!(audits(&lines, 10).is_empty());
+    }
+
+    #[test]
+    fn what_if_and_replay_leave_the_quarantine_file_alone() {
+        let state = env::temp_dir().join(format!("zen-replay-state-{}", std::process::id()));
+        let file = state.join("zen").join("quarantine.jsonl");
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        let saved = "{\"zen-quarantine\":1}\n\
+            {\"pid\":20,\"start\":0,\"path\":\"/tmp/miner\",\"name\":\"miner\",\"uid\":501,\
+            \"ppid\":10,\"method\":\"sigstop\",\"since\":1708300000}\n";
+        std::fs::write(&file, saved).unwrap();
+        // Nothing else reads XDG_STATE_HOME.
+        env::set_var("XDG_STATE_HOME", &state);
+
+        let replay = "scan\n20\t10\t501\t0\t1024\tminer\t/tmp/miner\n";
+        replay_daemon("state-replay", "name:zsh\n", replay, |_| {});
+        replay_daemon("state-whatif", "name:zsh\n", replay, |args| {
+            // The source is still scripted, but the run looks like a live --what-if.
+            args.replay = None;
+            args.what_if = Some(60);
+        });
+        let learn = Args { learn: true, ..Args::default() };
+        assert_eq!(quarantine_file(&learn), None);
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), saved);
+        assert_eq!(quarantine_file(&Args::default()), Some(file));
+        let _ = std::fs::remove_dir_all(&state);
+    }
+
+    #[test]
+    fn replay_enforce_kills_unknowns_only() {
+        let replay = "scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            20\t10\t501\t0\t1024\tminer\t/tmp/miner\n\
+            scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            20\t10\t501\t0\t1024\tminer\t/tmp/miner\n";
+        let lines = replay_daemon("enforce", "name:zsh\n", replay, |args| args.enforce = true);
+
+        let miner = audits(&lines, 20);
+        assert_eq!(miner.len(), 1, "{:?}", lines);
+        assert!(miner[0].contains("\"killed\"") && miner[0].contains("\"verified\":true"));
+        assert!(audits(&lines, 10).is_empty());
+    }
 
-        std::thread::sleep(Duration::from_millis(args.interval_ms));
+    #[test]
+    fn replay_escalates_sigterm_on_a_later_scan() {
+        let replay = "scan\n\
+            20\t1\t501\t0\t1024\tminer\t/tmp/miner\n\
+            scan\n\
+            20\t1\t501\t0\t1024\tminer\t/tmp/miner\n\
+            scan\n\
+            20\t1\t501\t0\t1024\tminer\t/tmp/miner\n";
+        let lines = replay_daemon("grace", "name:zsh\n", replay, |args| {
+            args.enforce = true;
+            args.term_grace_ms = Some(0);
+        });
+
+        let miner = audits(&lines, 20);
+        assert_eq!(miner.len(), 2, "{:?}", lines);
+        assert!(miner[0].contains("\"sigterm\""));
+        assert!(miner[1].contains("\"killed\""));
+    }
+
+    #[test]
+    fn replay_kill_tree_spares_allowed_descendants() {
+        let replay = "scan\n\
+            20\t1\t501\t0\t1024\tminer\t/tmp/miner\n\
+            21\t20\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            22\t20\t501\t0\t1024\thelper\t/tmp/helper\n\
+            23\t21\t501\t0\t1024\tcurl\t/tmp/curl\n";
+        let lines = replay_daemon("tree", "name:zsh\n", replay, |args| {
+            args.enforce = true;
+            args.kill_tree = true;
+        });
+
+        for pid in [20, 22, 23] {
+            let steps = audits(&lines, pid);
+            assert_eq!(steps.len(), 1, "{}: {:?}", pid, lines);
+            assert!(steps[0].contains("\"killed\""));
+        }
+        assert!(audits(&lines, 21).is_empty(), "{:?}", lines);
+    }
+
+    #[test]
+    fn replay_what_if_only_summarizes() {
+        let replay = "scan\n\
+            20\t1\t501\t0\t1024\tminer\t/tmp/miner\n\
+            scan\n\
+            20\t1\t501\t0\t1024\tminer\t/tmp/miner\n";
+        let lines = replay_daemon("whatif", "name:zsh\n", replay, |args| {
+            args.enforce = true;
+            args.what_if = Some(300);
+        });
+
+        let miner = audits(&lines, 20);
+        assert_eq!(miner.len(), 1, "{:?}", lines);
+        assert!(miner[0].contains("\"would-kill\""));
+        let summary: Vec<&String> = lines.iter().filter(|l| l.contains("\"kind\":\"what-if\"")).collect();
+        assert_eq!(summary.len(), 1, "{:?}", lines);
+        assert!(summary[0].contains("path:/tmp/miner"));
+    }
+
+    #[test]
+    fn replay_learn_writes_a_loadable_allowlist() {
+        let output = env::temp_dir().join(format!("zen-replay-learned-{}.txt", std::process::id()));
+        let replay = "scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            scan\n\
+            10\t1\t501\t0\t1024\tzsh\t/bin/zsh\n\
+            20\t10\t501\t0\t1024\tvim\t/usr/bin/vim\n";
+        let lines = replay_daemon("learn", "name:zsh\n", replay, |args| {
+            args.learn = true;
+            args.learn_output = Some(output.clone());
+        });
+
+        assert!(audits(&lines, 20).is_empty());
+        let learned = config::load_allowlist(&output).unwrap();
+        assert!(learned.paths.contains("/bin/zsh") && learned.paths.contains("/usr/bin/vim"));
+        let _ = std::fs::remove_file(&output);
+    }
+
+    #[test]
+    fn replay_deny_rules_reach_system_processes() {
+        let replay = "system /usr/libexec/\n\
+            scan\n\
+            5\t1\t0\t0\t10\tindexer\t/usr/libexec/indexer\n\
+            6\t1\t0\t0\t10\tidle\t/usr/libexec/idle\n";
+        let lines = replay_daemon("system-deny", "name:zsh\ndeny:name:indexer\n", replay, |args| args.enforce = true);
+
+        let indexer = audits(&lines, 5);
+        assert_eq!(indexer.len(), 2, "{:?}", lines);
+        assert!(indexer[0].contains("\"denied\"") && indexer[1].contains("\"killed\""));
+        assert!(audits(&lines, 6).is_empty());
+    }
+
+    #[test]
+    fn replay_flags_overloaded_system_processes() {
+        let replay = "mem 1000\n\
+            system /usr/libexec/\n\
+            scan\n\
+            5\t1\t0\t0\t500\tindexer\t/usr/libexec/indexer\n\
+            6\t1\t0\t0\t10\tidle\t/usr/libexec/idle\n\
+            scan\n\
+            5\t1\t0\t0\t500\tindexer\t/usr/libexec/indexer\n\
+            6\t1\t0\t0\t10\tidle\t/usr/libexec/idle\n";
+        let lines = replay_daemon("overload", "name:zsh\n", replay, |_| {});
+
+        let overloads: Vec<&String> = lines.iter().filter(|l| l.contains("\"kind\":\"system-overload\"")).collect();
+        assert_eq!(overloads.len(), 1, "{:?}", lines);
+        assert!(overloads[0].contains("\"pid\":5,"));
+        // System processes are never audited as unknown.
+        assert!(audits(&lines, 5).is_empty() && audits(&lines, 6).is_empty());
+    }
+
+    #[test]
+    fn switch_flags_turn_settings_back_off() {
+        let mut args = Args::default();
+        for key in ["daemon", "enforce", "kill-tree", "events", "stealth", "no-ignore-system"] {
+            apply_setting(&mut args, key, &toml::Value::Bool(true)).unwrap();
+        }
+        for flag in ["--no-daemon", "--enforce=false", "--no-kill-tree", "--events=false", "--no-stealth"] {
+            let (name, on) = switch_flag(flag).unwrap();
+            apply_setting(&mut args, name, &toml::Value::Bool(on)).unwrap();
+        }
+        assert!(!args.daemon && !args.enforce && !args.kill_tree && !args.events && !args.stealth);
+        assert_eq!(switch_flag("--no-ignore-system=false"), Some(("no-ignore-system", false)));
+        assert_eq!(switch_flag("--quarantine=true"), Some(("quarantine", true)));
+        for bad in ["--no-no-ignore-system", "--no-interval", "--daemon=yes", "--interval=5", "--no-"] {
+            assert_eq!(switch_flag(bad), None, "{}", bad);
+        }
     }
 }
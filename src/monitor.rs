@@ -2,7 +2,7 @@ use std::collections::HashMap;
 use std::time::{Duration, Instant};
 
 use crate::export;
-use crate::platform;
+use crate::platform::ProcessSource;
 
 pub struct MonitorConfig {
     pub interval_ms: u64,
@@ -20,19 +20,23 @@ struct PrevSample {
     sustain_count: u32,
 }
 
-pub fn run_stealth(cfg: MonitorConfig, mut exporter: Option<&mut export::Exporter>) -> Result<(), String> {
-    let num_cpus = platform::num_cpus()? as f64;
-    let total_mem = platform::total_mem_bytes()? as f64;
+pub fn run_stealth(
+    cfg: MonitorConfig,
+    source: &dyn ProcessSource,
+    mut exporter: Option<&mut export::Exporter>,
+) -> Result<(), String> {
+    let num_cpus = source.num_cpus()? as f64;
+    let total_mem = source.total_mem_bytes()? as f64;
     let sustain_samples = resolve_sustain_samples(cfg.interval_ms, cfg.sustain_samples, cfg.sustain_seconds);
     let mut prev: HashMap<u32, PrevSample> = HashMap::new();
 
-    loop {
+    while !source.is_exhausted() {
         let start = Instant::now();
-        let processes = platform::list_processes()?;
+        let processes = source.list()?;
         let mut next: HashMap<u32, PrevSample> = HashMap::new();
 
         for proc in processes {
-            let sample = match platform::sample_process(proc.pid) {
+            let sample = match source.sample(proc.pid) {
                 Ok(sample) => sample,
                 Err(_) => continue,
             };
@@ -139,14 +143,16 @@ pub fn run_stealth(cfg: MonitorConfig, mut exporter: Option<&mut export::Exporte
             std::thread::sleep(interval - elapsed);
         }
     }
+
+    Ok(())
 }
 
-fn resolve_sustain_samples(interval_ms: u64, fallback: u32, sustain_seconds: Option<u64>) -> u32 {
+pub fn resolve_sustain_samples(interval_ms: u64, fallback: u32, sustain_seconds: Option<u64>) -> u32 {
     if let Some(seconds) = sustain_seconds {
         if interval_ms == 0 {
             return fallback;
         }
-        let intervals = (seconds * 1000 + interval_ms - 1) / interval_ms;
+        let intervals = (seconds * 1000).div_ceil(interval_ms);
         return intervals as u32;
     }
     fallback
@@ -0,0 +1,365 @@
+// Package databases only exist to be queried on Linux.
+#![cfg_attr(not(target_os = "linux"), allow(dead_code))]
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::sha256::{self, BlockBuffer};
+
+const DPKG_ADMIN: &str = "/var/lib/dpkg";
+const DPKG_ORIGIN: &str = "/etc/dpkg/origins/default";
+const RPM_DBS: [&str; 2] = ["/var/lib/rpm", "/usr/lib/sysimage/rpm"];
+/// `%{FILEDIGESTALGO}` is 1 for MD5 and 8 for SHA-256.
+const RPM_QUERY: &str =
+    "%{NAME}\\t%{VERSION}-%{RELEASE}\\t%{VENDOR}\\t%{FILEDIGESTALGO}\\n[%{FILENAMES}\\t%{FILEDIGESTS}\\n]";
+/// Top-level directories that merged-/usr systems turn into links into /usr.
+const MERGED_DIRS: [&str; 6] = ["bin", "sbin", "lib", "lib32", "lib64", "libx32"];
+
+/// The installed package that owns an executable.
+#[derive(Clone, Debug)]
+pub struct PackageInfo {
+    /// `dpkg` or `rpm`.
+    pub manager: &'static str,
+    pub name: String,
+    pub version: String,
+    /// rpm's `Vendor`, dpkg's `Origin`, or the distribution dpkg was built for.
+    pub vendor: Option<String>,
+    /// The file on disk no longer has the digest the package recorded. False
+    /// when the package recorded none (dpkg conffiles, for instance).
+    pub tampered: bool,
+}
+
+/// Which package owns `path`, from the dpkg database or else rpm's.
+pub fn lookup(path: &str) -> Option<PackageInfo> {
+    dpkg_lookup(path).or_else(|| rpm_lookup(path))
+}
+
+/// The dpkg database reduced to what lookups need. Rebuilt whenever the
+/// `status` file changes, so upgrades are picked up by a running daemon.
+struct Dpkg {
+    stamp: SystemTime,
+    /// File path to the `info/` stem of its `.list`, e.g. `libc6:amd64`.
+    owners: HashMap<String, String>,
+    /// Package name (with `:arch` for multi-arch ones) to version and origin.
+    packages: HashMap<String, (String, Option<String>)>,
+    vendor: Option<String>,
+}
+
+static DPKG: Mutex<Option<Dpkg>> = Mutex::new(None);
+
+fn dpkg_lookup(path: &str) -> Option<PackageInfo> {
+    let info_dir = Path::new(DPKG_ADMIN).join("info");
+    let stamp = fs::metadata(Path::new(DPKG_ADMIN).join("status")).and_then(|m| m.modified()).ok()?;
+    let mut db = DPKG.lock().unwrap();
+    if db.as_ref().is_none_or(|db| db.stamp != stamp) {
+        *db = Some(Dpkg::load(&info_dir, stamp));
+    }
+    let db = db.as_ref()?;
+
+    let (listed, stem) = aliases(path).into_iter().find_map(|p| db.owners.get(&p).map(|s| (p, s)))?;
+    let name = stem.split(':').next().unwrap_or(stem);
+    let (version, origin) = db
+        .packages
+        .get(stem)
+        .or_else(|| db.packages.get(name))
+        .cloned()
+        .unwrap_or_else(|| ("-".to_string(), None));
+
+    // md5sums names files relative to `/`.
+    let recorded = fs::read_to_string(info_dir.join(format!("{}.md5sums", stem)))
+        .ok()
+        .and_then(|sums| {
+            let relative = listed.trim_start_matches('/');
+            sums.lines().find_map(|line| {
+                let (sum, file) = line.split_once("  ")?;
+                (file == relative).then(|| sum.to_string())
+            })
+        });
+    let tampered = recorded.is_some_and(|sum| md5_hex(path).is_some_and(|actual| actual != sum));
+
+    Some(PackageInfo {
+        manager: "dpkg",
+        name: name.to_string(),
+        version,
+        vendor: origin.or_else(|| db.vendor.clone()),
+        tampered,
+    })
+}
+
+impl Dpkg {
+    fn load(info_dir: &Path, stamp: SystemTime) -> Dpkg {
+        let mut owners = HashMap::new();
+        if let Ok(entries) = fs::read_dir(info_dir) {
+            for entry in entries.flatten() {
+                let file_name = entry.file_name();
+                let Some(stem) = file_name.to_str().and_then(|n| n.strip_suffix(".list")) else {
+                    continue;
+                };
+                let Ok(list) = fs::read_to_string(entry.path()) else {
+                    continue;
+                };
+                for line in list.lines() {
+                    owners.entry(line.to_string()).or_insert_with(|| stem.to_string());
+                }
+            }
+        }
+
+        let mut packages = HashMap::new();
+        let status = fs::read_to_string(Path::new(DPKG_ADMIN).join("status")).unwrap_or_default();
+        for paragraph in status.split("\n\n") {
+            let field = |key: &str| {
+                paragraph
+                    .lines()
+                    .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))
+                    .map(|v| v.trim().to_string())
+            };
+            let (Some(name), Some(version)) = (field("Package"), field("Version")) else {
+                continue;
+            };
+            if field("Status").is_some_and(|s| !s.ends_with(" installed")) {
+                continue;
+            }
+            let origin = field("Origin");
+            // Multi-arch packages name their .list files `NAME:ARCH`.
+            if let Some(arch) = field("Architecture").filter(|a| a != "all") {
+                packages.insert(format!("{}:{}", name, arch), (version.clone(), origin.clone()));
+            }
+            packages.insert(name, (version, origin));
+        }
+
+        let vendor = fs::read_to_string(DPKG_ORIGIN)
+            .ok()
+            .and_then(|o| o.lines().find_map(|l| l.strip_prefix("Vendor:").map(|v| v.trim().to_string())));
+
+        Dpkg {
+            stamp,
+            owners,
+            packages,
+            vendor,
+        }
+    }
+}
+
+/// `path`, plus its spelling outside /usr when the top-level directory is a
+/// merged-/usr link: dpkg still lists `/bin/bash` for `/usr/bin/bash`.
+fn aliases(path: &str) -> Vec<String> {
+    let mut out = vec![path.to_string()];
+    if let Some(rest) = path.strip_prefix("/usr/") {
+        let top = rest.split('/').next().unwrap_or("");
+        let linked = fs::symlink_metadata(format!("/{}", top)).is_ok_and(|m| m.file_type().is_symlink());
+        if MERGED_DIRS.contains(&top) && linked {
+            out.push(format!("/{}", rest));
+        }
+    }
+    out
+}
+
+fn rpm_lookup(path: &str) -> Option<PackageInfo> {
+    if !RPM_DBS.iter().any(|db| Path::new(db).is_dir()) {
+        return None;
+    }
+    // One query for the header and every file's recorded digest, compared
+    // here rather than forking `rpm -V` as well.
+    let output = Command::new("rpm")
+        .args(["-qf", "--queryformat", RPM_QUERY, "--", path])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None; // Not owned by any package
+    }
+    let (mut info, recorded) = parse_rpm_query(&String::from_utf8_lossy(&output.stdout), &aliases(path))?;
+    info.tampered = recorded.is_some_and(|(algo, sum)| {
+        let actual = match algo.as_str() {
+            // Packages built before rpm recorded the algorithm used MD5.
+            "1" | "(none)" => md5_hex(path),
+            "8" => sha256::file_hex(path).ok(),
+            _ => return false,
+        };
+        actual.is_some_and(|actual| actual != sum)
+    });
+    Some(info)
+}
+
+/// The package from `rpm -qf --queryformat RPM_QUERY` output, and the digest
+/// algorithm and digest it recorded for whichever of `paths` it lists. Only
+/// the first package is read when several own the file.
+fn parse_rpm_query(stdout: &str, paths: &[String]) -> Option<(PackageInfo, Option<(String, String)>)> {
+    let mut lines = stdout.lines();
+    let mut fields = lines.next()?.split('\t');
+    let name = fields.next()?.to_string();
+    let version = fields.next()?.to_string();
+    let vendor = fields.next().filter(|v| !v.is_empty() && *v != "(none)").map(str::to_string);
+    let algo = fields.next().unwrap_or("(none)").to_string();
+    let recorded = lines
+        .map_while(|line| line.split_once('\t').filter(|(_, sum)| !sum.contains('\t')))
+        .find(|(file, sum)| !sum.is_empty() && paths.iter().any(|p| p == file))
+        .map(|(_, sum)| (algo, sum.to_string()));
+    let info = PackageInfo {
+        manager: "rpm",
+        name,
+        version,
+        vendor,
+        tampered: false,
+    };
+    Some((info, recorded))
+}
+
+/// MD5 of the file at `path` as lowercase hex, the digest dpkg records.
+fn md5_hex(path: &str) -> Option<String> {
+    let mut file = File::open(path).ok()?;
+    let mut md5 = Md5::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).ok()?;
+        if n == 0 {
+            break;
+        }
+        md5.update(&buf[..n]);
+    }
+    Some(sha256::hex(&md5.finish()))
+}
+
+/// RFC 1321.
+struct Md5 {
+    state: [u32; 4],
+    blocks: BlockBuffer,
+}
+
+const SHIFTS: [u32; 64] = [
+    7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14, 20, 5, 9, 14,
+    20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6,
+    10, 15, 21,
+];
+
+/// `floor(abs(sin(i + 1)) * 2^32)`.
+const K: [u32; 64] = [
+    0xd76a_a478, 0xe8c7_b756, 0x2420_70db, 0xc1bd_ceee,
+    0xf57c_0faf, 0x4787_c62a, 0xa830_4613, 0xfd46_9501,
+    0x6980_98d8, 0x8b44_f7af, 0xffff_5bb1, 0x895c_d7be,
+    0x6b90_1122, 0xfd98_7193, 0xa679_438e, 0x49b4_0821,
+    0xf61e_2562, 0xc040_b340, 0x265e_5a51, 0xe9b6_c7aa,
+    0xd62f_105d, 0x0244_1453, 0xd8a1_e681, 0xe7d3_fbc8,
+    0x21e1_cde6, 0xc337_07d6, 0xf4d5_0d87, 0x455a_14ed,
+    0xa9e3_e905, 0xfcef_a3f8, 0x676f_02d9, 0x8d2a_4c8a,
+    0xfffa_3942, 0x8771_f681, 0x6d9d_6122, 0xfde5_380c,
+    0xa4be_ea44, 0x4bde_cfa9, 0xf6bb_4b60, 0xbebf_bc70,
+    0x289b_7ec6, 0xeaa1_27fa, 0xd4ef_3085, 0x0488_1d05,
+    0xd9d4_d039, 0xe6db_99e5, 0x1fa2_7cf8, 0xc4ac_5665,
+    0xf429_2244, 0x432a_ff97, 0xab94_23a7, 0xfc93_a039,
+    0x655b_59c3, 0x8f0c_cc92, 0xffef_f47d, 0x8584_5dd1,
+    0x6fa8_7e4f, 0xfe2c_e6e0, 0xa301_4314, 0x4e08_11a1,
+    0xf753_7e82, 0xbd3a_f235, 0x2ad7_d2bb, 0xeb86_d391,
+];
+
+impl Md5 {
+    fn new() -> Md5 {
+        Md5 {
+            state: [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476],
+            blocks: BlockBuffer::new(),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        let state = &mut self.state;
+        self.blocks.update(data, |block| md5_compress(state, block));
+    }
+
+    fn finish(mut self) -> [u8; 16] {
+        self.blocks.finish(false, |block| md5_compress(&mut self.state, block));
+        let mut out = [0u8; 16];
+        for (chunk, word) in out.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        out
+    }
+}
+
+/// Folds one 64-byte block into `state`.
+fn md5_compress(state: &mut [u32; 4], block: &[u8; 64]) {
+    let mut m = [0u32; 16];
+    for (word, bytes) in m.iter_mut().zip(block.chunks_exact(4)) {
+        *word = u32::from_le_bytes(bytes.try_into().unwrap());
+    }
+    let [mut a, mut b, mut c, mut d] = *state;
+    for i in 0..64 {
+        let (f, g) = match i / 16 {
+            0 => ((b & c) | (!b & d), i),
+            1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+            2 => (b ^ c ^ d, (3 * i + 5) % 16),
+            _ => (c ^ (b | !d), (7 * i) % 16),
+        };
+        let rotated = a.wrapping_add(f).wrapping_add(K[i]).wrapping_add(m[g]).rotate_left(SHIFTS[i]);
+        a = d;
+        d = c;
+        c = b;
+        b = b.wrapping_add(rotated);
+    }
+    for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digest(data: &[u8]) -> String {
+        let mut md5 = Md5::new();
+        md5.update(data);
+        sha256::hex(&md5.finish())
+    }
+
+    // The test suite in RFC 1321, appendix A.5.
+    #[test]
+    fn rfc_1321_vectors() {
+        assert_eq!(digest(b""), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(digest(b"a"), "0cc175b9c0f1b6a831c399e269772661");
+        assert_eq!(digest(b"abc"), "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(digest(b"message digest"), "f96b697d7cb7938d525a2f31aaf161d0");
+        assert_eq!(digest(b"abcdefghijklmnopqrstuvwxyz"), "c3fcd3d76192e4007dfb496cca67e13b");
+        assert_eq!(
+            digest(b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789"),
+            "d174ab98d277d9f5a5611c2c9f419d9f"
+        );
+        assert_eq!(digest("1234567890".repeat(8).as_bytes()), "57edf4a22be3c955ac49da2e2107b67a");
+    }
+
+    #[test]
+    fn split_updates_match_one_shot() {
+        let data: Vec<u8> = (0..=255u8).cycle().take(1000).collect();
+        let whole = digest(&data);
+        for step in [1, 3, 55, 56, 63, 64, 65, 127] {
+            let mut md5 = Md5::new();
+            for piece in data.chunks(step) {
+                md5.update(piece);
+            }
+            assert_eq!(sha256::hex(&md5.finish()), whole, "pieces of {}", step);
+        }
+    }
+
+    #[test]
+    fn reads_the_package_and_the_recorded_digest() {
+        let stdout = "bash\t5.2.26-3.fc40\tFedora Project\t8\n\
+                      /etc/skel/.bashrc\tabc\n\
+                      /usr/bin\t\n\
+                      /usr/bin/bash\tf00d\n\
+                      other\t1-1\t(none)\t1\n\
+                      /usr/bin/bash\tbeef\n";
+        let paths = vec!["/usr/bin/bash".to_string(), "/bin/bash".to_string()];
+        let (info, recorded) = parse_rpm_query(stdout, &paths).unwrap();
+        assert_eq!((info.name.as_str(), info.version.as_str()), ("bash", "5.2.26-3.fc40"));
+        assert_eq!(info.vendor.as_deref(), Some("Fedora Project"));
+        assert_eq!(recorded, Some(("8".to_string(), "f00d".to_string())));
+
+        // A directory has no digest, and files of a second owner are not ours.
+        let (info, recorded) = parse_rpm_query("other\t1-1\t(none)\t(none)\n/usr/bin\t\n", &paths).unwrap();
+        assert_eq!((info.vendor, recorded), (None, None));
+        let paths = vec!["/usr/bin".to_string()];
+        assert_eq!(parse_rpm_query("x\t1-1\t\t1\n/usr/bin\t\n", &paths).unwrap().1, None);
+    }
+}
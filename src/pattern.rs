@@ -0,0 +1,518 @@
+//! Small matcher for `path~`/`name~` globs and `re:` rules. Patterns are
+//! compiled once when the allowlist loads into a program that runs as a Pike
+//! VM: every live state advances one character at a time, so a match costs
+//! at most the text length times the program length, whatever the pattern.
+//!
+//! Regex subset: literals, `.`, `[...]` classes with ranges and `^`
+//! negation, `\d \w \s` (and upper-case negations), `^ $`, groups with `|`,
+//! and `* + ? {n} {n,} {n,m}` (a trailing lazy `?` is accepted; it changes
+//! nothing for a yes/no match). Unanchored unless `^`/`$` are used.
+//!
+//! Globs always match the whole string: `*` is any run without `/`, `**` is
+//! any run, `?` is one character other than `/`, `[...]` a class (`!` or `^`
+//! negates).
+
+#[derive(Clone, Copy)]
+enum Perl {
+    Digit,
+    Word,
+    Space,
+}
+
+#[derive(Clone)]
+enum ClassItem {
+    Range(char, char),
+    Perl(Perl, bool),
+}
+
+#[derive(Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class { items: Vec<ClassItem>, negated: bool },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat { node: Box<Node>, min: u32, max: Option<u32> },
+}
+
+/// Largest `{n,m}` bound; each repetition is a copy of the repeated node.
+const MAX_REPEAT: u32 = 1000;
+/// Largest compiled program, so nested repeats cannot blow up memory.
+const MAX_PROGRAM: usize = 10_000;
+
+#[derive(Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class { items: Vec<ClassItem>, negated: bool },
+    Start,
+    End,
+    /// Continue at both targets.
+    Split(usize, usize),
+    Jmp(usize),
+    Match,
+}
+
+#[derive(Clone)]
+pub struct Pattern {
+    prog: Vec<Inst>,
+}
+
+impl Pattern {
+    pub fn regex(source: &str) -> Result<Pattern, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            pos: 0,
+        };
+        let alts = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err(format!("unmatched ')' in regex {}", source));
+        }
+        Pattern::compile(&[Node::Group(alts)]).map_err(|e| format!("{} in regex {}", e, source))
+    }
+
+    pub fn glob(source: &str) -> Result<Pattern, String> {
+        let chars: Vec<char> = source.chars().collect();
+        let mut nodes = vec![Node::Start];
+        let mut idx = 0;
+        while idx < chars.len() {
+            match chars[idx] {
+                '*' if chars.get(idx + 1) == Some(&'*') => {
+                    nodes.push(repeat(Node::Any));
+                    idx += 1;
+                }
+                '*' => nodes.push(repeat(not_slash())),
+                '?' => nodes.push(not_slash()),
+                '[' => {
+                    let mut parser = Parser {
+                        chars: chars.clone(),
+                        pos: idx + 1,
+                    };
+                    if parser.peek() == Some('!') {
+                        parser.chars[parser.pos] = '^';
+                    }
+                    nodes.push(parser.class().map_err(|e| format!("{} in glob {}", e, source))?);
+                    idx = parser.pos;
+                    continue;
+                }
+                c => nodes.push(Node::Char(c)),
+            }
+            idx += 1;
+        }
+        nodes.push(Node::End);
+        Pattern::compile(&nodes).map_err(|e| format!("{} in glob {}", e, source))
+    }
+
+    fn compile(nodes: &[Node]) -> Result<Pattern, String> {
+        let mut prog = Vec::new();
+        for node in nodes {
+            emit(node, &mut prog)?;
+        }
+        prog.push(Inst::Match);
+        Ok(Pattern { prog })
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        let text: Vec<char> = text.chars().collect();
+        let mut vm = Vm {
+            prog: &self.prog,
+            text: &text,
+            seen: vec![0; self.prog.len()],
+            stack: Vec::new(),
+        };
+        let mut current = Vec::new();
+        let mut next = Vec::new();
+        for pos in 0..=text.len() {
+            // Unanchored: a new attempt starts at every position.
+            if vm.add(0, pos, &mut current) {
+                return true;
+            }
+            let Some(&c) = text.get(pos) else {
+                break;
+            };
+            for &pc in &current {
+                let step = match &self.prog[pc] {
+                    Inst::Char(want) => *want == c,
+                    Inst::Any => true,
+                    Inst::Class { items, negated } => class_matches(items, c) != *negated,
+                    _ => false,
+                };
+                if step && vm.add(pc + 1, pos + 1, &mut next) {
+                    return true;
+                }
+            }
+            current.clear();
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+}
+
+/// Appends the instructions for one node.
+fn emit(node: &Node, prog: &mut Vec<Inst>) -> Result<(), String> {
+    match node {
+        Node::Char(c) => prog.push(Inst::Char(*c)),
+        Node::Any => prog.push(Inst::Any),
+        Node::Class { items, negated } => prog.push(Inst::Class {
+            items: items.clone(),
+            negated: *negated,
+        }),
+        Node::Start => prog.push(Inst::Start),
+        Node::End => prog.push(Inst::End),
+        Node::Group(alts) => {
+            let mut exits = Vec::new();
+            for (idx, alt) in alts.iter().enumerate() {
+                let split = prog.len();
+                if idx + 1 < alts.len() {
+                    prog.push(Inst::Split(split + 1, 0));
+                }
+                for node in alt {
+                    emit(node, prog)?;
+                }
+                if idx + 1 < alts.len() {
+                    exits.push(prog.len());
+                    prog.push(Inst::Jmp(0));
+                    prog[split] = Inst::Split(split + 1, prog.len());
+                }
+            }
+            for exit in exits {
+                prog[exit] = Inst::Jmp(prog.len());
+            }
+        }
+        Node::Repeat { node, min, max } => {
+            if *min > MAX_REPEAT || max.is_some_and(|m| m > MAX_REPEAT) {
+                return Err(format!("repeat count over {}", MAX_REPEAT));
+            }
+            for _ in 0..*min {
+                emit(node, prog)?;
+            }
+            match max {
+                None => {
+                    let split = prog.len();
+                    prog.push(Inst::Split(split + 1, 0));
+                    emit(node, prog)?;
+                    prog.push(Inst::Jmp(split));
+                    prog[split] = Inst::Split(split + 1, prog.len());
+                }
+                Some(max) => {
+                    // Each optional copy may skip straight past the rest.
+                    let mut splits = Vec::new();
+                    for _ in *min..*max {
+                        splits.push(prog.len());
+                        prog.push(Inst::Split(prog.len() + 1, 0));
+                        emit(node, prog)?;
+                    }
+                    for split in splits {
+                        prog[split] = Inst::Split(split + 1, prog.len());
+                    }
+                }
+            }
+        }
+    }
+    if prog.len() > MAX_PROGRAM {
+        return Err("pattern too large".to_string());
+    }
+    Ok(())
+}
+
+fn repeat(node: Node) -> Node {
+    Node::Repeat {
+        node: Box::new(node),
+        min: 0,
+        max: None,
+    }
+}
+
+fn not_slash() -> Node {
+    Node::Class {
+        items: vec![ClassItem::Range('/', '/')],
+        negated: true,
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// Sequences separated by `|`, up to a `)` or the end.
+    fn alternation(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alts = vec![self.sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alts.push(self.sequence()?);
+        }
+        Ok(alts)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Ok(nodes)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        match self.next() {
+            Some('(') => {
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let alts = self.alternation()?;
+                if self.next() != Some(')') {
+                    return Err("unclosed '('".to_string());
+                }
+                Ok(Node::Group(alts))
+            }
+            Some('[') => self.class(),
+            Some('.') => Ok(Node::Any),
+            Some('^') => Ok(Node::Start),
+            Some('$') => Ok(Node::End),
+            Some('\\') => match self.escape()? {
+                ClassItem::Range(c, _) => Ok(Node::Char(c)),
+                item => Ok(Node::Class {
+                    items: vec![item],
+                    negated: false,
+                }),
+            },
+            Some(c @ ('*' | '+' | '?')) => Err(format!("nothing to repeat before '{}'", c)),
+            Some(c) => Ok(Node::Char(c)),
+            None => Err("unexpected end".to_string()),
+        }
+    }
+
+    fn quantified(&mut self, atom: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('{') => match self.braces() {
+                Some(bounds) => bounds,
+                None => return Ok(atom),
+            },
+            Some(c @ ('*' | '+' | '?')) => {
+                self.pos += 1;
+                match c {
+                    '*' => (0, None),
+                    '+' => (1, None),
+                    _ => (0, Some(1)),
+                }
+            }
+            _ => return Ok(atom),
+        };
+        if matches!(atom, Node::Start | Node::End) {
+            return Err("cannot repeat an anchor".to_string());
+        }
+        if self.peek() == Some('?') {
+            self.pos += 1;
+        }
+        Ok(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    /// `{n}`, `{n,}` or `{n,m}`; anything else leaves `{` as a literal.
+    fn braces(&mut self) -> Option<(u32, Option<u32>)> {
+        let rest: String = self.chars[self.pos + 1..].iter().collect();
+        let close = rest.find('}')?;
+        let body = &rest[..close];
+        let (min, max) = match body.split_once(',') {
+            Some((lo, "")) => (lo.parse().ok()?, None),
+            Some((lo, hi)) => (lo.parse().ok()?, Some(hi.parse().ok()?)),
+            None => {
+                let n = body.parse().ok()?;
+                (n, Some(n))
+            }
+        };
+        if max.is_some_and(|m| m < min) {
+            return None;
+        }
+        self.pos += body.chars().count() + 2;
+        Some((min, max))
+    }
+
+    /// Parses a class body; the opening `[` is already consumed.
+    fn class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.next().ok_or("unclosed '['")?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let item = if c == '\\' { self.escape()? } else { ClassItem::Range(c, c) };
+            match item {
+                ClassItem::Range(lo, _) if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|n| *n != ']') => {
+                    self.pos += 1;
+                    let hi = match self.next() {
+                        Some('\\') => match self.escape()? {
+                            ClassItem::Range(hi, _) => hi,
+                            _ => return Err("bad class range".to_string()),
+                        },
+                        Some(hi) => hi,
+                        None => return Err("unclosed '['".to_string()),
+                    };
+                    if hi < lo {
+                        return Err(format!("bad class range {}-{}", lo, hi));
+                    }
+                    items.push(ClassItem::Range(lo, hi));
+                }
+                item => items.push(item),
+            }
+        }
+        Ok(Node::Class { items, negated })
+    }
+
+    /// The character after a `\`.
+    fn escape(&mut self) -> Result<ClassItem, String> {
+        let c = self.next().ok_or("trailing '\\'")?;
+        Ok(match c {
+            'd' => ClassItem::Perl(Perl::Digit, false),
+            'D' => ClassItem::Perl(Perl::Digit, true),
+            'w' => ClassItem::Perl(Perl::Word, false),
+            'W' => ClassItem::Perl(Perl::Word, true),
+            's' => ClassItem::Perl(Perl::Space, false),
+            'S' => ClassItem::Perl(Perl::Space, true),
+            't' => ClassItem::Range('\t', '\t'),
+            'n' => ClassItem::Range('\n', '\n'),
+            c if c.is_ascii_alphanumeric() => return Err(format!("unknown escape \\{}", c)),
+            c => ClassItem::Range(c, c),
+        })
+    }
+}
+
+struct Vm<'p> {
+    prog: &'p [Inst],
+    text: &'p [char],
+    /// `pos + 1` for every instruction already queued at `pos`, so each
+    /// state is queued at most once per position.
+    seen: Vec<usize>,
+    stack: Vec<usize>,
+}
+
+impl Vm<'_> {
+    /// Queues `pc` at `pos` on `list`, following jumps, splits and anchors.
+    /// True when that reaches `Match`.
+    fn add(&mut self, pc: usize, pos: usize, list: &mut Vec<usize>) -> bool {
+        self.stack.push(pc);
+        while let Some(pc) = self.stack.pop() {
+            if self.seen[pc] == pos + 1 {
+                continue;
+            }
+            self.seen[pc] = pos + 1;
+            match self.prog[pc] {
+                Inst::Jmp(to) => self.stack.push(to),
+                Inst::Split(first, second) => {
+                    self.stack.push(second);
+                    self.stack.push(first);
+                }
+                Inst::Start if pos == 0 => self.stack.push(pc + 1),
+                Inst::End if pos == self.text.len() => self.stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => {
+                    self.stack.clear();
+                    return true;
+                }
+                _ => list.push(pc),
+            }
+        }
+        false
+    }
+}
+
+fn class_matches(items: &[ClassItem], c: char) -> bool {
+    items.iter().any(|item| match item {
+        ClassItem::Range(lo, hi) => (*lo..=*hi).contains(&c),
+        ClassItem::Perl(perl, negated) => {
+            let hit = match perl {
+                Perl::Digit => c.is_ascii_digit(),
+                Perl::Word => c.is_alphanumeric() || c == '_',
+                Perl::Space => c.is_whitespace(),
+            };
+            hit != *negated
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn globs_match_the_whole_string() {
+        let glob = Pattern::glob("/opt/app-*/bin/app").unwrap();
+        assert!(glob.is_match("/opt/app-1.2.3/bin/app"));
+        assert!(!glob.is_match("/opt/app-1/x/bin/app"));
+        assert!(!glob.is_match("/opt/app-1/bin/app2"));
+        let deep = Pattern::glob("/nix/store/**/bin/node").unwrap();
+        assert!(deep.is_match("/nix/store/abc-node-20/lib/x/bin/node"));
+        let class = Pattern::glob("worker-[!0-4]?").unwrap();
+        assert!(class.is_match("worker-7a"));
+        assert!(!class.is_match("worker-3a"));
+        assert!(!class.is_match("worker-7/"));
+    }
+
+    #[test]
+    fn regex_subset() {
+        let cases = [
+            ("^node(js)?$", "nodejs", true),
+            ("^node(js)?$", "node-js", false),
+            ("python3\\.\\d+", "/usr/bin/python3.12", true),
+            ("^(cargo|rustc) ", "rustc --edition 2021", true),
+            ("^a{2,3}$", "aaaa", false),
+            ("^a{2,}b", "aaab", true),
+            ("[^\\s]+\\.sh$", "run job.sh", true),
+            ("x*?y", "xxy", true),
+            ("(a*)*$", "", true),
+        ];
+        for (regex, text, expected) in cases {
+            assert_eq!(Pattern::regex(regex).unwrap().is_match(text), expected, "{} on {}", regex, text);
+        }
+    }
+
+    #[test]
+    fn bad_patterns_fail_to_compile() {
+        assert!(Pattern::regex("(ab").is_err());
+        assert!(Pattern::regex("ab)").is_err());
+        assert!(Pattern::regex("*a").is_err());
+        assert!(Pattern::regex("\\q").is_err());
+        assert!(Pattern::regex("a{1001}").is_err());
+        assert!(Pattern::regex("((a{1000}){1000})").is_err());
+        assert!(Pattern::glob("[z-a]").is_err());
+    }
+
+    #[test]
+    fn pathological_patterns_stay_linear() {
+        // Exponential for a backtracking matcher: (a*)* and (a|a)* against
+        // a long run of a's with no way to match the end.
+        let text = format!("{}b", "a".repeat(20_000));
+        let nested = Pattern::regex("^(a*)*c").unwrap();
+        let alternation = Pattern::regex("^(a|a)*c$").unwrap();
+        let start = std::time::Instant::now();
+        assert!(!nested.is_match(&text));
+        assert!(!alternation.is_match(&text));
+        assert!(Pattern::glob("**a**a**a**c").unwrap().is_match(&format!("{}c", text)));
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    }
+}
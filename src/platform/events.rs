@@ -0,0 +1,283 @@
+#[cfg(not(target_os = "linux"))]
+use std::time::Duration;
+
+/// Process lifecycle events from the kernel. Only thread-group leaders are
+/// reported; thread exec/exit noise is dropped.
+pub enum ProcEvent {
+    /// A new process; it runs the parent's image until it execs.
+    Fork { pid: u32, ppid: u32 },
+    Exec { pid: u32 },
+    Uid { pid: u32 },
+    /// The name changed; exec sends one just before `Exec`.
+    Comm { pid: u32, comm: String },
+    Exit { pid: u32 },
+}
+
+#[cfg(target_os = "linux")]
+pub use linux_impl::ProcEvents;
+
+#[cfg(not(target_os = "linux"))]
+pub struct ProcEvents;
+
+#[cfg(not(target_os = "linux"))]
+impl ProcEvents {
+    pub fn open() -> Result<ProcEvents, String> {
+        Err("proc connector requires linux".to_string())
+    }
+
+    pub fn recv(&self, timeout: Duration) -> Result<Vec<ProcEvent>, String> {
+        std::thread::sleep(timeout);
+        Ok(Vec::new())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux_impl {
+    use std::mem;
+    use std::os::raw::{c_int, c_short, c_uint, c_void};
+    use std::time::Duration;
+
+    use super::ProcEvent;
+
+    const AF_NETLINK: c_int = 16;
+    const SOCK_DGRAM: c_int = 2;
+    const SOCK_CLOEXEC: c_int = 0o2000000;
+    const NETLINK_CONNECTOR: c_int = 11;
+    const CN_IDX_PROC: u32 = 1;
+    const CN_VAL_PROC: u32 = 1;
+    const NLMSG_DONE: u16 = 3;
+    const PROC_CN_MCAST_LISTEN: u32 = 1;
+    const POLLIN: c_short = 1;
+
+    const PROC_EVENT_FORK: u32 = 0x0000_0001;
+    const PROC_EVENT_EXEC: u32 = 0x0000_0002;
+    const PROC_EVENT_UID: u32 = 0x0000_0004;
+    const PROC_EVENT_COMM: u32 = 0x0000_0200;
+    const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+    const NLMSG_HDR_LEN: usize = 16;
+    const CN_MSG_LEN: usize = 20;
+    const PROC_EVENT_HDR_LEN: usize = 16;
+    const TASK_COMM_LEN: usize = 16;
+
+    #[repr(C)]
+    struct SockaddrNl {
+        nl_family: u16,
+        nl_pad: u16,
+        nl_pid: u32,
+        nl_groups: u32,
+    }
+
+    #[repr(C)]
+    struct PollFd {
+        fd: c_int,
+        events: c_short,
+        revents: c_short,
+    }
+
+    extern "C" {
+        fn socket(domain: c_int, ty: c_int, protocol: c_int) -> c_int;
+        fn bind(fd: c_int, addr: *const c_void, len: c_uint) -> c_int;
+        fn send(fd: c_int, buf: *const c_void, len: usize, flags: c_int) -> isize;
+        fn recv(fd: c_int, buf: *mut c_void, len: usize, flags: c_int) -> isize;
+        fn poll(fds: *mut PollFd, nfds: u64, timeout: c_int) -> c_int;
+        fn close(fd: c_int) -> c_int;
+    }
+
+    /// Subscription to the netlink proc connector. Needs CAP_NET_ADMIN.
+    pub struct ProcEvents {
+        fd: c_int,
+    }
+
+    impl ProcEvents {
+        pub fn open() -> Result<ProcEvents, String> {
+            let fd = unsafe { socket(AF_NETLINK, SOCK_DGRAM | SOCK_CLOEXEC, NETLINK_CONNECTOR) };
+            if fd < 0 {
+                return Err(format!("netlink socket failed: {}", std::io::Error::last_os_error()));
+            }
+            let events = ProcEvents { fd };
+
+            let addr = SockaddrNl {
+                nl_family: AF_NETLINK as u16,
+                nl_pad: 0,
+                nl_pid: 0,
+                nl_groups: CN_IDX_PROC,
+            };
+            let ret = unsafe {
+                bind(
+                    fd,
+                    &addr as *const _ as *const c_void,
+                    mem::size_of::<SockaddrNl>() as c_uint,
+                )
+            };
+            if ret != 0 {
+                return Err(format!("netlink bind failed: {}", std::io::Error::last_os_error()));
+            }
+
+            let mut msg = Vec::with_capacity(NLMSG_HDR_LEN + CN_MSG_LEN + 4);
+            let total = (NLMSG_HDR_LEN + CN_MSG_LEN + 4) as u32;
+            msg.extend_from_slice(&total.to_ne_bytes());
+            msg.extend_from_slice(&NLMSG_DONE.to_ne_bytes());
+            msg.extend_from_slice(&0u16.to_ne_bytes());
+            msg.extend_from_slice(&0u32.to_ne_bytes());
+            msg.extend_from_slice(&std::process::id().to_ne_bytes());
+            msg.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+            msg.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+            msg.extend_from_slice(&0u32.to_ne_bytes());
+            msg.extend_from_slice(&0u32.to_ne_bytes());
+            msg.extend_from_slice(&4u16.to_ne_bytes());
+            msg.extend_from_slice(&0u16.to_ne_bytes());
+            msg.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+
+            let sent = unsafe { send(fd, msg.as_ptr() as *const c_void, msg.len(), 0) };
+            if sent < 0 {
+                return Err(format!("netlink subscribe failed: {}", std::io::Error::last_os_error()));
+            }
+            Ok(events)
+        }
+
+        /// Waits up to `timeout` for events. Returns an empty list on timeout.
+        pub fn recv(&self, timeout: Duration) -> Result<Vec<ProcEvent>, String> {
+            let mut pfd = PollFd {
+                fd: self.fd,
+                events: POLLIN,
+                revents: 0,
+            };
+            let ms = timeout.as_millis().min(c_int::MAX as u128) as c_int;
+            let ready = unsafe { poll(&mut pfd, 1, ms) };
+            if ready < 0 {
+                let err = std::io::Error::last_os_error();
+                if err.kind() == std::io::ErrorKind::Interrupted {
+                    return Ok(Vec::new());
+                }
+                return Err(format!("netlink poll failed: {}", err));
+            }
+            if ready == 0 {
+                return Ok(Vec::new());
+            }
+
+            let mut buf = [0u8; 8192];
+            let len = unsafe { recv(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len(), 0) };
+            if len < 0 {
+                return Err(format!("netlink recv failed: {}", std::io::Error::last_os_error()));
+            }
+            Ok(parse_messages(&buf[..len as usize]))
+        }
+    }
+
+    impl Drop for ProcEvents {
+        fn drop(&mut self) {
+            unsafe {
+                close(self.fd);
+            }
+        }
+    }
+
+    fn parse_messages(buf: &[u8]) -> Vec<ProcEvent> {
+        let mut out = Vec::new();
+        let mut offset = 0usize;
+        while offset + NLMSG_HDR_LEN <= buf.len() {
+            let msg_len = read_u32(buf, offset) as usize;
+            if msg_len < NLMSG_HDR_LEN || offset + msg_len > buf.len() {
+                break;
+            }
+            let ev = offset + NLMSG_HDR_LEN + CN_MSG_LEN;
+            if ev + PROC_EVENT_HDR_LEN <= offset + msg_len {
+                if let Some(event) = parse_event(&buf[ev..offset + msg_len]) {
+                    out.push(event);
+                }
+            }
+            offset += (msg_len + 3) & !3;
+        }
+        out
+    }
+
+    fn parse_event(ev: &[u8]) -> Option<ProcEvent> {
+        let what = read_u32(ev, 0);
+        let data = PROC_EVENT_HDR_LEN;
+        if ev.len() < data + 8 {
+            return None;
+        }
+        let pid = read_u32(ev, data);
+        let tgid = read_u32(ev, data + 4);
+        // Fork data is parent pid/tgid then child pid/tgid; a new thread has
+        // a child pid that differs from its tgid.
+        if what == PROC_EVENT_FORK {
+            if ev.len() < data + 16 {
+                return None;
+            }
+            let child_pid = read_u32(ev, data + 8);
+            let child_tgid = read_u32(ev, data + 12);
+            if child_pid != child_tgid {
+                return None;
+            }
+            return Some(ProcEvent::Fork { pid: child_tgid, ppid: tgid });
+        }
+        if pid != tgid {
+            return None;
+        }
+        match what {
+            PROC_EVENT_EXEC => Some(ProcEvent::Exec { pid: tgid }),
+            PROC_EVENT_EXIT => Some(ProcEvent::Exit { pid: tgid }),
+            PROC_EVENT_UID => Some(ProcEvent::Uid { pid: tgid }),
+            PROC_EVENT_COMM => {
+                let raw = ev.get(data + 8..data + 8 + TASK_COMM_LEN)?;
+                let end = raw.iter().position(|b| *b == 0).unwrap_or(raw.len());
+                let comm = String::from_utf8_lossy(&raw[..end]).into_owned();
+                Some(ProcEvent::Comm { pid: tgid, comm })
+            }
+            _ => None,
+        }
+    }
+
+    fn read_u32(buf: &[u8], offset: usize) -> u32 {
+        let mut raw = [0u8; 4];
+        raw.copy_from_slice(&buf[offset..offset + 4]);
+        u32::from_ne_bytes(raw)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn event(what: u32, data: &[u32], tail: &[u8]) -> Vec<u8> {
+            let mut ev = what.to_ne_bytes().to_vec();
+            ev.resize(PROC_EVENT_HDR_LEN, 0);
+            for value in data {
+                ev.extend_from_slice(&value.to_ne_bytes());
+            }
+            ev.extend_from_slice(tail);
+            ev
+        }
+
+        #[test]
+        fn fork_reports_new_processes_only() {
+            match parse_event(&event(PROC_EVENT_FORK, &[40, 40, 41, 41], &[])) {
+                Some(ProcEvent::Fork { pid: 41, ppid: 40 }) => {}
+                _ => panic!("fork not parsed"),
+            }
+            // A process forked from a worker thread of 40 still names 40 as parent.
+            match parse_event(&event(PROC_EVENT_FORK, &[43, 40, 44, 44], &[])) {
+                Some(ProcEvent::Fork { pid: 44, ppid: 40 }) => {}
+                _ => panic!("fork from a thread not parsed"),
+            }
+            assert!(parse_event(&event(PROC_EVENT_FORK, &[40, 40, 42, 40], &[])).is_none());
+        }
+
+        #[test]
+        fn comm_carries_the_new_name() {
+            let mut comm = *b"curl\0\0\0\0\0\0\0\0\0\0\0\0";
+            match parse_event(&event(PROC_EVENT_COMM, &[7, 7], &comm)) {
+                Some(ProcEvent::Comm { pid: 7, comm }) => assert_eq!(comm, "curl"),
+                _ => panic!("comm not parsed"),
+            }
+            comm = *b"0123456789abcdef";
+            match parse_event(&event(PROC_EVENT_COMM, &[7, 7], &comm)) {
+                Some(ProcEvent::Comm { comm, .. }) => assert_eq!(comm, "0123456789abcdef"),
+                _ => panic!("unterminated comm not parsed"),
+            }
+            assert!(parse_event(&event(PROC_EVENT_COMM, &[8, 7], &comm)).is_none());
+            assert!(parse_event(&event(PROC_EVENT_COMM, &[7, 7], b"short")).is_none());
+        }
+    }
+}
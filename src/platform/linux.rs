@@ -1,21 +1,608 @@
-use super::{ProcSample, ProcessInfo};
+use std::fs;
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+use std::os::raw::{c_int, c_long};
+use std::path::{Path, PathBuf};
+use std::ptr;
+
+use super::{Freeze, KillOutcome, ProcHandle, ProcSample, ProcessInfo};
+
+const PROC_ROOT: &str = "/proc";
+const SC_CLK_TCK: c_int = 2;
+const SC_PAGESIZE: c_int = 30;
+const SIGKILL: c_int = 9;
+const SIGTERM: c_int = 15;
+const SIGCONT: c_int = 18;
+const SIGSTOP: c_int = 19;
+const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+const QUARANTINE_CGROUP: &str = "zen.quarantine";
+const KTHREADD_PID: u32 = 2;
+const PRIO_PROCESS: c_int = 0;
+const ESRCH: i32 = 3;
+const SYS_PIDFD_SEND_SIGNAL: c_long = 424;
+const SYS_PIDFD_OPEN: c_long = 434;
+
+extern "C" {
+    fn sysconf(name: c_int) -> c_long;
+    fn kill(pid: c_int, sig: c_int) -> c_int;
+    fn syscall(num: c_long, ...) -> c_long;
+    fn setpriority(which: c_int, who: c_int, prio: c_int) -> c_int;
+}
 
 pub fn list_processes() -> Result<Vec<ProcessInfo>, String> {
-    Err("linux not implemented".to_string())
+    list_processes_in(Path::new(PROC_ROOT))
+}
+
+pub fn process_info(pid: u32) -> Result<ProcessInfo, String> {
+    let root = Path::new(PROC_ROOT);
+    read_process(&root.join(pid.to_string()), pid).ok_or_else(|| format!("pid {} not found", pid))
 }
 
-pub fn sample_process(_pid: u32) -> Result<ProcSample, String> {
-    Err("linux not implemented".to_string())
+pub fn sample_process(pid: u32) -> Result<ProcSample, String> {
+    sample_process_in(Path::new(PROC_ROOT), pid)
 }
 
 pub fn num_cpus() -> Result<u32, String> {
-    Err("linux not implemented".to_string())
+    num_cpus_in(Path::new(PROC_ROOT))
 }
 
 pub fn total_mem_bytes() -> Result<u64, String> {
-    Err("linux not implemented".to_string())
+    total_mem_bytes_in(Path::new(PROC_ROOT))
+}
+
+pub fn kill_process(pid: u32) -> Result<(), String> {
+    let ret = unsafe { kill(pid as c_int, SIGKILL) };
+    if ret != 0 {
+        return Err(format!("kill failed: {}", io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Opens a pidfd and records start time and executable. If either no longer
+/// matches what the scan saw in `info`, the PID was recycled in between.
+pub fn pin_process(info: &ProcessInfo) -> Result<ProcHandle, String> {
+    let root = Path::new(PROC_ROOT);
+    let raw = unsafe { syscall(SYS_PIDFD_OPEN, info.pid as c_int, 0 as c_int) };
+    // Owned before any early return, so a failed pin closes it.
+    let fd = if raw >= 0 {
+        Some(unsafe { OwnedFd::from_raw_fd(raw as c_int) })
+    } else {
+        None
+    };
+    // Read identity after the pidfd exists so it describes the pinned process.
+    let (start_time, path) = read_identity(root, info.pid).ok_or("process exited before pin")?;
+    if path != info.path || (info.start_time != 0 && start_time != info.start_time) {
+        return Err("pid recycled before pin".to_string());
+    }
+    Ok(ProcHandle {
+        pid: info.pid,
+        path,
+        start_time,
+        fd,
+    })
+}
+
+pub fn kill_pinned(handle: &ProcHandle) -> Result<KillOutcome, String> {
+    signal_pinned(handle, SIGKILL)
+}
+
+pub fn terminate_pinned(handle: &ProcHandle) -> Result<KillOutcome, String> {
+    signal_pinned(handle, SIGTERM)
+}
+
+/// Zombies have no `exe` link, so they fail the identity check too.
+pub fn is_alive(handle: &ProcHandle) -> bool {
+    if !identity_matches(handle) {
+        return false;
+    }
+    let ret = match &handle.fd {
+        Some(fd) => unsafe {
+            syscall(
+                SYS_PIDFD_SEND_SIGNAL,
+                fd.as_raw_fd(),
+                0 as c_int,
+                ptr::null::<u8>(),
+                0 as c_int,
+            )
+        },
+        None => unsafe { kill(handle.pid as c_int, 0) as c_long },
+    };
+    ret == 0
+}
+
+/// There is no pidfd variant of setpriority, so the identity is re-checked
+/// right before the raw-PID calls. On Linux the nice value belongs to each
+/// thread, so every task is reniced; threads started later inherit it from
+/// the thread that creates them.
+pub fn renice_pinned(handle: &ProcHandle, nice: i32) -> Result<KillOutcome, String> {
+    if handle.fd.is_some() && !identity_matches(handle) {
+        return Ok(KillOutcome::IdentityChanged);
+    }
+    for tid in task_ids_in(Path::new(PROC_ROOT), handle.pid) {
+        if unsafe { setpriority(PRIO_PROCESS, tid as c_int, nice) } != 0 {
+            let err = io::Error::last_os_error();
+            // The thread exited after the listing.
+            if err.raw_os_error() == Some(ESRCH) && tid != handle.pid {
+                continue;
+            }
+            return Err(format!("setpriority failed: {}", err));
+        }
+    }
+    Ok(if handle.fd.is_some() { KillOutcome::Verified } else { KillOutcome::Unverified })
+}
+
+/// Moves the process into its own frozen cgroup when cgroup v2 is writable,
+/// otherwise stops it with SIGSTOP.
+pub fn freeze_pinned(handle: &ProcHandle) -> Result<Freeze, String> {
+    if !identity_matches(handle) {
+        return Err("identity changed".to_string());
+    }
+    if let Ok(original) = cgroup_freeze(handle.pid) {
+        return Ok(Freeze::Cgroup { original });
+    }
+    match signal_pinned(handle, SIGSTOP)? {
+        KillOutcome::IdentityChanged => Err("identity changed".to_string()),
+        _ => Ok(Freeze::Stop),
+    }
+}
+
+pub fn thaw_pinned(handle: &ProcHandle, freeze: &Freeze) -> Result<(), String> {
+    match freeze {
+        Freeze::Cgroup { original } => cgroup_thaw(handle.pid, original),
+        Freeze::Stop => match signal_pinned(handle, SIGCONT)? {
+            KillOutcome::IdentityChanged => Err("identity changed".to_string()),
+            _ => Ok(()),
+        },
+    }
+}
+
+/// Re-checks start time and executable, then signals through the pidfd.
+/// Without a pidfd (kernel < 5.3) falls back to a raw-PID signal.
+fn signal_pinned(handle: &ProcHandle, sig: c_int) -> Result<KillOutcome, String> {
+    let Some(fd) = &handle.fd else {
+        if unsafe { kill(handle.pid as c_int, sig) } != 0 {
+            return Err(format!("kill failed: {}", io::Error::last_os_error()));
+        }
+        return Ok(KillOutcome::Unverified);
+    };
+
+    if !identity_matches(handle) {
+        return Ok(KillOutcome::IdentityChanged);
+    }
+
+    let ret = unsafe {
+        syscall(
+            SYS_PIDFD_SEND_SIGNAL,
+            fd.as_raw_fd(),
+            sig,
+            ptr::null::<u8>(),
+            0 as c_int,
+        )
+    };
+    if ret != 0 {
+        return Err(format!("pidfd_send_signal failed: {}", std::io::Error::last_os_error()));
+    }
+    Ok(KillOutcome::Verified)
+}
+
+fn identity_matches(handle: &ProcHandle) -> bool {
+    read_identity(Path::new(PROC_ROOT), handle.pid) == Some((handle.start_time, handle.path.clone()))
+}
+
+/// Returns the cgroup the process came from so `cgroup_thaw` can put it back.
+fn cgroup_freeze(pid: u32) -> Result<String, String> {
+    let cgroup = fs::read_to_string(Path::new(PROC_ROOT).join(pid.to_string()).join("cgroup"))
+        .map_err(|err| err.to_string())?;
+    let original = cgroup
+        .lines()
+        .find_map(|line| line.strip_prefix("0::"))
+        .ok_or("not on cgroup v2")?
+        .to_string();
+
+    let dir = quarantine_cgroup(pid);
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    // cgroupfs creates the control files itself; a plain directory means no v2 here.
+    if !dir.join("cgroup.freeze").exists() {
+        let _ = fs::remove_dir(&dir);
+        let _ = fs::remove_dir(dir.parent().unwrap_or(&dir));
+        return Err("cgroup v2 freezer unavailable".to_string());
+    }
+    if let Err(err) = write_control(&dir.join("cgroup.procs"), &pid.to_string()) {
+        let _ = fs::remove_dir(&dir);
+        return Err(err);
+    }
+    if let Err(err) = write_control(&dir.join("cgroup.freeze"), "1") {
+        let _ = cgroup_thaw(pid, &original);
+        return Err(err.to_string());
+    }
+    Ok(original)
+}
+
+/// Thaws, moves the process back if it is still alive and removes the
+/// quarantine cgroup.
+fn cgroup_thaw(pid: u32, original: &str) -> Result<(), String> {
+    let dir = quarantine_cgroup(pid);
+    write_control(&dir.join("cgroup.freeze"), "0").map_err(|err| format!("thaw failed: {}", err))?;
+    let back = Path::new(CGROUP_ROOT).join(original.trim_start_matches('/'));
+    let _ = write_control(&back.join("cgroup.procs"), &pid.to_string());
+    let _ = fs::remove_dir(&dir);
+    Ok(())
+}
+
+/// Writes an existing control file; never creates one.
+fn write_control(path: &Path, value: &str) -> Result<(), String> {
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    file.write_all(value.as_bytes())
+        .map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+fn quarantine_cgroup(pid: u32) -> PathBuf {
+    Path::new(CGROUP_ROOT).join(QUARANTINE_CGROUP).join(format!("pid-{}", pid))
+}
+
+/// Lists processes from a procfs-shaped directory. `root` is `/proc` in
+/// production and a fixture tree otherwise.
+pub fn list_processes_in(root: &Path) -> Result<Vec<ProcessInfo>, String> {
+    let entries = fs::read_dir(root).map_err(|err| format!("read {} failed: {}", root.display(), err))?;
+
+    let mut processes = Vec::new();
+    for entry in entries.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|s| s.parse::<u32>().ok()) else {
+            continue;
+        };
+        // Processes may exit between readdir and the reads below.
+        if let Some(info) = read_process(&entry.path(), pid) {
+            processes.push(info);
+        }
+    }
+
+    processes.sort_by_key(|p| p.pid);
+    Ok(processes)
+}
+
+pub fn sample_process_in(root: &Path, pid: u32) -> Result<ProcSample, String> {
+    let dir = root.join(pid.to_string());
+    let stat = fs::read_to_string(dir.join("stat")).map_err(|_| "read stat failed".to_string())?;
+    let fields = stat_fields(&stat).ok_or("bad stat format")?;
+    // Fields after comm start at state (field 3); utime/stime are fields 14/15.
+    let utime: u64 = fields.get(11).and_then(|v| v.parse().ok()).ok_or("bad utime")?;
+    let stime: u64 = fields.get(12).and_then(|v| v.parse().ok()).ok_or("bad stime")?;
+
+    let statm = fs::read_to_string(dir.join("statm")).map_err(|_| "read statm failed".to_string())?;
+    let rss_pages: u64 = statm
+        .split_whitespace()
+        .nth(1)
+        .and_then(|v| v.parse().ok())
+        .ok_or("bad statm format")?;
+
+    let ticks = sysconf_or(SC_CLK_TCK, 100);
+    let page_size = sysconf_or(SC_PAGESIZE, 4096);
+    Ok(ProcSample {
+        cpu_ns: utime.saturating_add(stime).saturating_mul(1_000_000_000) / ticks,
+        rss_bytes: rss_pages.saturating_mul(page_size),
+    })
+}
+
+pub fn num_cpus_in(root: &Path) -> Result<u32, String> {
+    let stat = fs::read_to_string(root.join("stat")).map_err(|_| "read stat failed".to_string())?;
+    let count = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu") && line.as_bytes().get(3).is_some_and(u8::is_ascii_digit))
+        .count();
+    if count == 0 {
+        return Err("no cpus in stat".to_string());
+    }
+    Ok(count as u32)
+}
+
+pub fn total_mem_bytes_in(root: &Path) -> Result<u64, String> {
+    let meminfo = fs::read_to_string(root.join("meminfo")).map_err(|_| "read meminfo failed".to_string())?;
+    for line in meminfo.lines() {
+        if let Some(rest) = line.strip_prefix("MemTotal:") {
+            let kb: u64 = rest
+                .split_whitespace()
+                .next()
+                .and_then(|v| v.parse().ok())
+                .ok_or("bad MemTotal")?;
+            return Ok(kb * 1024);
+        }
+    }
+    Err("MemTotal not found".to_string())
+}
+
+fn read_process(dir: &Path, pid: u32) -> Option<ProcessInfo> {
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+    let fields = stat_fields(&stat)?;
+    let ppid = fields.get(1)?.parse().ok()?;
+    let start_time = fields.get(19).and_then(|v| v.parse().ok()).unwrap_or(0);
+
+    let name = match fs::read_to_string(dir.join("comm")) {
+        Ok(comm) => comm.trim_end_matches('\n').to_string(),
+        Err(_) => stat_comm(&stat)?.to_string(),
+    };
+
+    let uid = fs::read_to_string(dir.join("status"))
+        .ok()
+        .and_then(|status| parse_status_uid(&status))
+        .unwrap_or(0);
+
+    let path = fs::read_link(dir.join("exe"))
+        .ok()
+        .map(|p| p.to_string_lossy().into_owned());
+
+    let args = fs::read(dir.join("cmdline"))
+        .map(|raw| parse_cmdline(&raw))
+        .unwrap_or_default();
+
+    Some(ProcessInfo {
+        pid,
+        ppid,
+        uid,
+        name,
+        path,
+        args,
+        start_time,
+    })
+}
+
+/// Start time (field 22 of stat, in clock ticks since boot) and executable.
+fn read_identity(root: &Path, pid: u32) -> Option<(u64, Option<String>)> {
+    let dir = root.join(pid.to_string());
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+    let start_time = stat_fields(&stat)?.get(19)?.parse().ok()?;
+    let path = fs::read_link(dir.join("exe"))
+        .ok()
+        .map(|p| p.to_string_lossy().into_owned());
+    Some((start_time, path))
+}
+
+/// The thread ids under `/proc/<pid>/task`, or just `pid` if unreadable.
+fn task_ids_in(root: &Path, pid: u32) -> Vec<u32> {
+    let tids: Vec<u32> = fs::read_dir(root.join(pid.to_string()).join("task"))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    if tids.is_empty() {
+        vec![pid]
+    } else {
+        tids
+    }
+}
+
+/// `comm` may contain spaces and parentheses, so it is delimited by the
+/// first `(` and the last `)`.
+fn stat_comm(stat: &str) -> Option<&str> {
+    let open = stat.find('(')?;
+    let close = stat.rfind(')')?;
+    stat.get(open + 1..close)
+}
+
+fn stat_fields(stat: &str) -> Option<Vec<&str>> {
+    let close = stat.rfind(')')?;
+    Some(stat.get(close + 1..)?.split_whitespace().collect())
+}
+
+/// Effective uid, matching `pbi_uid` on macOS.
+fn parse_status_uid(status: &str) -> Option<u32> {
+    let line = status.lines().find(|l| l.starts_with("Uid:"))?;
+    line["Uid:".len()..].split_whitespace().nth(1)?.parse().ok()
+}
+
+fn parse_cmdline(raw: &[u8]) -> Vec<String> {
+    raw.split(|b| *b == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect()
+}
+
+fn sysconf_or(name: c_int, fallback: u64) -> u64 {
+    let value = unsafe { sysconf(name) };
+    if value > 0 {
+        value as u64
+    } else {
+        fallback
+    }
+}
+
+/// Only init's own helpers. Distro binaries under `/usr/bin/`, `/usr/sbin/`
+/// or `/usr/libexec/` are anything a package manager installed, so they go
+/// through the allowlist like everything else.
+const SYSTEM_PREFIXES: &[&str] = &["/usr/lib/systemd/", "/lib/systemd/"];
+const INIT_PID: u32 = 1;
+
+pub fn is_system_process(info: &ProcessInfo) -> bool {
+    // Kernel threads have no executable and descend from kthreadd.
+    if info.path.is_none() && (info.pid == KTHREADD_PID || info.ppid == KTHREADD_PID) {
+        return true;
+    }
+    if info.pid == INIT_PID {
+        return true;
+    }
+    match &info.path {
+        Some(path) => SYSTEM_PREFIXES.iter().any(|p| path.starts_with(p)),
+        None => false,
+    }
 }
 
-pub fn kill_process(_pid: u32) -> Result<(), String> {
-    Err("linux not implemented".to_string())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// Builds a procfs stand-in: one directory per process plus the
+    /// top-level `stat` and `meminfo`.
+    struct FakeProc {
+        root: PathBuf,
+    }
+
+    impl FakeProc {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("zen-proc-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            fs::create_dir_all(&root).unwrap();
+            fs::write(root.join("stat"), "cpu  10 0 10 100\ncpu0 5 0 5 50\ncpu1 5 0 5 50\nintr 0\n").unwrap();
+            fs::write(root.join("meminfo"), "MemTotal:       16384 kB\nMemFree:         1024 kB\n").unwrap();
+            FakeProc { root }
+        }
+
+        fn add(&self, pid: u32, ppid: u32, comm: &str, uid: u32, exe: Option<&str>, cmdline: &[&str]) {
+            let dir = self.root.join(pid.to_string());
+            fs::create_dir_all(&dir).unwrap();
+            let stat = format!(
+                "{} ({}) S {} {} {} 0 -1 4194304 100 0 0 0 250 50 0 0 20 0 1 0 4242 1000 300\n",
+                pid, comm, ppid, pid, pid
+            );
+            fs::write(dir.join("stat"), stat).unwrap();
+            fs::write(dir.join("comm"), format!("{}\n", comm)).unwrap();
+            fs::write(dir.join("status"), format!("Name:\t{}\nUid:\t1000\t{}\t{}\t{}\n", comm, uid, uid, uid)).unwrap();
+            fs::write(dir.join("statm"), "1000 300 100 1 0 200 0\n").unwrap();
+            let mut raw = Vec::new();
+            for arg in cmdline {
+                raw.extend_from_slice(arg.as_bytes());
+                raw.push(0);
+            }
+            fs::write(dir.join("cmdline"), raw).unwrap();
+            if let Some(exe) = exe {
+                symlink(exe, dir.join("exe")).unwrap();
+            }
+        }
+    }
+
+    impl Drop for FakeProc {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+
+    fn info(pid: u32, ppid: u32, path: Option<&str>) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            ppid,
+            uid: 0,
+            name: String::new(),
+            path: path.map(str::to_string),
+            args: Vec::new(),
+            start_time: 0,
+        }
+    }
+
+    #[test]
+    fn lists_processes_from_fixture() {
+        let proc = FakeProc::new("list");
+        proc.add(2, 0, "kthreadd", 0, None, &[]);
+        proc.add(812, 1, "tmux: server", 1000, Some("/usr/bin/tmux"), &["tmux", "new", "-s", "x"]);
+        proc.add(40, 2, "kworker/0:1", 0, None, &[]);
+        fs::create_dir_all(proc.root.join("self")).unwrap();
+        fs::create_dir_all(proc.root.join("sys")).unwrap();
+
+        let list = list_processes_in(&proc.root).unwrap();
+        let pids: Vec<u32> = list.iter().map(|p| p.pid).collect();
+        assert_eq!(pids, vec![2, 40, 812]);
+
+        let tmux = &list[2];
+        assert_eq!(tmux.ppid, 1);
+        assert_eq!(tmux.uid, 1000);
+        assert_eq!(tmux.name, "tmux: server");
+        assert_eq!(tmux.path.as_deref(), Some("/usr/bin/tmux"));
+        assert_eq!(tmux.args, vec!["tmux", "new", "-s", "x"]);
+        assert_eq!(tmux.start_time, 4242);
+
+        assert_eq!(list[1].ppid, 2);
+        assert!(list[1].path.is_none());
+        assert!(list[1].args.is_empty());
+    }
+
+    #[test]
+    fn comm_falls_back_to_stat() {
+        let proc = FakeProc::new("comm");
+        proc.add(77, 1, "odd) name (x", 0, Some("/opt/odd"), &[]);
+        fs::remove_file(proc.root.join("77/comm")).unwrap();
+
+        let list = list_processes_in(&proc.root).unwrap();
+        assert_eq!(list[0].name, "odd) name (x");
+        assert_eq!(list[0].ppid, 1);
+    }
+
+    #[test]
+    fn skips_processes_that_vanish() {
+        let proc = FakeProc::new("vanish");
+        proc.add(10, 1, "alive", 0, Some("/opt/alive"), &[]);
+        fs::create_dir_all(proc.root.join("11")).unwrap();
+
+        let list = list_processes_in(&proc.root).unwrap();
+        assert_eq!(list.len(), 1);
+        assert_eq!(list[0].pid, 10);
+    }
+
+    #[test]
+    fn samples_and_machine_totals() {
+        let proc = FakeProc::new("sample");
+        proc.add(5, 1, "busy", 0, Some("/opt/busy"), &[]);
+
+        let sample = sample_process_in(&proc.root, 5).unwrap();
+        let ticks = sysconf_or(SC_CLK_TCK, 100);
+        assert_eq!(sample.cpu_ns, 300 * 1_000_000_000 / ticks);
+        assert_eq!(sample.rss_bytes, 300 * sysconf_or(SC_PAGESIZE, 4096));
+        assert!(sample_process_in(&proc.root, 6).is_err());
+
+        assert_eq!(num_cpus_in(&proc.root).unwrap(), 2);
+        assert_eq!(total_mem_bytes_in(&proc.root).unwrap(), 16384 * 1024);
+    }
+
+    #[test]
+    fn reads_start_time() {
+        let proc = FakeProc::new("identity");
+        proc.add(9, 1, "pinned", 0, Some("/opt/pinned"), &[]);
+
+        let (start, path) = read_identity(&proc.root, 9).unwrap();
+        assert_eq!(start, 4242);
+        assert_eq!(path.as_deref(), Some("/opt/pinned"));
+    }
+
+    #[test]
+    fn pin_rejects_a_start_time_other_than_the_scan() {
+        let mut me = process_info(std::process::id()).unwrap();
+        assert_ne!(me.start_time, 0);
+        let handle = pin_process(&me).unwrap();
+        assert_eq!(handle.start_time, me.start_time);
+
+        me.start_time += 1;
+        assert!(pin_process(&me).is_err());
+    }
+
+    #[test]
+    fn lists_every_task_of_a_process() {
+        let proc = FakeProc::new("tasks");
+        proc.add(300, 1, "worker", 1000, Some("/usr/bin/worker"), &["worker"]);
+        for tid in [300, 301, 305] {
+            fs::create_dir_all(proc.root.join("300/task").join(tid.to_string())).unwrap();
+        }
+        let mut tids = task_ids_in(&proc.root, 300);
+        tids.sort();
+        assert_eq!(tids, vec![300, 301, 305]);
+        // No task directory (or no process): fall back to the pid itself.
+        assert_eq!(task_ids_in(&proc.root, 400), vec![400]);
+    }
+
+    #[test]
+    fn system_is_kernel_threads_init_and_systemd() {
+        assert!(is_system_process(&info(2, 0, None)));
+        assert!(is_system_process(&info(40, 2, None)));
+        assert!(is_system_process(&info(1, 0, Some("/usr/lib/systemd/systemd"))));
+        assert!(is_system_process(&info(300, 1, Some("/usr/lib/systemd/systemd-journald"))));
+        assert!(is_system_process(&info(301, 1, Some("/lib/systemd/systemd-udevd"))));
+
+        assert!(!is_system_process(&info(400, 1, Some("/usr/bin/curl"))));
+        assert!(!is_system_process(&info(401, 1, Some("/usr/sbin/sshd"))));
+        assert!(!is_system_process(&info(402, 1, Some("/usr/libexec/helper"))));
+        assert!(!is_system_process(&info(403, 1, Some("/sbin/agetty"))));
+        // No executable but not under kthreadd: a zombie or a hidden exe.
+        assert!(!is_system_process(&info(404, 1, None)));
+    }
 }
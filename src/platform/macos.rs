@@ -3,7 +3,7 @@ use std::mem;
 use std::os::raw::{c_char, c_int, c_uint, c_void};
 use std::ptr;
 
-use super::{ProcSample, ProcessInfo};
+use super::{Freeze, KillOutcome, ProcHandle, ProcSample, ProcessInfo};
 
 const PROC_ALL_PIDS: c_uint = 1;
 const PROC_PIDTBSDINFO: c_int = 3;
@@ -11,6 +11,13 @@ const RUSAGE_INFO_V2: c_int = 2;
 const CTL_KERN: c_int = 1;
 const KERN_PROCARGS2: c_int = 49;
 const SIGKILL: c_int = 9;
+const SIGTERM: c_int = 15;
+const SIGSTOP: c_int = 17;
+const SIGCONT: c_int = 19;
+const PRIO_PROCESS: c_int = 0;
+const CS_OPS_STATUS: c_uint = 0;
+const CS_OPS_CDHASH: c_uint = 5;
+const CS_VALID: u32 = 0x1;
 
 #[repr(C)]
 struct ProcBsdInfo {
@@ -94,6 +101,8 @@ extern "C" {
         newlen: usize,
     ) -> c_int;
     fn kill(pid: c_int, sig: c_int) -> c_int;
+    fn setpriority(which: c_int, who: c_uint, prio: c_int) -> c_int;
+    fn csops(pid: c_int, ops: c_uint, useraddr: *mut c_void, usersize: usize) -> c_int;
 }
 
 pub fn list_processes() -> Result<Vec<ProcessInfo>, String> {
@@ -171,12 +180,20 @@ pub fn list_processes() -> Result<Vec<ProcessInfo>, String> {
             name,
             path,
             args,
+            start_time: bsd_start_time(&bsdinfo),
         });
     }
 
     Ok(processes)
 }
 
+pub fn process_info(pid: u32) -> Result<ProcessInfo, String> {
+    list_processes()?
+        .into_iter()
+        .find(|p| p.pid == pid)
+        .ok_or_else(|| format!("pid {} not found", pid))
+}
+
 pub fn sample_process(pid: u32) -> Result<ProcSample, String> {
     let mut info: RusageInfoV2 = unsafe { mem::zeroed() };
     let ret = unsafe { proc_pid_rusage(pid as c_int, RUSAGE_INFO_V2, &mut info as *mut _ as *mut c_void) };
@@ -235,6 +252,95 @@ pub fn kill_process(pid: u32) -> Result<(), String> {
     Ok(())
 }
 
+/// macOS has no pidfd; the handle only records what was observed. A start
+/// time other than the scan's means the PID was recycled in between.
+pub fn pin_process(info: &ProcessInfo) -> Result<ProcHandle, String> {
+    let start_time = read_start_time(info.pid).ok_or("process exited before pin")?;
+    if info.start_time != 0 && start_time != info.start_time {
+        return Err("pid recycled before pin".to_string());
+    }
+    Ok(ProcHandle {
+        pid: info.pid,
+        path: info.path.clone(),
+        start_time,
+        fd: None,
+    })
+}
+
+fn read_start_time(pid: u32) -> Option<u64> {
+    let mut bsdinfo: ProcBsdInfo = unsafe { mem::zeroed() };
+    let ret = unsafe {
+        proc_pidinfo(
+            pid as c_int,
+            PROC_PIDTBSDINFO,
+            0,
+            &mut bsdinfo as *mut _ as *mut c_void,
+            mem::size_of::<ProcBsdInfo>() as c_int,
+        )
+    };
+    (ret > 0).then(|| bsd_start_time(&bsdinfo))
+}
+
+/// Microseconds since the epoch.
+fn bsd_start_time(info: &ProcBsdInfo) -> u64 {
+    info.pbi_start_tvsec
+        .saturating_mul(1_000_000)
+        .saturating_add(info.pbi_start_tvusec)
+}
+
+/// Without pidfds the raw PID is all there is, so the start time recorded
+/// at pin time is checked again right before each signal: a PID recycled
+/// during a grace period no longer matches it.
+fn identity_matches(handle: &ProcHandle) -> bool {
+    read_start_time(handle.pid) == Some(handle.start_time)
+}
+
+fn signal_pinned(handle: &ProcHandle, sig: c_int, name: &str) -> Result<KillOutcome, String> {
+    if !identity_matches(handle) {
+        return Ok(KillOutcome::IdentityChanged);
+    }
+    if unsafe { kill(handle.pid as c_int, sig) } != 0 {
+        return Err(format!("{} failed", name));
+    }
+    Ok(KillOutcome::Unverified)
+}
+
+pub fn kill_pinned(handle: &ProcHandle) -> Result<KillOutcome, String> {
+    signal_pinned(handle, SIGKILL, "SIGKILL")
+}
+
+pub fn terminate_pinned(handle: &ProcHandle) -> Result<KillOutcome, String> {
+    signal_pinned(handle, SIGTERM, "SIGTERM")
+}
+
+pub fn is_alive(handle: &ProcHandle) -> bool {
+    identity_matches(handle) && unsafe { kill(handle.pid as c_int, 0) == 0 }
+}
+
+pub fn renice_pinned(handle: &ProcHandle, nice: i32) -> Result<KillOutcome, String> {
+    if !identity_matches(handle) {
+        return Ok(KillOutcome::IdentityChanged);
+    }
+    if unsafe { setpriority(PRIO_PROCESS, handle.pid as c_uint, nice) } != 0 {
+        return Err("setpriority failed".to_string());
+    }
+    Ok(KillOutcome::Unverified)
+}
+
+pub fn freeze_pinned(handle: &ProcHandle) -> Result<Freeze, String> {
+    match signal_pinned(handle, SIGSTOP, "SIGSTOP")? {
+        KillOutcome::IdentityChanged => Err("identity changed".to_string()),
+        _ => Ok(Freeze::Stop),
+    }
+}
+
+pub fn thaw_pinned(handle: &ProcHandle, _freeze: &Freeze) -> Result<(), String> {
+    match signal_pinned(handle, SIGCONT, "SIGCONT")? {
+        KillOutcome::IdentityChanged => Err("identity changed".to_string()),
+        _ => Ok(()),
+    }
+}
+
 fn get_args(pid: i32) -> Vec<String> {
     let mut mib = [CTL_KERN, KERN_PROCARGS2, pid];
     let mut size: usize = 0;
@@ -308,6 +414,24 @@ const SYSTEM_PREFIXES: &[&str] = &[
     "/Library/Apple/",
 ];
 
+/// The cdhash the kernel validated `pid`'s code against, while the process
+/// still has `CS_VALID`: every page it ran so far matched that
+/// CodeDirectory.
+pub fn validated_cdhash(pid: u32) -> Option<[u8; 20]> {
+    let mut cdhash = [0u8; 20];
+    let mut status: u32 = 0;
+    unsafe {
+        if csops(pid as c_int, CS_OPS_CDHASH, cdhash.as_mut_ptr() as *mut c_void, cdhash.len()) != 0 {
+            return None;
+        }
+        // Checked after the hash, so a signature invalidated in between is caught.
+        if csops(pid as c_int, CS_OPS_STATUS, &mut status as *mut u32 as *mut c_void, mem::size_of::<u32>()) != 0 {
+            return None;
+        }
+    }
+    (status & CS_VALID != 0).then_some(cdhash)
+}
+
 pub fn is_system_process(info: &ProcessInfo) -> bool {
     match &info.path {
         Some(path) => SYSTEM_PREFIXES.iter().any(|p| path.starts_with(p)),
@@ -4,6 +4,9 @@ mod macos;
 mod linux;
 #[cfg(target_os = "windows")]
 mod windows;
+mod events;
+mod replay;
+mod watch;
 
 #[cfg(target_os = "macos")]
 use macos as platform_impl;
@@ -12,6 +15,12 @@ use linux as platform_impl;
 #[cfg(target_os = "windows")]
 use windows as platform_impl;
 
+use std::os::fd::OwnedFd;
+
+pub use events::{ProcEvent, ProcEvents};
+pub use replay::ReplaySource;
+pub use watch::FileWatch;
+
 #[derive(Clone)]
 pub struct ProcessInfo {
     pub pid: u32,
@@ -20,33 +29,147 @@ pub struct ProcessInfo {
     pub name: String,
     pub path: Option<String>,
     pub args: Vec<String>,
+    /// When the process started, read in the same scan as the rest (clock
+    /// ticks since boot on Linux, microseconds since the epoch on macOS); 0
+    /// when unknown. Pinning compares against it to catch a recycled PID.
+    pub start_time: u64,
 }
 
+#[derive(Clone)]
 pub struct ProcSample {
     pub cpu_ns: u64,
     pub rss_bytes: u64,
 }
 
-pub fn list_processes() -> Result<Vec<ProcessInfo>, String> {
-    platform_impl::list_processes()
+/// A process pinned when first observed. On Linux `fd` is a pidfd, so a
+/// later signal cannot reach a recycled PID; elsewhere it is `None`.
+pub struct ProcHandle {
+    pub pid: u32,
+    pub path: Option<String>,
+    pub start_time: u64,
+    pub fd: Option<OwnedFd>,
 }
 
-pub fn sample_process(pid: u32) -> Result<ProcSample, String> {
-    platform_impl::sample_process(pid)
+pub enum KillOutcome {
+    /// Identity re-checked and signal delivered through the pidfd.
+    Verified,
+    /// Signal sent by raw PID; the target could not be re-checked.
+    Unverified,
+    /// Start time or executable changed since the process was pinned.
+    IdentityChanged,
 }
 
-pub fn num_cpus() -> Result<u32, String> {
-    platform_impl::num_cpus()
+/// How a quarantined process was frozen, so it can be thawed the same way.
+pub enum Freeze {
+    /// Moved into a frozen cgroup v2 leaf; `original` is the cgroup to return to.
+    Cgroup { original: String },
+    /// Stopped with SIGSTOP.
+    Stop,
 }
 
-pub fn total_mem_bytes() -> Result<u64, String> {
-    platform_impl::total_mem_bytes()
+impl Freeze {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Freeze::Cgroup { .. } => "cgroup",
+            Freeze::Stop => "sigstop",
+        }
+    }
 }
 
-pub fn kill_process(pid: u32) -> Result<(), String> {
-    platform_impl::kill_process(pid)
+/// Where process snapshots come from. `NativeSource` wraps the OS backend;
+/// `ReplaySource` replays scripted snapshots from a file.
+pub trait ProcessSource: Send + Sync {
+    fn list(&self) -> Result<Vec<ProcessInfo>, String>;
+    fn process(&self, pid: u32) -> Result<ProcessInfo, String>;
+    fn sample(&self, pid: u32) -> Result<ProcSample, String>;
+    fn kill(&self, pid: u32) -> Result<(), String>;
+    fn pin(&self, info: &ProcessInfo) -> Result<ProcHandle, String>;
+    fn kill_pinned(&self, handle: &ProcHandle) -> Result<KillOutcome, String>;
+    fn terminate_pinned(&self, handle: &ProcHandle) -> Result<KillOutcome, String>;
+    /// False once the pinned process exited, became a zombie or was replaced.
+    fn is_alive(&self, handle: &ProcHandle) -> bool;
+    /// Sets the nice value of the pinned process.
+    fn renice(&self, handle: &ProcHandle, nice: i32) -> Result<KillOutcome, String>;
+    fn freeze(&self, handle: &ProcHandle) -> Result<Freeze, String>;
+    fn thaw(&self, handle: &ProcHandle, freeze: &Freeze) -> Result<(), String>;
+    fn is_system(&self, info: &ProcessInfo) -> bool;
+    fn num_cpus(&self) -> Result<u32, String>;
+    fn total_mem_bytes(&self) -> Result<u64, String>;
+
+    /// True once a finite source has no snapshots left.
+    fn is_exhausted(&self) -> bool {
+        false
+    }
 }
 
-pub fn is_system_process(info: &ProcessInfo) -> bool {
-    platform_impl::is_system_process(info)
+/// The cdhash the kernel validated `pid`'s running code against, if it
+/// still holds a valid code signature. Only macOS validates signatures.
+#[cfg(target_os = "macos")]
+pub fn validated_cdhash(pid: u32) -> Option<[u8; 20]> {
+    platform_impl::validated_cdhash(pid)
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn validated_cdhash(_pid: u32) -> Option<[u8; 20]> {
+    None
+}
+
+pub struct NativeSource;
+
+impl ProcessSource for NativeSource {
+    fn list(&self) -> Result<Vec<ProcessInfo>, String> {
+        platform_impl::list_processes()
+    }
+
+    fn process(&self, pid: u32) -> Result<ProcessInfo, String> {
+        platform_impl::process_info(pid)
+    }
+
+    fn sample(&self, pid: u32) -> Result<ProcSample, String> {
+        platform_impl::sample_process(pid)
+    }
+
+    fn kill(&self, pid: u32) -> Result<(), String> {
+        platform_impl::kill_process(pid)
+    }
+
+    fn pin(&self, info: &ProcessInfo) -> Result<ProcHandle, String> {
+        platform_impl::pin_process(info)
+    }
+
+    fn kill_pinned(&self, handle: &ProcHandle) -> Result<KillOutcome, String> {
+        platform_impl::kill_pinned(handle)
+    }
+
+    fn terminate_pinned(&self, handle: &ProcHandle) -> Result<KillOutcome, String> {
+        platform_impl::terminate_pinned(handle)
+    }
+
+    fn is_alive(&self, handle: &ProcHandle) -> bool {
+        platform_impl::is_alive(handle)
+    }
+
+    fn renice(&self, handle: &ProcHandle, nice: i32) -> Result<KillOutcome, String> {
+        platform_impl::renice_pinned(handle, nice)
+    }
+
+    fn freeze(&self, handle: &ProcHandle) -> Result<Freeze, String> {
+        platform_impl::freeze_pinned(handle)
+    }
+
+    fn thaw(&self, handle: &ProcHandle, freeze: &Freeze) -> Result<(), String> {
+        platform_impl::thaw_pinned(handle, freeze)
+    }
+
+    fn is_system(&self, info: &ProcessInfo) -> bool {
+        platform_impl::is_system_process(info)
+    }
+
+    fn num_cpus(&self) -> Result<u32, String> {
+        platform_impl::num_cpus()
+    }
+
+    fn total_mem_bytes(&self) -> Result<u64, String> {
+        platform_impl::total_mem_bytes()
+    }
 }
@@ -0,0 +1,228 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{Freeze, KillOutcome, ProcHandle, ProcSample, ProcessInfo, ProcessSource};
+
+struct ReplayProcess {
+    info: ProcessInfo,
+    sample: ProcSample,
+}
+
+/// Replays a fixed sequence of process snapshots. Each `list` call returns
+/// the next snapshot; `sample` answers from the snapshot last returned.
+///
+/// File format (one directive per line, `#` comments):
+///
+/// ```text
+/// cpus 4
+/// mem 17179869184
+/// system /usr/sbin/
+/// scan
+/// pid<TAB>ppid<TAB>uid<TAB>cpu_ns<TAB>rss_bytes<TAB>name<TAB>path[<TAB>arg...]
+/// ```
+///
+/// `scan` starts a new snapshot and a path of `-` means no executable.
+/// Killed PIDs are dropped from every later snapshot.
+pub struct ReplaySource {
+    scans: Vec<Vec<ReplayProcess>>,
+    system_prefixes: Vec<String>,
+    num_cpus: u32,
+    total_mem: u64,
+    cursor: Mutex<usize>,
+    killed: Mutex<Vec<u32>>,
+}
+
+impl ReplaySource {
+    pub fn load(path: &Path) -> Result<ReplaySource, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read replay {}: {}", path.display(), err))?;
+        ReplaySource::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<ReplaySource, String> {
+        let mut source = ReplaySource {
+            scans: Vec::new(),
+            system_prefixes: Vec::new(),
+            num_cpus: 1,
+            total_mem: 1,
+            cursor: Mutex::new(0),
+            killed: Mutex::new(Vec::new()),
+        };
+
+        for (idx, line) in content.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if trimmed == "scan" {
+                source.scans.push(Vec::new());
+                continue;
+            }
+            if let Some(value) = trimmed.strip_prefix("cpus ") {
+                source.num_cpus = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid cpus on replay line {}", idx + 1))?;
+                continue;
+            }
+            if let Some(value) = trimmed.strip_prefix("mem ") {
+                source.total_mem = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("invalid mem on replay line {}", idx + 1))?;
+                continue;
+            }
+            if let Some(value) = trimmed.strip_prefix("system ") {
+                source.system_prefixes.push(value.trim().to_string());
+                continue;
+            }
+
+            let proc = parse_process(line).ok_or_else(|| format!("invalid replay line {}: {}", idx + 1, trimmed))?;
+            let scan = source
+                .scans
+                .last_mut()
+                .ok_or_else(|| format!("process before first scan on replay line {}", idx + 1))?;
+            scan.push(proc);
+        }
+
+        if source.scans.is_empty() {
+            return Err("replay has no scans".to_string());
+        }
+        Ok(source)
+    }
+
+    fn current(&self) -> Option<&Vec<ReplayProcess>> {
+        let cursor = *self.cursor.lock().unwrap();
+        cursor.checked_sub(1).and_then(|idx| self.scans.get(idx))
+    }
+}
+
+impl ProcessSource for ReplaySource {
+    fn list(&self) -> Result<Vec<ProcessInfo>, String> {
+        let mut cursor = self.cursor.lock().unwrap();
+        let scan = self.scans.get(*cursor).ok_or("replay exhausted")?;
+        *cursor += 1;
+        let killed = self.killed.lock().unwrap();
+        Ok(scan
+            .iter()
+            .filter(|p| !killed.contains(&p.info.pid))
+            .map(|p| p.info.clone())
+            .collect())
+    }
+
+    fn process(&self, pid: u32) -> Result<ProcessInfo, String> {
+        self.current()
+            .and_then(|scan| scan.iter().find(|p| p.info.pid == pid))
+            .map(|p| p.info.clone())
+            .ok_or_else(|| "replay has no process".to_string())
+    }
+
+    fn sample(&self, pid: u32) -> Result<ProcSample, String> {
+        self.current()
+            .and_then(|scan| scan.iter().find(|p| p.info.pid == pid))
+            .map(|p| p.sample.clone())
+            .ok_or_else(|| "replay has no sample".to_string())
+    }
+
+    fn kill(&self, pid: u32) -> Result<(), String> {
+        self.killed.lock().unwrap().push(pid);
+        Ok(())
+    }
+
+    fn pin(&self, info: &ProcessInfo) -> Result<ProcHandle, String> {
+        Ok(ProcHandle {
+            pid: info.pid,
+            path: info.path.clone(),
+            start_time: 0,
+            fd: None,
+        })
+    }
+
+    /// Verified when the current snapshot still has the same PID and path.
+    fn kill_pinned(&self, handle: &ProcHandle) -> Result<KillOutcome, String> {
+        match self.process(handle.pid) {
+            Ok(info) if info.path == handle.path => {
+                self.kill(handle.pid)?;
+                Ok(KillOutcome::Verified)
+            }
+            _ => Ok(KillOutcome::IdentityChanged),
+        }
+    }
+
+    /// Replayed processes ignore SIGTERM, so a grace period always escalates.
+    fn terminate_pinned(&self, handle: &ProcHandle) -> Result<KillOutcome, String> {
+        match self.process(handle.pid) {
+            Ok(info) if info.path == handle.path => Ok(KillOutcome::Verified),
+            _ => Ok(KillOutcome::IdentityChanged),
+        }
+    }
+
+    fn is_alive(&self, handle: &ProcHandle) -> bool {
+        !self.killed.lock().unwrap().contains(&handle.pid) && self.process(handle.pid).is_ok()
+    }
+
+    fn renice(&self, handle: &ProcHandle, _nice: i32) -> Result<KillOutcome, String> {
+        match self.process(handle.pid) {
+            Ok(info) if info.path == handle.path => Ok(KillOutcome::Verified),
+            _ => Ok(KillOutcome::IdentityChanged),
+        }
+    }
+
+    fn freeze(&self, handle: &ProcHandle) -> Result<Freeze, String> {
+        self.process(handle.pid)?;
+        Ok(Freeze::Stop)
+    }
+
+    fn thaw(&self, _handle: &ProcHandle, _freeze: &Freeze) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn is_system(&self, info: &ProcessInfo) -> bool {
+        match &info.path {
+            Some(path) => self.system_prefixes.iter().any(|p| path.starts_with(p.as_str())),
+            None => false,
+        }
+    }
+
+    fn num_cpus(&self) -> Result<u32, String> {
+        Ok(self.num_cpus)
+    }
+
+    fn total_mem_bytes(&self) -> Result<u64, String> {
+        Ok(self.total_mem)
+    }
+
+    fn is_exhausted(&self) -> bool {
+        *self.cursor.lock().unwrap() >= self.scans.len()
+    }
+}
+
+fn parse_process(line: &str) -> Option<ReplayProcess> {
+    let mut fields = line.split('\t');
+    let pid = fields.next()?.trim().parse().ok()?;
+    let ppid = fields.next()?.parse().ok()?;
+    let uid = fields.next()?.parse().ok()?;
+    let cpu_ns = fields.next()?.parse().ok()?;
+    let rss_bytes = fields.next()?.parse().ok()?;
+    let name = fields.next()?.to_string();
+    let path = match fields.next()? {
+        "-" => None,
+        p => Some(p.to_string()),
+    };
+    let args = fields.map(|a| a.to_string()).collect();
+
+    Some(ReplayProcess {
+        info: ProcessInfo {
+            pid,
+            ppid,
+            uid,
+            name,
+            path,
+            args,
+            start_time: 0,
+        },
+        sample: ProcSample { cpu_ns, rss_bytes },
+    })
+}
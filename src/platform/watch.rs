@@ -0,0 +1,171 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Notices edits to a set of files. On Linux it watches their directories
+/// with inotify, so editors that save by renaming a new file into place are
+/// seen too; elsewhere, or when inotify is unavailable, it compares
+/// modification times on every check.
+pub struct FileWatch {
+    files: Vec<PathBuf>,
+    inotify: Option<Inotify>,
+    stamps: Vec<Option<SystemTime>>,
+}
+
+impl FileWatch {
+    pub fn new(files: &[PathBuf]) -> FileWatch {
+        FileWatch {
+            files: files.to_vec(),
+            inotify: Inotify::open(files).ok(),
+            stamps: files.iter().map(|f| mtime(f)).collect(),
+        }
+    }
+
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    pub fn is_inotify(&self) -> bool {
+        self.inotify.is_some()
+    }
+
+    /// True when any watched file changed since the last call. Never blocks.
+    pub fn changed(&mut self) -> bool {
+        if let Some(inotify) = &self.inotify {
+            return inotify.changed(&self.files);
+        }
+        let stamps: Vec<Option<SystemTime>> = self.files.iter().map(|f| mtime(f)).collect();
+        if stamps == self.stamps {
+            return false;
+        }
+        self.stamps = stamps;
+        true
+    }
+}
+
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// The directory a watched file lives in, as inotify needs it.
+fn parent_dir(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+struct Inotify;
+
+#[cfg(not(target_os = "linux"))]
+impl Inotify {
+    fn open(_files: &[PathBuf]) -> Result<Inotify, String> {
+        Err("inotify requires linux".to_string())
+    }
+
+    fn changed(&self, _files: &[PathBuf]) -> bool {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+use linux_impl::Inotify;
+
+#[cfg(target_os = "linux")]
+mod linux_impl {
+    use std::ffi::CString;
+    use std::os::raw::{c_char, c_int, c_void};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::PathBuf;
+
+    use super::parent_dir;
+
+    const IN_NONBLOCK: c_int = 0o4000;
+    const IN_CLOEXEC: c_int = 0o2000000;
+    const IN_CLOSE_WRITE: u32 = 0x0000_0008;
+    const IN_MOVED_TO: u32 = 0x0000_0080;
+    const IN_DELETE: u32 = 0x0000_0200;
+    const EVENT_HDR_LEN: usize = 16;
+
+    extern "C" {
+        fn inotify_init1(flags: c_int) -> c_int;
+        fn inotify_add_watch(fd: c_int, path: *const c_char, mask: u32) -> c_int;
+        fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+        fn close(fd: c_int) -> c_int;
+    }
+
+    pub struct Inotify {
+        fd: c_int,
+        /// Watch descriptor and the directory it watches.
+        dirs: Vec<(c_int, PathBuf)>,
+    }
+
+    impl Inotify {
+        pub fn open(files: &[PathBuf]) -> Result<Inotify, String> {
+            let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(format!("inotify_init1 failed: {}", std::io::Error::last_os_error()));
+            }
+            let mut inotify = Inotify { fd, dirs: Vec::new() };
+            for file in files {
+                let dir = parent_dir(file);
+                if inotify.dirs.iter().any(|(_, d)| *d == dir) {
+                    continue;
+                }
+                let c_dir = CString::new(dir.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+                let wd = unsafe { inotify_add_watch(fd, c_dir.as_ptr(), IN_CLOSE_WRITE | IN_MOVED_TO | IN_DELETE) };
+                if wd < 0 {
+                    return Err(format!(
+                        "inotify watch on {} failed: {}",
+                        dir.display(),
+                        std::io::Error::last_os_error()
+                    ));
+                }
+                inotify.dirs.push((wd, dir));
+            }
+            Ok(inotify)
+        }
+
+        /// Drains pending events and reports whether any named one of `files`.
+        pub fn changed(&self, files: &[PathBuf]) -> bool {
+            let mut hit = false;
+            let mut buf = [0u8; 4096];
+            loop {
+                let len = unsafe { read(self.fd, buf.as_mut_ptr() as *mut c_void, buf.len()) };
+                if len <= 0 {
+                    return hit;
+                }
+                let mut offset = 0usize;
+                let len = len as usize;
+                while offset + EVENT_HDR_LEN <= len {
+                    let wd = i32::from_ne_bytes(buf[offset..offset + 4].try_into().unwrap());
+                    let name_len = u32::from_ne_bytes(buf[offset + 12..offset + 16].try_into().unwrap()) as usize;
+                    let name_start = offset + EVENT_HDR_LEN;
+                    let name_end = (name_start + name_len).min(len);
+                    let name = &buf[name_start..name_end];
+                    let name = &name[..name.iter().position(|b| *b == 0).unwrap_or(name.len())];
+                    hit |= self.names_file(wd, name, files);
+                    offset = name_start + name_len;
+                }
+            }
+        }
+
+        fn names_file(&self, wd: c_int, name: &[u8], files: &[PathBuf]) -> bool {
+            let Some((_, dir)) = self.dirs.iter().find(|(w, _)| *w == wd) else {
+                return false;
+            };
+            files.iter().any(|file| {
+                parent_dir(file) == *dir && file.file_name().map(|n| n.as_bytes()) == Some(name)
+            })
+        }
+    }
+
+    impl Drop for Inotify {
+        fn drop(&mut self) {
+            unsafe {
+                close(self.fd);
+            }
+        }
+    }
+}
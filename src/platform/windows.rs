@@ -1,9 +1,13 @@
-use super::{ProcSample, ProcessInfo};
+use super::{Freeze, KillOutcome, ProcHandle, ProcSample, ProcessInfo};
 
 pub fn list_processes() -> Result<Vec<ProcessInfo>, String> {
     Err("windows not implemented".to_string())
 }
 
+pub fn process_info(_pid: u32) -> Result<ProcessInfo, String> {
+    Err("windows not implemented".to_string())
+}
+
 pub fn sample_process(_pid: u32) -> Result<ProcSample, String> {
     Err("windows not implemented".to_string())
 }
@@ -19,3 +23,31 @@ pub fn total_mem_bytes() -> Result<u64, String> {
 pub fn kill_process(_pid: u32) -> Result<(), String> {
     Err("windows not implemented".to_string())
 }
+
+pub fn pin_process(_info: &ProcessInfo) -> Result<ProcHandle, String> {
+    Err("windows not implemented".to_string())
+}
+
+pub fn kill_pinned(_handle: &ProcHandle) -> Result<KillOutcome, String> {
+    Err("windows not implemented".to_string())
+}
+
+pub fn freeze_pinned(_handle: &ProcHandle) -> Result<Freeze, String> {
+    Err("windows not implemented".to_string())
+}
+
+pub fn thaw_pinned(_handle: &ProcHandle, _freeze: &Freeze) -> Result<(), String> {
+    Err("windows not implemented".to_string())
+}
+
+pub fn renice_pinned(_handle: &ProcHandle, _nice: i32) -> Result<KillOutcome, String> {
+    Err("windows not implemented".to_string())
+}
+
+pub fn terminate_pinned(_handle: &ProcHandle) -> Result<KillOutcome, String> {
+    Err("windows not implemented".to_string())
+}
+
+pub fn is_alive(_handle: &ProcHandle) -> bool {
+    false
+}
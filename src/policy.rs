@@ -1,93 +1,569 @@
-use std::collections::HashMap;
-use std::fs;
-use std::os::raw::c_void;
+use crate::cache::FileCache;
+use crate::config::{Allowlist, Condition, Field, Limit, Meta, Op, Rule, Scope, Source};
+use crate::platform::{self, ProcessInfo, ProcessSource};
+use crate::signature::SignatureInfo;
+use crate::package::PackageInfo;
+use crate::sha256;
+use crate::tree::ProcTree;
 
-use crate::config::Allowlist;
-use crate::platform::ProcessInfo;
+/// The allowlist line that decided a verdict.
+pub struct Match {
+    /// The line's key (`name`, `path~`, `rule`, ...), prefixed with `deny:`
+    /// or `quarantine:` for those rule sets.
+    pub kind: String,
+    pub value: String,
+    /// The file and line it came from.
+    pub source: Option<Source>,
+}
+
+impl Match {
+    fn new(prefix: &str, kind: &str, value: &str, allowlist: &Allowlist) -> Match {
+        let kind = format!("{}{}", prefix, kind);
+        let source = allowlist.origins.get(&format!("{}:{}", kind, value)).cloned();
+        Match {
+            kind,
+            value: value.to_string(),
+            source,
+        }
+    }
+
+    /// `name:nvim (profiles/base.txt:2)`, plus the severity when the rule
+    /// file gave one: `deny:name:nc (profiles/red.toml:7, severity high)`.
+    pub fn describe(&self) -> String {
+        match &self.source {
+            Some(Source {
+                file,
+                line,
+                meta: Meta {
+                    severity: Some(severity),
+                    ..
+                },
+                ..
+            }) => format!("{}:{} ({}:{}, severity {})", self.kind, self.value, file, line, severity),
+            Some(source) => format!("{}:{} ({}:{})", self.kind, self.value, source.file, source.line),
+            None => format!("{}:{}", self.kind, self.value),
+        }
+    }
+}
 
+pub enum Verdict {
+    Denied(Match),
+    Quarantined(Match),
+    Allowed(Match),
+    /// Nothing matched; one line per check that failed. Only `explain`
+    /// fills it in, so daemon scans build no strings for unknowns.
+    Unknown(Vec<String>),
+}
+
+impl Verdict {
+    pub fn is_allowed(&self) -> bool {
+        matches!(self, Verdict::Allowed(_))
+    }
+
+    pub fn is_denied(&self) -> bool {
+        matches!(self, Verdict::Denied(_))
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Verdict::Denied(_) => "denied",
+            Verdict::Quarantined(_) => "quarantined",
+            Verdict::Allowed(_) => "allowed",
+            Verdict::Unknown(_) => "unknown",
+        }
+    }
+
+    pub fn rule(&self) -> Option<&Match> {
+        match self {
+            Verdict::Denied(m) | Verdict::Quarantined(m) | Verdict::Allowed(m) => Some(m),
+            Verdict::Unknown(_) => None,
+        }
+    }
+
+    /// Multi-line explanation for `--explain` and `EXPLAIN`.
+    pub fn describe(&self) -> String {
+        match self {
+            Verdict::Unknown(misses) => {
+                let mut out = "verdict: unknown, no rule matched\n".to_string();
+                for miss in misses {
+                    out.push_str(&format!("  - {}\n", miss));
+                }
+                out
+            }
+            verdict => {
+                let rule = verdict.rule();
+                let mut out = format!(
+                    "verdict: {}\n  rule: {}\n",
+                    verdict.label(),
+                    rule.map(|m| m.describe()).unwrap_or_default()
+                );
+                if let Some(meta) = rule.and_then(|m| m.source.as_ref()).map(|s| &s.meta) {
+                    for (label, value) in [
+                        ("added by", &meta.added_by),
+                        ("expires", &meta.expires),
+                        ("comment", &meta.comment),
+                    ] {
+                        if let Some(value) = value {
+                            out.push_str(&format!("  {}: {}\n", label, value.replace('\n', " / ")));
+                        }
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+/// Evaluation order: `deny:` rules first (a hit is never allowed), then
+/// `quarantine:` rules, then the plain allow rules.
 pub fn is_allowed(
     info: &ProcessInfo,
     allowlist: &Allowlist,
-    hash_cache: &mut HashMap<String, String>,
-    sig_cache: &mut HashMap<String, Option<crate::signature::SignatureInfo>>,
-) -> bool {
+    tree: Option<&ProcTree>,
+    cache: &mut FileCache,
+) -> Verdict {
+    if let Some(verdict) = restriction(info, allowlist, tree, cache) {
+        return verdict;
+    }
+    match first_match(info, allowlist, tree, cache) {
+        Some((kind, value)) => Verdict::Allowed(Match::new("", &kind, &value, allowlist)),
+        None => Verdict::Unknown(Vec::new()),
+    }
+}
+
+/// The first plain rule of `allowlist` that matches, as `(kind, value)`,
+/// ignoring nested rule sets.
+fn first_match(
+    info: &ProcessInfo,
+    allowlist: &Allowlist,
+    tree: Option<&ProcTree>,
+    cache: &mut FileCache,
+) -> Option<(String, String)> {
+    let hit = |kind: &str, value: &str| Some((kind.to_string(), value.to_string()));
     if allowlist.names.contains(&info.name) {
-        return true;
+        return hit("name", &info.name);
     }
 
     if let Some(path) = &info.path {
         if allowlist.paths.contains(path) {
-            return true;
+            return hit("path", path);
         }
     }
 
     if allowlist.uids.contains(&info.uid) {
-        return true;
+        return hit("uid", &info.uid.to_string());
     }
 
     if allowlist.ppids.contains(&info.ppid) {
-        return true;
+        return hit("ppid", &info.ppid.to_string());
     }
 
     if !allowlist.args.is_empty() && !info.args.is_empty() {
         let joined = info.args.join(" ");
         for arg in &allowlist.args {
             if joined.contains(arg) {
-                return true;
+                return hit("arg", arg);
             }
         }
     }
 
     if !allowlist.hashes.is_empty() {
         if let Some(path) = &info.path {
-            let entry = hash_cache.entry(path.clone()).or_insert_with(|| {
-                compute_sha256_hex(path).unwrap_or_default()
-            });
-            if !entry.is_empty() && allowlist.hashes.contains(&entry.to_lowercase()) {
-                return true;
+            if let Some(hash) = cache.hash(path) {
+                if allowlist.hashes.contains(hash) {
+                    return hit("hash", hash);
+                }
             }
         }
     }
 
-    if !allowlist.teams.is_empty() || !allowlist.authorities.is_empty() {
+    if !allowlist.build_ids.is_empty() {
         if let Some(path) = &info.path {
-            let sig_opt = sig_cache.entry(path.clone()).or_insert_with(|| {
-                crate::signature::get_signature_info(path)
-            });
-            if let Some(sig) = sig_opt {
-                if let Some(team) = &sig.team_id {
-                    if allowlist.teams.contains(team) { return true; }
+            if let Some(id) = cache.elf(path).and_then(|elf| elf.build_id.as_deref()) {
+                if allowlist.build_ids.contains(id) {
+                    return hit("buildid", id);
                 }
-                if let Some(auth) = &sig.authority {
-                    if allowlist.authorities.contains(auth) { return true; }
+            }
+        }
+    }
+
+    if !allowlist.teams.is_empty() || !allowlist.authorities.is_empty() {
+        if let Some(sig) = verified_signature(info, cache) {
+            if let Some(team) = &sig.team_id {
+                if allowlist.teams.contains(team) {
+                    return hit("team", team);
                 }
             }
+            if let Some(auth) = sig.authority() {
+                if allowlist.authorities.contains(auth) {
+                    return hit("authority", auth);
+                }
+            }
+        }
+    }
+
+    if !allowlist.packages.is_empty() || !allowlist.vendors.is_empty() {
+        if let Some(pkg) = trusted_package(info, cache) {
+            if allowlist.packages.contains(&pkg.name) {
+                return hit("package", &pkg.name);
+            }
+            if let Some(vendor) = pkg.vendor.as_ref().filter(|v| allowlist.vendors.contains(*v)) {
+                return hit("vendor", vendor);
+            }
         }
     }
 
-    false
+    allowlist
+        .rules
+        .iter()
+        .find(|rule| rule_matches(info, rule, tree, cache))
+        .and_then(|rule| rule.text.split_once(':'))
+        .map(|(kind, value)| (kind.to_string(), value.to_string()))
 }
 
-#[cfg(target_os = "macos")]
-extern "C" {
-    fn CC_SHA256(data: *const c_void, len: u32, md: *mut u8) -> *mut u8;
+/// Why no plain rule matched, one line per kind of rule the allowlist has.
+fn misses(
+    info: &ProcessInfo,
+    allowlist: &Allowlist,
+    cache: &FileCache,
+) -> Vec<String> {
+    let path = info.path.as_deref().unwrap_or("-");
+    let mut out = vec![
+        format!("name {} is not among {} name: rules", info.name, allowlist.names.len()),
+        format!("path {} is not among {} path: rules", path, allowlist.paths.len()),
+    ];
+    if !allowlist.uids.is_empty() {
+        out.push(format!("uid {} is not among {} uid: rules", info.uid, allowlist.uids.len()));
+    }
+    if !allowlist.ppids.is_empty() {
+        out.push(format!("ppid {} is not among {} ppid: rules", info.ppid, allowlist.ppids.len()));
+    }
+    if !allowlist.args.is_empty() {
+        out.push(format!("command line matches none of {} arg: rules", allowlist.args.len()));
+    }
+    if !allowlist.hashes.is_empty() {
+        let hash = info.path.as_deref().and_then(|p| cache.peek_hash(p));
+        out.push(match hash {
+            Some(hash) => format!("sha256 {} is not among {} hash: rules", hash, allowlist.hashes.len()),
+            None => format!("sha256 unavailable for {} hash: rules", allowlist.hashes.len()),
+        });
+    }
+    if !allowlist.build_ids.is_empty() {
+        let id = info.path.as_deref().and_then(|p| cache.peek_elf(p)).and_then(|elf| elf.build_id.as_deref());
+        out.push(match id {
+            Some(id) => format!("build-id {} is not among {} buildid: rules", id, allowlist.build_ids.len()),
+            None => format!("no ELF build-id, {} buildid: rules cannot match", allowlist.build_ids.len()),
+        });
+    }
+    if !allowlist.teams.is_empty() || !allowlist.authorities.is_empty() {
+        let sig = info.path.as_deref().and_then(|p| cache.peek_signature(p));
+        out.push(match sig {
+            Some(sig) if (sig.team_id.is_some() || sig.authority().is_some()) && !signature_verified(info.pid, sig) => {
+                format!(
+                    "signature team={} authority={} is unverified, {} team:/authority: rules do not apply",
+                    sig.team_id.as_deref().unwrap_or("-"),
+                    sig.authority().unwrap_or("-"),
+                    allowlist.teams.len() + allowlist.authorities.len()
+                )
+            }
+            Some(sig) => format!(
+                "signature team={} authority={} is not among {} team:/authority: rules",
+                sig.team_id.as_deref().unwrap_or("-"),
+                sig.authority().unwrap_or("-"),
+                allowlist.teams.len() + allowlist.authorities.len()
+            ),
+            None => format!(
+                "unsigned, {} team:/authority: rules cannot match",
+                allowlist.teams.len() + allowlist.authorities.len()
+            ),
+        });
+    }
+    if !allowlist.packages.is_empty() || !allowlist.vendors.is_empty() {
+        let count = allowlist.packages.len() + allowlist.vendors.len();
+        let pkg = info
+            .path
+            .as_deref()
+            .and_then(|p| cache.peek_signature(p))
+            .and_then(|s| s.package.as_ref());
+        out.push(match pkg {
+            Some(pkg) if pkg.tampered => format!(
+                "file differs from the digest recorded by {} {} ({}), {} package:/vendor: rules do not apply",
+                pkg.name, pkg.version, pkg.manager, count
+            ),
+            Some(pkg) => format!(
+                "package {} vendor={} is not among {} package:/vendor: rules",
+                pkg.name,
+                pkg.vendor.as_deref().unwrap_or("-"),
+                count
+            ),
+            None => format!("not owned by any installed package, {} package:/vendor: rules cannot match", count),
+        });
+    }
+    if !allowlist.rules.is_empty() {
+        out.push(format!("none of {} rule:/pattern/lineage rules holds", allowlist.rules.len()));
+    }
+    out
 }
 
-#[cfg(target_os = "macos")]
-fn compute_sha256_hex(path: &str) -> Result<String, String> {
-    let data = fs::read(path).map_err(|err| format!("hash read failed: {}", err))?;
-    let mut digest = [0u8; 32];
-    unsafe {
-        CC_SHA256(data.as_ptr() as *const c_void, data.len() as u32, digest.as_mut_ptr());
+fn rule_matches(
+    info: &ProcessInfo,
+    rule: &Rule,
+    tree: Option<&ProcTree>,
+    cache: &mut FileCache,
+) -> bool {
+    // Conditions are checked in file order, so cheap ones written first
+    // spare the hash or signature lookup when they already fail.
+    rule.conditions.iter().all(|cond| match cond.scope {
+        Scope::Process => condition_matches(info, cond, cache),
+        // Without a tree there is no ancestry to vouch for the process.
+        Scope::Parent => tree
+            .and_then(|t| t.ancestors(info.pid).into_iter().next())
+            .is_some_and(|parent| condition_matches(parent, cond, cache)),
+        Scope::Ancestor => tree.is_some_and(|t| {
+            t.ancestors(info.pid)
+                .into_iter()
+                .any(|a| condition_matches(a, cond, cache))
+        }),
+    })
+}
+
+fn condition_matches(
+    info: &ProcessInfo,
+    cond: &Condition,
+    cache: &mut FileCache,
+) -> bool {
+    let test = |actual: &str| match &cond.op {
+        Op::Equals => actual == cond.value,
+        Op::Contains => actual.contains(cond.value.as_str()),
+        Op::Glob(pattern) | Op::Regex(pattern) => pattern.is_match(actual),
+    };
+    match cond.field {
+        Field::Name => test(&info.name),
+        Field::Path => info.path.as_deref().is_some_and(test),
+        Field::Uid => info.uid.to_string() == cond.value,
+        Field::Ppid => info.ppid.to_string() == cond.value,
+        Field::Arg => match cond.op {
+            Op::Equals => info.args.contains(&cond.value),
+            _ => test(&info.args.join(" ")),
+        },
+        Field::Hash => {
+            let Some(path) = &info.path else { return false };
+            cache.hash(path).is_some_and(test)
+        }
+        Field::Team | Field::Authority => {
+            let value = verified_signature(info, cache).and_then(|s| match cond.field {
+                Field::Team => s.team_id.as_deref(),
+                _ => s.authority(),
+            });
+            value.is_some_and(test)
+        }
+        Field::BuildId => {
+            let Some(path) = &info.path else { return false };
+            cache.elf(path).and_then(|elf| elf.build_id.as_deref()).is_some_and(test)
+        }
+        Field::Package => trusted_package(info, cache).is_some_and(|pkg| test(&pkg.name)),
+        Field::Vendor => trusted_package(info, cache)
+            .and_then(|pkg| pkg.vendor.as_deref())
+            .is_some_and(test),
+    }
+}
+
+/// The package that owns the executable, unless the file was modified after
+/// it was installed: a tampered file must not borrow its package's trust.
+fn trusted_package<'a>(info: &ProcessInfo, cache: &'a mut FileCache) -> Option<&'a PackageInfo> {
+    cache
+        .signature(info.path.as_ref()?)?
+        .package
+        .as_ref()
+        .filter(|pkg| !pkg.tampered)
+}
+
+/// True when the kernel vouches for `sig`: `pid` runs code validated against
+/// one of the CodeDirectories `sig` was read from. Anyone can write a team ID
+/// and certificate names into a signature; nothing here checks the CMS chain,
+/// so without the kernel's word they are only what the file claims.
+pub fn signature_verified(pid: u32, sig: &SignatureInfo) -> bool {
+    !sig.adhoc && platform::validated_cdhash(pid).is_some_and(|cdhash| sig.cdhashes.contains(&sha256::hex(&cdhash)))
+}
+
+/// The code signature of the executable, if `signature_verified` holds for
+/// it. Only these may satisfy `team:` and `authority:` rules.
+pub fn verified_signature<'a>(info: &ProcessInfo, cache: &'a mut FileCache) -> Option<&'a SignatureInfo> {
+    cache
+        .signature(info.path.as_ref()?)
+        .filter(|sig| signature_verified(info.pid, sig))
+}
+
+/// Why `pid` is allowed, flagged or unknown under `allowlist`, for
+/// `--explain` and the `EXPLAIN` IPC command.
+pub fn explain(source: &dyn ProcessSource, allowlist: &Allowlist, pid: u32) -> Result<String, String> {
+    let tree = ProcTree::from_processes(source.list()?);
+    let info = tree
+        .nodes
+        .get(&pid)
+        .map(|node| &node.info)
+        .ok_or_else(|| format!("no process with pid {}", pid))?;
+    let mut cache = FileCache::new(crate::cache::DEFAULT_CAPACITY);
+
+    let mut out = format!(
+        "pid {} {} ({}) uid={} ppid={}\n",
+        info.pid,
+        info.name,
+        info.path.as_deref().unwrap_or("-"),
+        info.uid,
+        info.ppid
+    );
+    if let Some(id) = info.path.as_deref().and_then(|p| cache.elf(p)).and_then(|elf| elf.build_id.as_ref()) {
+        out.push_str(&format!("build-id {}\n", id));
     }
-    let mut out = String::with_capacity(64);
-    for byte in digest {
-        out.push_str(&format!("{:02x}", byte));
+    if let Some(sig) = info.path.as_deref().and_then(|p| cache.signature(p)).filter(|sig| sig.identifier.is_some()) {
+        out.push_str(&describe_code_signature(sig, signature_verified(info.pid, sig)));
+    }
+    if source.is_system(info) {
+        out.push_str("system process: the daemon skips it unless --no-ignore-system or a deny/quarantine rule matches\n");
+    }
+    let verdict = match is_allowed(info, allowlist, Some(&tree), &mut cache) {
+        Verdict::Unknown(_) => Verdict::Unknown(misses(info, allowlist, &cache)),
+        verdict => verdict,
+    };
+    out.push_str(&verdict.describe());
+    if let Some(limit) = limit_for(info, allowlist, Some(&tree), &mut cache) {
+        let (kind, value) = limit.selector.text.split_once(':').unwrap_or(("limit", ""));
+        out.push_str(&format!("  budget: {}\n", Match::new("", kind, value, allowlist).describe()));
     }
     Ok(out)
 }
 
-#[cfg(not(target_os = "macos"))]
-fn compute_sha256_hex(_path: &str) -> Result<String, String> {
-    Err("hashing not supported on this platform".to_string())
+/// The deny or quarantine verdict for `info`, if one of those rules matches.
+/// They apply even to processes the daemon otherwise skips as system ones.
+pub fn restriction(
+    info: &ProcessInfo,
+    allowlist: &Allowlist,
+    tree: Option<&ProcTree>,
+    cache: &mut FileCache,
+) -> Option<Verdict> {
+    if let Some(rules) = &allowlist.deny {
+        if let Some((kind, value)) = first_match(info, rules, tree, cache) {
+            return Some(Verdict::Denied(Match::new("deny:", &kind, &value, allowlist)));
+        }
+    }
+    if let Some(rules) = &allowlist.quarantine {
+        if let Some((kind, value)) = first_match(info, rules, tree, cache) {
+            return Some(Verdict::Quarantined(Match::new("quarantine:", &kind, &value, allowlist)));
+        }
+    }
+    None
+}
+
+/// The Mach-O signature lines of `explain`.
+fn describe_code_signature(sig: &SignatureInfo, verified: bool) -> String {
+    let mut out = format!(
+        "signed: identifier={} team={}",
+        sig.identifier.as_deref().unwrap_or("-"),
+        sig.team_id.as_deref().unwrap_or("-")
+    );
+    if sig.adhoc {
+        out.push_str(" ad-hoc");
+    }
+    if sig.hardened_runtime {
+        out.push_str(" hardened-runtime");
+    }
+    if !sig.adhoc && !verified {
+        out.push_str(" unverified");
+    }
+    out.push('\n');
+    if !sig.authorities.is_empty() {
+        out.push_str(&format!("  authorities: {}\n", sig.authorities.join(" > ")));
+    }
+    if let Some(requirement) = &sig.requirement {
+        out.push_str(&format!("  designated: {}\n", requirement));
+    }
+    out
+}
+
+/// The first `limit:` whose selector matches `info`.
+pub fn limit_for<'a>(
+    info: &ProcessInfo,
+    allowlist: &'a Allowlist,
+    tree: Option<&ProcTree>,
+    cache: &mut FileCache,
+) -> Option<&'a Limit> {
+    allowlist
+        .limits
+        .iter()
+        .find(|limit| rule_matches(info, &limit.selector, tree, cache))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+    use crate::platform::ReplaySource;
+
+    const REPLAY: &str = "system /usr/sbin/\n\
+        scan\n\
+        1\t0\t0\t0\t1024\tinit\t/usr/sbin/init\n\
+        10\t1\t501\t0\t1024\tcargo\t/home/dev/.cargo/bin/cargo\n\
+        11\t10\t501\t0\t1024\tcc\t/opt/zen-test/bin/cc\n\
+        12\t1\t501\t0\t1024\tcc\t/opt/zen-test/bin/cc\n\
+        13\t1\t501\t0\t1024\tnc\t/opt/zen-test/bin/nc\t-l\n";
+
+    /// Loads `rules` from a file so verdicts carry their file and line.
+    fn explain_with(name: &str, rules: &str, pid: u32) -> Result<String, String> {
+        let path = std::env::temp_dir().join(format!("zen-policy-{}-{}.txt", name, std::process::id()));
+        std::fs::write(&path, rules).unwrap();
+        let allowlist = config::load_allowlist(&path);
+        std::fs::remove_file(&path).unwrap();
+        let source = ReplaySource::parse(REPLAY).unwrap();
+        let out = explain(&source, &allowlist?, pid)?;
+        Ok(out.replace(&path.display().to_string(), "allow.txt"))
+    }
+
+    #[test]
+    fn explains_the_rule_and_where_it_came_from() {
+        let out = explain_with("allowed", "name:zsh\nrule:name=cc & ancestor:name=cargo\n", 11).unwrap();
+        assert_eq!(
+            out,
+            "pid 11 cc (/opt/zen-test/bin/cc) uid=501 ppid=10\n\
+             verdict: allowed\n  rule: rule:name=cc & ancestor:name=cargo (allow.txt:2)\n"
+        );
+    }
+
+    #[test]
+    fn explains_every_failed_check() {
+        let out = explain_with("unknown", "name:zsh\nuid:0\nrule:name=cc & ancestor:name=cargo\n", 12).unwrap();
+        assert!(out.contains("verdict: unknown, no rule matched\n"), "{}", out);
+        assert!(out.contains("  - name cc is not among 1 name: rules\n"), "{}", out);
+        assert!(out.contains("  - uid 501 is not among 1 uid: rules\n"), "{}", out);
+        assert!(out.contains("  - none of 1 rule:/pattern/lineage rules holds\n"), "{}", out);
+    }
+
+    #[test]
+    fn deny_wins_and_system_processes_are_noted() {
+        let out = explain_with("deny", "name:nc\ndeny:arg:-l\n", 13).unwrap();
+        assert!(out.contains("verdict: denied\n  rule: deny:arg:-l (allow.txt:2)\n"), "{}", out);
+        let out = explain_with("system", "name:zsh\n", 1).unwrap();
+        assert!(out.contains("system process: the daemon skips it"), "{}", out);
+        assert!(out.contains("verdict: unknown"), "{}", out);
+        assert_eq!(explain_with("missing", "name:zsh\n", 99).err().unwrap(), "no process with pid 99");
+    }
+
+    #[test]
+    fn signatures_the_kernel_did_not_validate_are_unverified() {
+        let sig = SignatureInfo {
+            identifier: Some("dev.zen.sample".to_string()),
+            team_id: Some("ABCDE12345".to_string()),
+            authorities: vec!["Developer ID Application: Zen Test (ABCDE12345)".to_string()],
+            cdhashes: vec!["5619489a6c40e0b149604eed4b498c5dc67b48ef".to_string()],
+            ..SignatureInfo::default()
+        };
+        // Whatever this test binary runs as, it is not that code.
+        assert!(!signature_verified(std::process::id(), &sig));
+        assert_eq!(
+            describe_code_signature(&sig, false).lines().next(),
+            Some("signed: identifier=dev.zen.sample team=ABCDE12345 unverified")
+        );
+        assert_eq!(sig.display_name(false), "⚠️  SIN VERIFICAR: Zen Test (ABCDE12345) (Team: ABCDE12345)");
+        assert_eq!(sig.display_name(true), "Zen Test (ABCDE12345) (Team: ABCDE12345)");
+    }
 }
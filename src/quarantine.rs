@@ -0,0 +1,427 @@
+use std::collections::{BTreeMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::export::{self, json_escape, Exporter};
+use crate::json;
+use crate::platform::{Freeze, KillOutcome, ProcHandle, ProcessSource};
+use crate::toml::{Entry as Field, Value};
+
+/// Bumped whenever the line layout of the quarantine file changes.
+const VERSION: u64 = 1;
+
+/// Audit writer shared between the daemon loop and the IPC thread.
+pub type SharedAudit = Arc<Mutex<Option<Exporter>>>;
+
+pub struct Entry {
+    pub handle: ProcHandle,
+    pub name: String,
+    pub uid: u32,
+    pub ppid: u32,
+    pub freeze: Freeze,
+    pub since: u64,
+}
+
+/// Frozen processes waiting for a decision (`RELEASE` or `KILL`). With a
+/// `file` the list is rewritten on every change, so a daemon that dies
+/// without thawing can take its frozen processes back on the next start.
+#[derive(Default)]
+pub struct Quarantine {
+    entries: BTreeMap<u32, Entry>,
+    file: Option<PathBuf>,
+}
+
+impl Quarantine {
+    pub fn with_file(file: Option<PathBuf>) -> Quarantine {
+        Quarantine {
+            entries: BTreeMap::new(),
+            file,
+        }
+    }
+
+    pub fn insert(&mut self, entry: Entry) {
+        self.entries.insert(entry.handle.pid, entry);
+        self.persist();
+    }
+
+    pub fn contains(&self, pid: u32) -> bool {
+        self.entries.contains_key(&pid)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// One line per entry: `pid name path method since`.
+    pub fn list(&self) -> String {
+        let mut out = String::new();
+        for entry in self.entries.values() {
+            out.push_str(&format!(
+                "{} {} {} {} {}\n",
+                entry.handle.pid,
+                entry.name,
+                entry.handle.path.as_deref().unwrap_or("-"),
+                entry.freeze.label(),
+                entry.since
+            ));
+        }
+        out
+    }
+
+    pub fn release(&mut self, pid: u32, source: &dyn ProcessSource, audit: &SharedAudit) -> Result<(), String> {
+        let entry = self.entries.get(&pid).ok_or_else(|| format!("pid {} not quarantined", pid))?;
+        source.thaw(&entry.handle, &entry.freeze)?;
+        let entry = self.entries.remove(&pid).expect("entry checked above");
+        self.persist();
+        write_audit(audit, &entry, "released", None);
+        Ok(())
+    }
+
+    pub fn kill(&mut self, pid: u32, source: &dyn ProcessSource, audit: &SharedAudit) -> Result<KillOutcome, String> {
+        let entry = self.entries.get(&pid).ok_or_else(|| format!("pid {} not quarantined", pid))?;
+        let outcome = source.kill_pinned(&entry.handle)?;
+        if matches!(outcome, KillOutcome::IdentityChanged) {
+            return Ok(outcome);
+        }
+        // SIGKILL reaches frozen and stopped tasks; only the cgroup leaf is left over.
+        if matches!(entry.freeze, Freeze::Cgroup { .. }) {
+            let _ = source.thaw(&entry.handle, &entry.freeze);
+        }
+        let entry = self.entries.remove(&pid).expect("entry checked above");
+        self.persist();
+        write_audit(audit, &entry, "killed", Some(matches!(outcome, KillOutcome::Verified)));
+        Ok(outcome)
+    }
+
+    /// Forgets entries whose process exited while quarantined.
+    pub fn retain_alive(&mut self, alive: &HashSet<u32>, source: &dyn ProcessSource, audit: &SharedAudit) {
+        let gone: Vec<u32> = self.entries.keys().filter(|pid| !alive.contains(pid)).copied().collect();
+        if gone.is_empty() {
+            return;
+        }
+        for pid in gone {
+            if let Some(entry) = self.entries.remove(&pid) {
+                if matches!(entry.freeze, Freeze::Cgroup { .. }) {
+                    let _ = source.thaw(&entry.handle, &entry.freeze);
+                }
+                write_audit(audit, &entry, "quarantine-exited", None);
+            }
+        }
+        self.persist();
+    }
+
+    /// Thaws everything on daemon exit, so nothing stays frozen with no one
+    /// left to release it. Entries that fail to thaw stay in the file for
+    /// the next start. Returns how many were thawed.
+    pub fn thaw_all(&mut self, source: &dyn ProcessSource, audit: &SharedAudit) -> usize {
+        let mut thawed = 0;
+        let pids: Vec<u32> = self.entries.keys().copied().collect();
+        for pid in pids {
+            let entry = &self.entries[&pid];
+            if let Err(err) = source.thaw(&entry.handle, &entry.freeze) {
+                eprintln!("\x1b[31m[QUARANTINE]\x1b[0m No se pudo descongelar {}: {}", pid, err);
+                continue;
+            }
+            let entry = self.entries.remove(&pid).expect("entry checked above");
+            write_audit(audit, &entry, "released-on-exit", None);
+            thawed += 1;
+        }
+        self.persist();
+        thawed
+    }
+
+    /// Takes back the entries an earlier run left in the file whose process
+    /// still runs the same executable since the same start time; the rest
+    /// are dropped. Returns how many were restored.
+    pub fn restore(&mut self, source: &dyn ProcessSource, audit: &SharedAudit) -> Result<usize, String> {
+        let Some(path) = &self.file else {
+            return Ok(0);
+        };
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(format!("failed to read quarantine {}: {}", path.display(), err)),
+        };
+        let saved = parse_file(path, &content)?;
+
+        let mut restored = 0;
+        for saved in saved {
+            let Ok(info) = source.process(saved.pid) else { continue };
+            let recycled = info.path != saved.path || (saved.start_time != 0 && info.start_time != saved.start_time);
+            if recycled || self.entries.contains_key(&saved.pid) {
+                continue;
+            }
+            let Ok(handle) = source.pin(&info) else { continue };
+            let entry = Entry {
+                handle,
+                name: saved.name,
+                uid: saved.uid,
+                ppid: saved.ppid,
+                freeze: saved.freeze,
+                since: saved.since,
+            };
+            write_audit(audit, &entry, "quarantine-restored", None);
+            self.entries.insert(saved.pid, entry);
+            restored += 1;
+        }
+        self.persist();
+        Ok(restored)
+    }
+
+    /// Rewrites the file through a temporary file and a rename, or removes
+    /// it once nothing is quarantined.
+    fn persist(&self) {
+        let Some(path) = &self.file else {
+            return;
+        };
+        if let Err(err) = self.write_file(path) {
+            eprintln!("\x1b[31m[QUARANTINE]\x1b[0m {}", err);
+        }
+    }
+
+    fn write_file(&self, path: &Path) -> Result<(), String> {
+        if self.entries.is_empty() {
+            return match fs::remove_file(path) {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => {
+                    Err(format!("failed to remove {}: {}", path.display(), err))
+                }
+                _ => Ok(()),
+            };
+        }
+        let mut out = format!("{{\"zen-quarantine\":{}}}\n", VERSION);
+        for entry in self.entries.values() {
+            out.push_str(&write_entry(entry));
+            out.push('\n');
+        }
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|err| format!("failed to create {}: {}", dir.display(), err))?;
+        }
+        let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+        fs::write(&tmp, out).and_then(|()| fs::rename(&tmp, path)).map_err(|err| {
+            let _ = fs::remove_file(&tmp);
+            format!("failed to write quarantine {}: {}", path.display(), err)
+        })
+    }
+}
+
+/// Where the daemon keeps its quarantine list: `$XDG_STATE_HOME/zen/quarantine.jsonl`,
+/// or `~/.local/state/zen/quarantine.jsonl`.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("state")))
+        .map(|dir| dir.join("zen").join("quarantine.jsonl"))
+}
+
+// One JSON object per entry after a `{"zen-quarantine":1}` header:
+//
+//   {"pid":4242,"start":918273,"path":"/tmp/miner","name":"miner","uid":1000,
+//    "ppid":4200,"method":"cgroup","cgroup":"/user.slice/...","since":1708300000}
+//
+// `path` is left out for a process without an executable and `cgroup` for
+// `"method":"sigstop"`.
+
+struct Saved {
+    pid: u32,
+    start_time: u64,
+    path: Option<String>,
+    name: String,
+    uid: u32,
+    ppid: u32,
+    freeze: Freeze,
+    since: u64,
+}
+
+fn write_entry(entry: &Entry) -> String {
+    let string = |s: &str| format!("\"{}\"", json_escape(s));
+    let mut fields = vec![
+        format!("\"pid\":{}", entry.handle.pid),
+        format!("\"start\":{}", entry.handle.start_time as i64),
+    ];
+    if let Some(path) = &entry.handle.path {
+        fields.push(format!("\"path\":{}", string(path)));
+    }
+    fields.push(format!("\"name\":{}", string(&entry.name)));
+    fields.push(format!("\"uid\":{}", entry.uid));
+    fields.push(format!("\"ppid\":{}", entry.ppid));
+    fields.push(format!("\"method\":{}", string(entry.freeze.label())));
+    if let Freeze::Cgroup { original } = &entry.freeze {
+        fields.push(format!("\"cgroup\":{}", string(original)));
+    }
+    fields.push(format!("\"since\":{}", entry.since));
+    format!("{{{}}}", fields.join(","))
+}
+
+fn parse_file(path: &Path, content: &str) -> Result<Vec<Saved>, String> {
+    let mut lines = content.lines().enumerate();
+    let version = lines
+        .next()
+        .and_then(|(_, header)| json::parse(header).ok())
+        .and_then(|fields| field(&fields, "zen-quarantine").and_then(|v| v.as_u64().ok()));
+    if version != Some(VERSION) {
+        return Err(format!("{}: not a version {} zen quarantine file", path.display(), VERSION));
+    }
+    lines
+        .map(|(idx, line)| {
+            json::parse(line)
+                .and_then(|fields| read_entry(&fields))
+                .map_err(|err| format!("{}: line {}: {}", path.display(), idx + 1, err))
+        })
+        .collect()
+}
+
+fn read_entry(fields: &[Field]) -> Result<Saved, String> {
+    let int = |key: &str| -> Result<u64, String> {
+        match field(fields, key) {
+            Some(Value::Int(n)) => Ok(*n as u64),
+            _ => Err(format!("missing or bad '{}'", key)),
+        }
+    };
+    let small = |key: &str| -> Result<u32, String> { u32::try_from(int(key)?).map_err(|_| format!("bad '{}'", key)) };
+    let text = |key: &str| -> Result<Option<String>, String> {
+        field(fields, key).map(|v| v.as_str().map(str::to_string)).transpose()
+    };
+    let freeze = match text("method")?.as_deref() {
+        Some("cgroup") => Freeze::Cgroup {
+            original: text("cgroup")?.ok_or("missing 'cgroup'")?,
+        },
+        Some("sigstop") => Freeze::Stop,
+        _ => return Err("bad 'method'".to_string()),
+    };
+    Ok(Saved {
+        pid: small("pid")?,
+        start_time: int("start")?,
+        path: text("path")?,
+        name: text("name")?.ok_or("missing 'name'")?,
+        uid: small("uid")?,
+        ppid: small("ppid")?,
+        freeze,
+        since: int("since")?,
+    })
+}
+
+fn field<'a>(fields: &'a [Field], key: &str) -> Option<&'a Value> {
+    fields.iter().find(|f| f.key == key).map(|f| &f.value)
+}
+
+pub fn write_audit(audit: &SharedAudit, entry: &Entry, action: &str, verified: Option<bool>) {
+    let event = export::AuditEvent {
+        ts: export::now_ts(),
+        pid: entry.handle.pid,
+        uid: entry.uid,
+        ppid: entry.ppid,
+        name: &entry.name,
+        path: entry.handle.path.as_deref(),
+        action,
+        verified,
+        rule: None,
+        elf: None,
+    };
+    if let Some(writer) = audit.lock().unwrap().as_mut() {
+        let _ = writer.write_audit(&event);
+        let _ = writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::platform::ReplaySource;
+
+    const REPLAY: &str = "scan\n\
+        20\t1\t501\t0\t1024\tminer\t/tmp/miner\n\
+        21\t1\t501\t0\t1024\tbuild\t/opt/build\n";
+
+    fn temp(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("zen-quarantine-{}-{}", name, std::process::id()))
+    }
+
+    fn source() -> ReplaySource {
+        let source = ReplaySource::parse(REPLAY).unwrap();
+        source.list().unwrap();
+        source
+    }
+
+    fn freeze(source: &ReplaySource, quarantine: &mut Quarantine, pid: u32, freeze: Freeze) {
+        let info = source.process(pid).unwrap();
+        quarantine.insert(Entry {
+            handle: source.pin(&info).unwrap(),
+            name: info.name.clone(),
+            uid: info.uid,
+            ppid: info.ppid,
+            freeze,
+            since: 1_700_000_000,
+        });
+    }
+
+    fn no_audit() -> SharedAudit {
+        Arc::new(Mutex::new(None))
+    }
+
+    #[test]
+    fn restores_entries_after_a_crash() {
+        let path = temp("restore");
+        let source = source();
+        let mut first = Quarantine::with_file(Some(path.clone()));
+        freeze(&source, &mut first, 20, Freeze::Cgroup { original: "/user.slice".to_string() });
+        freeze(&source, &mut first, 21, Freeze::Stop);
+        drop(first);
+
+        let mut second = Quarantine::with_file(Some(path.clone()));
+        assert_eq!(second.restore(&source, &no_audit()).unwrap(), 2);
+        assert_eq!(second.list(), "20 miner /tmp/miner cgroup 1700000000\n21 build /opt/build sigstop 1700000000\n");
+        assert!(matches!(&second.entries[&20].freeze, Freeze::Cgroup { original } if original == "/user.slice"));
+
+        second.release(21, &source, &no_audit()).unwrap();
+        let mut third = Quarantine::with_file(Some(path.clone()));
+        assert_eq!(third.restore(&source, &no_audit()).unwrap(), 1);
+        assert!(third.contains(20) && !third.contains(21));
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn restore_skips_recycled_and_exited_pids() {
+        let path = temp("recycled");
+        fs::write(
+            &path,
+            "{\"zen-quarantine\":1}\n\
+             {\"pid\":20,\"start\":0,\"path\":\"/usr/bin/other\",\"name\":\"miner\",\"uid\":501,\"ppid\":1,\"method\":\"sigstop\",\"since\":1}\n\
+             {\"pid\":99,\"start\":0,\"path\":\"/tmp/gone\",\"name\":\"gone\",\"uid\":501,\"ppid\":1,\"method\":\"sigstop\",\"since\":1}\n\
+             {\"pid\":21,\"start\":0,\"path\":\"/opt/build\",\"name\":\"build\",\"uid\":501,\"ppid\":1,\"method\":\"sigstop\",\"since\":1}\n",
+        )
+        .unwrap();
+
+        let mut quarantine = Quarantine::with_file(Some(path.clone()));
+        assert_eq!(quarantine.restore(&source(), &no_audit()).unwrap(), 1);
+        assert!(quarantine.contains(21) && !quarantine.contains(20) && !quarantine.contains(99));
+        // The file now only lists what was taken back.
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 2);
+        let _ = fs::remove_file(&path);
+    }
+
+    #[test]
+    fn thaw_all_empties_the_list_and_the_file() {
+        let path = temp("thaw");
+        let source = source();
+        let mut quarantine = Quarantine::with_file(Some(path.clone()));
+        freeze(&source, &mut quarantine, 20, Freeze::Stop);
+        freeze(&source, &mut quarantine, 21, Freeze::Stop);
+        assert!(path.exists());
+
+        assert_eq!(quarantine.thaw_all(&source, &no_audit()), 2);
+        assert_eq!(quarantine.len(), 0);
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let path = temp("version");
+        fs::write(&path, "{\"zen-cache\":2}\n").unwrap();
+        let mut quarantine = Quarantine::with_file(Some(path.clone()));
+        assert!(quarantine.restore(&source(), &no_audit()).is_err());
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,309 @@
+use std::path::Path;
+
+use crate::config::Meta;
+use crate::export::json_escape;
+use crate::toml::{self, Entry, Value};
+
+const ACTIONS: [&str; 3] = ["allow", "deny", "quarantine"];
+const SEVERITIES: [&str; 4] = ["low", "medium", "high", "critical"];
+const RULE_KEYS: [&str; 6] = ["match", "action", "severity", "expires", "added_by", "comment"];
+
+/// How an allowlist file is written, chosen by its extension.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// One `KEY:VALUE` rule per line; anything but `.toml` and `.json`.
+    Lines,
+    Toml,
+    Json,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Format::Toml,
+            Some("json") => Format::Json,
+            _ => Format::Lines,
+        }
+    }
+}
+
+/// One entry of an allowlist file in any format: a rule line as the line
+/// format spells it (`deny:name:x`, `include:base`, ...) and its metadata.
+pub struct Item {
+    pub line: usize,
+    pub text: String,
+    pub meta: Meta,
+}
+
+/// The entries of one file, without following includes. In the line format
+/// the comment lines right above a rule become its `comment`. The structured
+/// formats look like this (JSON uses the same keys):
+///
+/// ```text
+/// extends = "base"
+/// include = ["dev"]
+///
+/// [[rules]]
+/// match = "path:/usr/local/bin/ollama"
+/// action = "quarantine"         # allow (default), deny or quarantine
+/// severity = "high"             # low, medium, high or critical
+/// expires = 2026-12-31          # ignored from the next day on
+/// added_by = "ana"
+/// comment = "hasta revisar el modelo"
+/// ```
+pub fn read(path: &Path, content: &str) -> Result<Vec<Item>, String> {
+    match Format::of(path) {
+        Format::Lines => Ok(read_lines(content)),
+        Format::Toml => read_structured(toml::parse(content)?),
+        Format::Json => read_structured(crate::json::parse(content)?),
+    }
+}
+
+fn read_lines(content: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut comment: Vec<&str> = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            comment.clear();
+            continue;
+        }
+        if let Some(text) = line.strip_prefix('#') {
+            comment.push(text.trim());
+            continue;
+        }
+        items.push(Item {
+            line: idx + 1,
+            text: line.to_string(),
+            meta: Meta {
+                comment: (!comment.is_empty()).then(|| comment.join("\n")),
+                ..Meta::default()
+            },
+        });
+        comment.clear();
+    }
+    items
+}
+
+fn read_structured(entries: Vec<Entry>) -> Result<Vec<Item>, String> {
+    let mut items = Vec::new();
+    for entry in entries {
+        let at = |err: String| format!("line {}: {}: {}", entry.line, entry.key, err);
+        match entry.key.as_str() {
+            "extends" => items.push(Item {
+                line: entry.line,
+                text: format!("extends:{}", entry.value.as_str().map_err(at)?),
+                meta: Meta::default(),
+            }),
+            "include" => {
+                let targets = match &entry.value {
+                    Value::Array(values) => values.iter().collect(),
+                    value => vec![value],
+                };
+                for target in targets {
+                    items.push(Item {
+                        line: entry.line,
+                        text: format!("include:{}", target.as_str().map_err(at)?),
+                        meta: Meta::default(),
+                    });
+                }
+            }
+            "rules" => {
+                let Value::Array(rules) = entry.value else {
+                    return Err(at("expected a list of rules".to_string()));
+                };
+                for rule in rules {
+                    let Value::Table(fields) = rule else {
+                        return Err(at("expected a list of rules".to_string()));
+                    };
+                    items.push(read_rule(entry.line, fields)?);
+                }
+            }
+            _ => return Err(at("unknown key, expected extends, include or rules".to_string())),
+        }
+    }
+    Ok(items)
+}
+
+/// One `[[rules]]` table. Its line is the line of `match`.
+fn read_rule(table_line: usize, fields: Vec<Entry>) -> Result<Item, String> {
+    let mut rule_match = None;
+    let mut action = None;
+    let mut meta = Meta::default();
+    for field in &fields {
+        let at = |err: String| format!("line {}: {}: {}", field.line, field.key, err);
+        if !RULE_KEYS.contains(&field.key.as_str()) {
+            return Err(at(format!("unknown rule key, expected one of {}", RULE_KEYS.join(", "))));
+        }
+        let value = field.value.as_str().map_err(at)?.to_string();
+        match field.key.as_str() {
+            "match" => rule_match = Some((field.line, value)),
+            "action" if ACTIONS.contains(&value.as_str()) => action = Some(value),
+            "action" => return Err(at("expected allow, deny or quarantine".to_string())),
+            "severity" if SEVERITIES.contains(&value.as_str()) => meta.severity = Some(value),
+            "severity" => return Err(at("expected low, medium, high or critical".to_string())),
+            "expires" if toml::is_date(&value) => meta.expires = Some(value),
+            "expires" => return Err(at("expected a YYYY-MM-DD date".to_string())),
+            "added_by" => meta.added_by = Some(value),
+            _ => meta.comment = Some(value),
+        }
+    }
+    let (line, rule_match) = rule_match.ok_or_else(|| format!("line {}: rule without match", table_line))?;
+    let text = match action.as_deref() {
+        None | Some("allow") => rule_match,
+        Some(_) if rule_match.starts_with("deny:") || rule_match.starts_with("quarantine:") => {
+            return Err(format!("line {}: action given both in match and in action", line));
+        }
+        Some(action) => format!("{}:{}", action, rule_match),
+    };
+    Ok(Item { line, text, meta })
+}
+
+/// `path` rewritten in `to`, a structured format. Includes stay includes;
+/// they are listed first.
+pub fn convert(path: &Path, content: &str, to: Format) -> Result<String, String> {
+    let items = read(path, content)?;
+    let (heads, rules): (Vec<&Item>, Vec<&Item>) = items
+        .iter()
+        .partition(|item| item.text.starts_with("include:") || item.text.starts_with("extends:"));
+    let directive = |prefix: &str| -> Vec<&str> {
+        heads.iter().filter_map(|item| item.text.strip_prefix(prefix)).map(str::trim).collect()
+    };
+    let extends = directive("extends:");
+    if extends.len() > 1 {
+        return Err("more than one extends".to_string());
+    }
+    let includes = directive("include:");
+    let rules: Vec<Vec<(&str, &str)>> = rules.iter().map(|item| rule_fields(item)).collect();
+    match to {
+        Format::Lines => Err("the line format cannot hold rule metadata; convert to .toml or .json".to_string()),
+        Format::Toml => Ok(write_toml(path, &extends, &includes, &rules)),
+        Format::Json => Ok(write_json(&extends, &includes, &rules)),
+    }
+}
+
+/// A rule's keys in the order they are written, `match` first.
+fn rule_fields(item: &Item) -> Vec<(&str, &str)> {
+    let (action, rule_match) = match item.text.split_once(':') {
+        Some((action @ ("deny" | "quarantine"), rest)) => (Some(action), rest),
+        _ => (None, item.text.as_str()),
+    };
+    let mut fields = vec![("match", rule_match)];
+    fields.extend(action.map(|a| ("action", a)));
+    let meta = &item.meta;
+    for (key, value) in [
+        ("severity", &meta.severity),
+        ("expires", &meta.expires),
+        ("added_by", &meta.added_by),
+        ("comment", &meta.comment),
+    ] {
+        fields.extend(value.as_deref().map(|v| (key, v)));
+    }
+    fields
+}
+
+fn write_toml(from: &Path, extends: &[&str], includes: &[&str], rules: &[Vec<(&str, &str)>]) -> String {
+    let mut out = format!("# Convertido de {} con zen --convert-config\n", from.display());
+    if let Some(parent) = extends.first() {
+        out.push_str(&format!("extends = {}\n", toml::quote(parent)));
+    }
+    if !includes.is_empty() {
+        let list: Vec<String> = includes.iter().map(|i| toml::quote(i)).collect();
+        out.push_str(&format!("include = [{}]\n", list.join(", ")));
+    }
+    for fields in rules {
+        out.push_str("\n[[rules]]\n");
+        for (key, value) in fields {
+            // Dates are written bare, as TOML local dates.
+            let value = if *key == "expires" { value.to_string() } else { toml::quote(value) };
+            out.push_str(&format!("{} = {}\n", key, value));
+        }
+    }
+    out
+}
+
+fn write_json(extends: &[&str], includes: &[&str], rules: &[Vec<(&str, &str)>]) -> String {
+    let string = |s: &str| format!("\"{}\"", json_escape(s));
+    let mut top = Vec::new();
+    if let Some(parent) = extends.first() {
+        top.push(format!("  \"extends\": {}", string(parent)));
+    }
+    if !includes.is_empty() {
+        let list: Vec<String> = includes.iter().map(|i| string(i)).collect();
+        top.push(format!("  \"include\": [{}]", list.join(", ")));
+    }
+    let rules: Vec<String> = rules
+        .iter()
+        .map(|fields| {
+            let fields: Vec<String> = fields.iter().map(|(k, v)| format!("{}: {}", string(k), string(v))).collect();
+            format!("    {{{}}}", fields.join(", "))
+        })
+        .collect();
+    if rules.is_empty() {
+        top.push("  \"rules\": []".to_string());
+    } else {
+        top.push(format!("  \"rules\": [\n{}\n  ]", rules.join(",\n")));
+    }
+    format!("{{\n{}\n}}\n", top.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINES: &str = "extends:base\ninclude:dev\n\n# the shell\n# of choice\nname:zsh\n\ndeny:name:nc\nrule:name=cc & ancestor:name=cargo\n";
+
+    /// `(text, comment)` of every item, for comparing formats.
+    fn summary(items: &[Item]) -> Vec<(String, Option<String>)> {
+        items.iter().map(|i| (i.text.clone(), i.meta.comment.clone())).collect()
+    }
+
+    #[test]
+    fn comment_lines_become_the_rule_comment() {
+        let items = read(Path::new("allow.txt"), LINES).unwrap();
+        let lines: Vec<usize> = items.iter().map(|i| i.line).collect();
+        assert_eq!(lines, vec![1, 2, 6, 8, 9]);
+        assert_eq!(items[2].meta.comment.as_deref(), Some("the shell\nof choice"));
+        assert!(items[3].meta.comment.is_none());
+    }
+
+    #[test]
+    fn convert_round_trips_through_toml_and_json() {
+        let original = read(Path::new("allow.txt"), LINES).unwrap();
+
+        let toml = convert(Path::new("allow.txt"), LINES, Format::Toml).unwrap();
+        let from_toml = read(Path::new("allow.toml"), &toml).unwrap();
+        assert_eq!(summary(&from_toml), summary(&original));
+
+        let json = convert(Path::new("allow.toml"), &toml, Format::Json).unwrap();
+        let from_json = read(Path::new("allow.json"), &json).unwrap();
+        assert_eq!(summary(&from_json), summary(&original));
+
+        // Metadata survives too.
+        let rich = "[[rules]]\nmatch = \"name:nc\"\naction = \"deny\"\nseverity = \"high\"\n\
+                    expires = 2030-01-31\nadded_by = \"ana\"\ncomment = \"say \\\"no\\\"\"\n";
+        let json = convert(Path::new("rich.toml"), rich, Format::Json).unwrap();
+        let back = convert(Path::new("rich.json"), &json, Format::Toml).unwrap();
+        let item = &read(Path::new("back.toml"), &back).unwrap()[0];
+        assert_eq!(item.text, "deny:name:nc");
+        assert_eq!(item.meta.severity.as_deref(), Some("high"));
+        assert_eq!(item.meta.expires.as_deref(), Some("2030-01-31"));
+        assert_eq!(item.meta.added_by.as_deref(), Some("ana"));
+        assert_eq!(item.meta.comment.as_deref(), Some("say \"no\""));
+        assert!(convert(Path::new("rich.toml"), rich, Format::Lines).is_err());
+    }
+
+    #[test]
+    fn structured_rules_are_validated() {
+        for (content, error) in [
+            ("[[rules]]\naction = \"deny\"\n", "line 1: rule without match"),
+            ("[[rules]]\nmatch = \"name:x\"\naction = \"kill\"\n", "line 3: action: expected allow, deny or quarantine"),
+            ("[[rules]]\nmatch = \"name:x\"\nseverity = \"meh\"\n", "line 3: severity: expected low, medium, high or critical"),
+            ("[[rules]]\nmatch = \"name:x\"\nexpires = \"soon\"\n", "line 3: expires: expected a YYYY-MM-DD date"),
+            ("[[rules]]\nmatch = \"deny:name:x\"\naction = \"deny\"\n", "line 2: action given both in match and in action"),
+            ("profile = \"x\"\n", "line 1: profile: unknown key, expected extends, include or rules"),
+        ] {
+            assert_eq!(read(Path::new("x.toml"), content).err().as_deref(), Some(error), "{}", content);
+        }
+    }
+}
@@ -0,0 +1,278 @@
+use std::fs;
+use std::os::raw::{c_char, c_int, c_long};
+use std::path::Path;
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+#[repr(C)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    tm_wday: c_int,
+    tm_yday: c_int,
+    tm_isdst: c_int,
+    tm_gmtoff: c_long,
+    tm_zone: *const c_char,
+}
+
+extern "C" {
+    fn localtime_r(t: *const i64, out: *mut Tm) -> *mut Tm;
+}
+
+/// A point in the local week, minute resolution. `weekday` 0 is Monday.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct WeekTime {
+    pub weekday: u32,
+    pub minute: u32,
+}
+
+impl WeekTime {
+    pub fn now() -> WeekTime {
+        let ts = crate::export::now_ts() as i64;
+        let mut tm: Tm = unsafe { std::mem::zeroed() };
+        if unsafe { localtime_r(&ts, &mut tm) }.is_null() {
+            // Without a usable local time, fall back to UTC.
+            let days = ts / 86400;
+            return WeekTime {
+                weekday: ((days + 3) % 7) as u32,
+                minute: ((ts % 86400) / 60) as u32,
+            };
+        }
+        WeekTime {
+            weekday: ((tm.tm_wday + 6) % 7) as u32,
+            minute: (tm.tm_hour * 60 + tm.tm_min) as u32,
+        }
+    }
+
+    fn plus_minutes(self, minutes: u32) -> WeekTime {
+        let total = self.minute + minutes;
+        WeekTime {
+            weekday: (self.weekday + total / MINUTES_PER_DAY) % 7,
+            minute: total % MINUTES_PER_DAY,
+        }
+    }
+
+    pub fn label(&self) -> String {
+        format!(
+            "{} {:02}:{:02}",
+            DAY_NAMES[self.weekday as usize],
+            self.minute / 60,
+            self.minute % 60
+        )
+    }
+}
+
+/// Today's local date as `YYYY-MM-DD`, the form of `expires` in rule files.
+pub fn today() -> String {
+    let ts = crate::export::now_ts() as i64;
+    let mut tm: Tm = unsafe { std::mem::zeroed() };
+    if unsafe { localtime_r(&ts, &mut tm) }.is_null() {
+        return crate::export::format_ts(ts as u64)[..10].to_string();
+    }
+    format!("{:04}-{:02}-{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday)
+}
+
+struct Window {
+    days: [bool; 7],
+    start: u32,
+    end: u32,
+    profile: String,
+}
+
+impl Window {
+    /// A window that ends before it starts runs past midnight and belongs to
+    /// the day it starts on.
+    fn contains(&self, t: WeekTime) -> bool {
+        if self.start < self.end {
+            return self.days[t.weekday as usize] && t.minute >= self.start && t.minute < self.end;
+        }
+        let yesterday = ((t.weekday + 6) % 7) as usize;
+        (self.days[t.weekday as usize] && t.minute >= self.start) || (self.days[yesterday] && t.minute < self.end)
+    }
+}
+
+/// Which profile should be active when. File format, one entry per line:
+///
+/// ```text
+/// mon-fri   09:00-13:00  sin-distracciones
+/// *         22:00-07:00  reposo
+/// default                compilando
+/// ```
+///
+/// The first matching window wins. Outside every window the `default`
+/// profile applies, or the profile the daemon started with if there is none.
+pub struct Schedule {
+    windows: Vec<Window>,
+    default: Option<String>,
+}
+
+impl Schedule {
+    pub fn load(path: &Path) -> Result<Schedule, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read schedule {}: {}", path.display(), err))?;
+        Schedule::parse(&content)
+    }
+
+    pub fn parse(content: &str) -> Result<Schedule, String> {
+        let mut schedule = Schedule {
+            windows: Vec::new(),
+            default: None,
+        };
+        for (idx, line) in content.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            match fields.as_slice() {
+                ["default", profile] => schedule.default = Some(profile.to_string()),
+                [days, hours, profile] => {
+                    let days = parse_days(days).ok_or_else(|| format!("invalid days on schedule line {}", idx + 1))?;
+                    let (start, end) =
+                        parse_hours(hours).ok_or_else(|| format!("invalid hours on schedule line {}", idx + 1))?;
+                    schedule.windows.push(Window {
+                        days,
+                        start,
+                        end,
+                        profile: profile.to_string(),
+                    });
+                }
+                _ => return Err(format!("invalid schedule line {}: {}", idx + 1, line)),
+            }
+        }
+        if schedule.windows.is_empty() && schedule.default.is_none() {
+            return Err("schedule is empty".to_string());
+        }
+        Ok(schedule)
+    }
+
+    /// The scheduled profile at `t`; `None` means the startup profile.
+    pub fn active_at(&self, t: WeekTime) -> Option<&str> {
+        self.windows
+            .iter()
+            .find(|w| w.contains(t))
+            .map(|w| w.profile.as_str())
+            .or(self.default.as_deref())
+    }
+
+    /// The next time within a week the scheduled profile changes, and to what.
+    pub fn next_change(&self, now: WeekTime) -> Option<(WeekTime, Option<&str>)> {
+        let current = self.active_at(now);
+        (1..=7 * MINUTES_PER_DAY)
+            .map(|m| now.plus_minutes(m))
+            .find(|t| self.active_at(*t) != current)
+            .map(|t| (t, self.active_at(t)))
+    }
+}
+
+/// `*`, `weekdays`, `weekends`, or comma-separated days and ranges (`mon-fri`,
+/// `fri-mon`, `sat,sun`).
+fn parse_days(value: &str) -> Option<[bool; 7]> {
+    let mut days = [false; 7];
+    match value.to_lowercase().as_str() {
+        "*" | "daily" => return Some([true; 7]),
+        "weekdays" => return parse_days("mon-fri"),
+        "weekends" => return parse_days("sat,sun"),
+        _ => {}
+    }
+    for part in value.to_lowercase().split(',') {
+        let (from, to) = part.split_once('-').unwrap_or((part, part));
+        let from = DAY_NAMES.iter().position(|d| *d == from)?;
+        let to = DAY_NAMES.iter().position(|d| *d == to)?;
+        let mut day = from;
+        loop {
+            days[day] = true;
+            if day == to {
+                break;
+            }
+            day = (day + 1) % 7;
+        }
+    }
+    Some(days)
+}
+
+/// `HH:MM-HH:MM`; an end of `24:00` or equal to the start covers the whole day.
+fn parse_hours(value: &str) -> Option<(u32, u32)> {
+    let (start, end) = value.split_once('-')?;
+    let start = parse_clock(start)?;
+    let end = parse_clock(end)?;
+    if start >= MINUTES_PER_DAY {
+        return None;
+    }
+    Some((start, end))
+}
+
+fn parse_clock(value: &str) -> Option<u32> {
+    let (h, m) = value.split_once(':')?;
+    let (h, m): (u32, u32) = (h.parse().ok()?, m.parse().ok()?);
+    if m >= 60 || h > 24 || (h == 24 && m > 0) {
+        return None;
+    }
+    Some(h * 60 + m)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(weekday: u32, hour: u32, minute: u32) -> WeekTime {
+        WeekTime {
+            weekday,
+            minute: hour * 60 + minute,
+        }
+    }
+
+    #[test]
+    fn parses_days() {
+        assert_eq!(parse_days("*"), Some([true; 7]));
+        assert_eq!(parse_days("weekdays"), Some([true, true, true, true, true, false, false]));
+        assert_eq!(parse_days("Sat,Sun"), Some([false, false, false, false, false, true, true]));
+        // Ranges wrap around the end of the week.
+        assert_eq!(parse_days("fri-mon"), Some([true, false, false, false, true, true, true]));
+        assert_eq!(parse_days("mon,wed-thu"), Some([true, false, true, true, false, false, false]));
+        assert_eq!(parse_days("mon-funday"), None);
+        assert_eq!(parse_days(""), None);
+    }
+
+    #[test]
+    fn overnight_window_belongs_to_its_start_day() {
+        let schedule = Schedule::parse("fri 22:00-07:00 reposo\n").unwrap();
+        assert_eq!(schedule.active_at(at(4, 21, 59)), None);
+        assert_eq!(schedule.active_at(at(4, 22, 0)), Some("reposo"));
+        assert_eq!(schedule.active_at(at(5, 6, 59)), Some("reposo"));
+        assert_eq!(schedule.active_at(at(5, 7, 0)), None);
+        // Thursday night is not Friday's window.
+        assert_eq!(schedule.active_at(at(4, 3, 0)), None);
+        assert_eq!(schedule.active_at(at(5, 22, 30)), None);
+    }
+
+    #[test]
+    fn first_window_wins_and_default_fills_the_rest() {
+        let schedule = Schedule::parse("mon-fri 09:00-13:00 foco\n* 00:00-24:00 todo\ndefault base\n").unwrap();
+        assert_eq!(schedule.active_at(at(0, 10, 0)), Some("foco"));
+        assert_eq!(schedule.active_at(at(6, 10, 0)), Some("todo"));
+        let schedule = Schedule::parse("sat 10:00-12:00 ocio\ndefault base\n").unwrap();
+        assert_eq!(schedule.active_at(at(0, 10, 0)), Some("base"));
+        assert!(Schedule::parse("mon 25:00-26:00 x\n").is_err());
+        assert!(Schedule::parse("# nothing\n").is_err());
+    }
+
+    #[test]
+    fn next_change_crosses_days_and_the_week() {
+        let schedule = Schedule::parse("mon-fri 22:00-07:00 reposo\n").unwrap();
+        let (t, profile) = schedule.next_change(at(0, 12, 0)).unwrap();
+        assert_eq!((t.label(), profile), ("mon 22:00".to_string(), Some("reposo")));
+        let (t, profile) = schedule.next_change(at(0, 23, 0)).unwrap();
+        assert_eq!((t.label(), profile), ("tue 07:00".to_string(), None));
+        // Saturday morning after Friday night: next is Monday night.
+        let (t, _) = schedule.next_change(at(5, 8, 0)).unwrap();
+        assert_eq!(t.label(), "mon 22:00");
+        let always = Schedule::parse("* 00:00-24:00 todo\n").unwrap();
+        assert!(always.next_change(at(3, 12, 0)).is_none());
+    }
+}
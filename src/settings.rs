@@ -0,0 +1,46 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::toml::{self, Entry};
+
+/// A `zen.toml` settings file: flat `key = value` lines (see `toml.rs`)
+/// whose keys are the long command-line flags without the leading dashes:
+///
+/// ```text
+/// daemon = true
+/// profile = "compilando"
+/// interval = 2000
+/// audit-log = "/var/log/zen/audit.log"
+/// ```
+pub struct Settings {
+    pub path: PathBuf,
+    pub entries: Vec<Entry>,
+}
+
+impl Settings {
+    pub fn load(path: &Path) -> Result<Settings, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("failed to read settings {}: {}", path.display(), err))?;
+        let entries = toml::parse(&content).map_err(|err| format!("{}: {}", path.display(), err))?;
+        Ok(Settings {
+            path: path.to_path_buf(),
+            entries,
+        })
+    }
+}
+
+/// Where zen looks for settings when `--settings` is not given: the user's
+/// `$XDG_CONFIG_HOME/zen/zen.toml` (or `~/.config/zen/zen.toml`), then the
+/// system-wide `/etc/zen/zen.toml`. The first one that exists wins.
+pub fn default_path() -> Option<PathBuf> {
+    let user_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    user_dir
+        .map(|dir| dir.join("zen").join("zen.toml"))
+        .into_iter()
+        .chain([PathBuf::from("/etc/zen/zen.toml")])
+        .find(|path| path.is_file())
+}
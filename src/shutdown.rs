@@ -0,0 +1,30 @@
+//! SIGINT and SIGTERM only raise a flag. The daemon loop polls it and leaves
+//! through its normal exit path, so quarantined processes are thawed and
+//! summaries and learned rules are still written.
+
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const SIGINT: c_int = 2;
+const SIGTERM: c_int = 15;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn signal(sig: c_int, handler: extern "C" fn(c_int)) -> usize;
+}
+
+extern "C" fn on_signal(_sig: c_int) {
+    REQUESTED.store(true, Ordering::SeqCst);
+}
+
+pub fn install() {
+    unsafe {
+        signal(SIGINT, on_signal);
+        signal(SIGTERM, on_signal);
+    }
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
@@ -1,14 +1,48 @@
-use std::process::Command;
+use crate::package::PackageInfo;
 
-#[derive(Clone, Debug)]
+/// Who vouches for an executable: its code signature on macOS, the package
+/// that installed it on Linux.
+#[derive(Clone, Debug, Default)]
 pub struct SignatureInfo {
     pub team_id: Option<String>,
-    pub authority: Option<String>,
+    /// Certificate common names, signing certificate first.
+    pub authorities: Vec<String>,
+    /// The signing identifier, usually a bundle ID.
+    pub identifier: Option<String>,
+    /// The designated requirement, as `codesign -d -r-` prints it.
+    pub requirement: Option<String>,
+    pub hardened_runtime: bool,
+    /// Signed without a certificate: nobody vouches for the file.
+    pub adhoc: bool,
+    /// Hex cdhashes of the Mach-O CodeDirectories. Everything above is what
+    /// the file claims; `policy::verified_signature` trusts it only when the
+    /// kernel reports one of these for the running process.
+    pub cdhashes: Vec<String>,
+    /// The package that owns the file; Linux only.
+    pub package: Option<PackageInfo>,
 }
 
 impl SignatureInfo {
-    pub fn display_name(&self) -> String {
-        if let Some(auth) = &self.authority {
+    /// The signing certificate, which `authority:` rules match.
+    pub fn authority(&self) -> Option<&str> {
+        self.authorities.first().map(String::as_str)
+    }
+
+    /// `verified` is whether the kernel vouches for the running code; an
+    /// unverified signer is shown as such.
+    pub fn display_name(&self, verified: bool) -> String {
+        if let Some(pkg) = &self.package {
+            let vendor = pkg.vendor.as_deref().unwrap_or("sin vendor");
+            return if pkg.tampered {
+                format!("🚨 MODIFICADO tras instalar: {} {} ({}, {})", pkg.name, pkg.version, pkg.manager, vendor)
+            } else {
+                format!("Paquete {} {} ({}, {})", pkg.name, pkg.version, pkg.manager, vendor)
+            };
+        }
+        if self.adhoc {
+            return "⚠️  SIN FIRMA (Ad-Hoc)".to_string();
+        }
+        let name = if let Some(auth) = self.authority() {
             // Strip "Developer ID Application: " if present for cleaner display
             let clean = auth.replace("Developer ID Application: ", "");
             if let Some(team) = &self.team_id {
@@ -19,40 +53,36 @@ impl SignatureInfo {
         } else if let Some(team) = &self.team_id {
             format!("Team ID: {}", team)
         } else {
-            "Firma Ad-Hoc / Sin Autoridad".to_string()
+            return "Firma Ad-Hoc / Sin Autoridad".to_string();
+        };
+        if verified {
+            name
+        } else {
+            format!("⚠️  SIN VERIFICAR: {}", name)
         }
     }
 }
 
+#[cfg(target_os = "linux")]
 pub fn get_signature_info(path: &str) -> Option<SignatureInfo> {
-    let output = Command::new("codesign")
-        .arg("-dv")
-        .arg(path)
-        .output()
-        .ok()?;
-
-    if !output.status.success() {
-        return None; // No firmado o error
-    }
-
-    // codesign -dv escribe en stderr
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    
-    let mut team_id = None;
-    let mut authority = None;
-
-    for line in stderr.lines() {
-        if line.starts_with("TeamIdentifier=") {
-            team_id = Some(line["TeamIdentifier=".len()..].to_string());
-        } else if authority.is_none() && line.starts_with("Authority=") {
-            // Guardamos la primera autoridad, que suele ser el Developer ID
-            authority = Some(line["Authority=".len()..].to_string());
-        }
-    }
-
-    if team_id.is_none() && authority.is_none() {
-        return None;
-    }
+    crate::package::lookup(path).map(|package| SignatureInfo {
+        package: Some(package),
+        ..SignatureInfo::default()
+    })
+}
 
-    Some(SignatureInfo { team_id, authority })
+/// Read from the Mach-O code signature itself; no `codesign` process.
+#[cfg(not(target_os = "linux"))]
+pub fn get_signature_info(path: &str) -> Option<SignatureInfo> {
+    let signature = crate::macho::inspect(path)?;
+    Some(SignatureInfo {
+        team_id: signature.team_id,
+        authorities: signature.authorities,
+        identifier: Some(signature.identifier),
+        requirement: signature.requirement,
+        hardened_runtime: signature.hardened_runtime,
+        adhoc: signature.adhoc,
+        cdhashes: signature.cdhashes,
+        package: None,
+    })
 }
@@ -0,0 +1,293 @@
+/// The subset of TOML zen reads: `key = value` lines with strings, integers,
+/// floats, booleans, local dates (kept as `YYYY-MM-DD` strings) and
+/// single-line arrays, `[[name]]` arrays of tables, and `#` comments.
+/// `settings.rs` and the structured allowlists in `rulefile.rs` share it;
+/// `json.rs` parses into the same `Value`.
+pub enum Value {
+    Str(String),
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Array(Vec<Value>),
+    Table(Vec<Entry>),
+}
+
+impl Value {
+    pub fn as_bool(&self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            _ => Err("expected true or false".to_string()),
+        }
+    }
+
+    pub fn as_str(&self) -> Result<&str, String> {
+        match self {
+            Value::Str(s) => Ok(s),
+            _ => Err("expected a string".to_string()),
+        }
+    }
+
+    pub fn as_u64(&self) -> Result<u64, String> {
+        match self {
+            Value::Int(n) if *n >= 0 => Ok(*n as u64),
+            _ => Err("expected a non-negative integer".to_string()),
+        }
+    }
+
+    /// Integers are accepted too, so `cpu-threshold = 80` works.
+    pub fn as_f64(&self) -> Result<f64, String> {
+        match self {
+            Value::Float(f) => Ok(*f),
+            Value::Int(n) => Ok(*n as f64),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+}
+
+/// A key, its value and the line it was defined on.
+pub struct Entry {
+    pub key: String,
+    pub value: Value,
+    pub line: usize,
+}
+
+/// The top-level keys of `content`. Each `[[name]]` header adds a table to
+/// the array under `name`; the keys after it go into that table.
+pub fn parse(content: &str) -> Result<Vec<Entry>, String> {
+    let mut root: Vec<Entry> = Vec::new();
+    // Index in `root` of the array whose last table takes the current keys.
+    let mut current: Option<usize> = None;
+    for (idx, line) in content.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix("[[") {
+            let (name, rest) = header
+                .split_once("]]")
+                .ok_or_else(|| format!("line {}: unterminated [[table]] header", line_no))?;
+            let rest = rest.trim_start();
+            if !rest.is_empty() && !rest.starts_with('#') {
+                return Err(format!("line {}: unexpected '{}' after header", line_no, rest));
+            }
+            let name = key(name.trim(), line_no)?;
+            let pos = match root.iter().position(|e| e.key == name) {
+                Some(pos) => pos,
+                None => {
+                    root.push(Entry {
+                        key: name.to_string(),
+                        value: Value::Array(Vec::new()),
+                        line: line_no,
+                    });
+                    root.len() - 1
+                }
+            };
+            let Value::Array(tables) = &mut root[pos].value else {
+                return Err(format!("line {}: '{}' is already a plain key", line_no, name));
+            };
+            tables.push(Value::Table(Vec::new()));
+            current = Some(pos);
+            continue;
+        }
+        if line.starts_with('[') {
+            return Err(format!("line {}: only [[arrays of tables]] are supported, not [tables]", line_no));
+        }
+        let (name, rest) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected key = value", line_no))?;
+        let name = key(name.trim(), line_no)?;
+        let (value, rest) = value(rest.trim()).map_err(|err| format!("line {}: {}", line_no, err))?;
+        let rest = rest.trim_start();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(format!("line {}: unexpected '{}' after value", line_no, rest));
+        }
+        let table = match current {
+            None => &mut root,
+            Some(pos) => match &mut root[pos].value {
+                Value::Array(tables) => match tables.last_mut() {
+                    Some(Value::Table(table)) => table,
+                    _ => unreachable!("a [[table]] header always pushes a table"),
+                },
+                _ => unreachable!("`current` always points at an array"),
+            },
+        };
+        if table.iter().any(|e| e.key == name) {
+            return Err(format!("line {}: duplicate key '{}'", line_no, name));
+        }
+        table.push(Entry {
+            key: name.to_string(),
+            value,
+            line: line_no,
+        });
+    }
+    Ok(root)
+}
+
+fn key(name: &str, line_no: usize) -> Result<&str, String> {
+    if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_') {
+        return Err(format!("line {}: invalid key '{}'", line_no, name));
+    }
+    Ok(name)
+}
+
+/// One value at the start of `text`, and what follows it.
+fn value(text: &str) -> Result<(Value, &str), String> {
+    if let Some(body) = text.strip_prefix('"') {
+        let (s, len) = basic_string(body)?;
+        return Ok((Value::Str(s), &body[len..]));
+    }
+    if let Some(body) = text.strip_prefix('\'') {
+        let end = body.find('\'').ok_or("unterminated string")?;
+        return Ok((Value::Str(body[..end].to_string()), &body[end + 1..]));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut items = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Ok((Value::Array(items), after));
+            }
+            let (item, after) = value(rest)?;
+            items.push(item);
+            rest = after.trim_start();
+            if let Some(after) = rest.strip_prefix(',') {
+                rest = after;
+            } else if !rest.starts_with(']') {
+                return Err("expected ',' or ']' in array".to_string());
+            }
+        }
+    }
+    if text.starts_with('{') {
+        return Err("inline tables are not supported".to_string());
+    }
+    let end = text
+        .find(|c: char| c.is_whitespace() || matches!(c, ',' | ']' | '#'))
+        .unwrap_or(text.len());
+    let word = &text[..end];
+    let value = match word {
+        "" => return Err("missing value".to_string()),
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ if is_date(word) => Value::Str(word.to_string()),
+        _ => number(word).ok_or_else(|| format!("invalid value '{}'", word))?,
+    };
+    Ok((value, &text[end..]))
+}
+
+/// The contents of a `"..."` string whose opening quote is already consumed,
+/// and how many bytes of `body` it took including the closing quote.
+fn basic_string(body: &str) -> Result<(String, usize), String> {
+    let mut out = String::new();
+    let mut chars = body.char_indices();
+    while let Some((idx, c)) = chars.next() {
+        match c {
+            '"' => return Ok((out, idx + 1)),
+            '\\' => {
+                let (_, escaped) = chars.next().ok_or("unterminated string")?;
+                out.push(match escaped {
+                    '"' => '"',
+                    '\\' => '\\',
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    other => return Err(format!("unsupported escape \\{}", other)),
+                });
+            }
+            c => out.push(c),
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+fn number(word: &str) -> Option<Value> {
+    let digits = word.replace('_', "");
+    if let Ok(n) = digits.parse::<i64>() {
+        return Some(Value::Int(n));
+    }
+    // Rust would also take `inf` and `nan`; no setting wants those.
+    if !digits.bytes().all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b)) {
+        return None;
+    }
+    digits.parse::<f64>().ok().map(Value::Float)
+}
+
+/// A TOML local date, `YYYY-MM-DD`.
+pub fn is_date(word: &str) -> bool {
+    let b = word.as_bytes();
+    b.len() == 10
+        && b[4] == b'-'
+        && b[7] == b'-'
+        && b.iter().enumerate().all(|(i, c)| i == 4 || i == 7 || c.is_ascii_digit())
+        && matches!(word[5..7].parse::<u32>(), Ok(1..=12))
+        && matches!(word[8..10].parse::<u32>(), Ok(1..=31))
+}
+
+/// `value` as a TOML basic string.
+pub fn quote(value: &str) -> String {
+    let mut out = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_values_and_arrays_of_tables() {
+        let entries = parse(
+            "interval = 1_000 # ms\nthreshold = 80.5\ndaemon = true\nname = 'a \"b\"'\nlist = [\"x\", 2]\n\n\
+             [[rules]]\nmatch = \"name:zsh\"\nexpires = 2026-12-31\n[[rules]]\nmatch = \"name:vim\\n\"\n",
+        )
+        .unwrap();
+        let keys: Vec<(&str, usize)> = entries.iter().map(|e| (e.key.as_str(), e.line)).collect();
+        assert_eq!(keys, vec![("interval", 1), ("threshold", 2), ("daemon", 3), ("name", 4), ("list", 5), ("rules", 7)]);
+        assert_eq!(entries[0].value.as_u64(), Ok(1000));
+        assert_eq!(entries[1].value.as_f64(), Ok(80.5));
+        assert_eq!(entries[2].value.as_bool(), Ok(true));
+        assert_eq!(entries[3].value.as_str(), Ok("a \"b\""));
+        assert!(matches!(&entries[4].value, Value::Array(items) if items.len() == 2));
+        let Value::Array(rules) = &entries[5].value else { panic!("rules is not an array") };
+        let Value::Table(second) = &rules[1] else { panic!("rule is not a table") };
+        assert_eq!(second[0].value.as_str(), Ok("name:vim\n"));
+        assert_eq!(second[0].line, 11);
+    }
+
+    #[test]
+    fn rejects_what_it_does_not_support() {
+        for (content, error) in [
+            ("a = 1\na = 2\n", "line 2: duplicate key 'a'"),
+            ("[table]\n", "line 1: only [[arrays of tables]] are supported, not [tables]"),
+            ("a = {b = 1}\n", "line 1: inline tables are not supported"),
+            ("a = \"open\n", "line 1: unterminated string"),
+            ("a = 1 2\n", "line 1: unexpected '2' after value"),
+            ("a = nan\n", "line 1: invalid value 'nan'"),
+            ("bad key = 1\n", "line 1: invalid key 'bad key'"),
+            ("a = 1\n[[a]]\n", "line 2: 'a' is already a plain key"),
+        ] {
+            assert_eq!(parse(content).err().as_deref(), Some(error), "{}", content);
+        }
+    }
+
+    #[test]
+    fn dates_and_quoting() {
+        assert!(is_date("2026-02-28"));
+        assert!(!is_date("2026-13-01"));
+        assert!(!is_date("2026-1-01"));
+        let quoted = quote("tab\there \"q\" back\\slash\n");
+        assert_eq!(quoted, "\"tab\\there \\\"q\\\" back\\\\slash\\n\"");
+        let entries = parse(&format!("v = {}\n", quoted)).unwrap();
+        assert_eq!(entries[0].value.as_str(), Ok("tab\there \"q\" back\\slash\n"));
+    }
+}
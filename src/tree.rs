@@ -49,6 +49,37 @@ impl ProcTree {
         out
     }
 
+    /// `pid` and all its descendants, children before parents.
+    pub fn subtree_children_first(&self, pid: u32) -> Vec<&ProcessInfo> {
+        let mut out = Vec::new();
+        self.post_order_from(pid, &mut out);
+        out
+    }
+
+    /// Parent first, up to the oldest ancestor in the snapshot.
+    pub fn ancestors(&self, pid: u32) -> Vec<&ProcessInfo> {
+        let mut out: Vec<&ProcessInfo> = Vec::new();
+        let mut current = self.nodes.get(&pid).map(|n| n.info.ppid);
+        while let Some(ppid) = current {
+            // A recycled PID can make the ppid chain loop back on itself.
+            let Some(node) = self.nodes.get(&ppid) else { break };
+            if ppid == pid || out.iter().any(|p| p.pid == ppid) {
+                break;
+            }
+            out.push(&node.info);
+            current = Some(node.info.ppid);
+        }
+        out
+    }
+
+    fn post_order_from<'a>(&'a self, pid: u32, out: &mut Vec<&'a ProcessInfo>) {
+        let Some(node) = self.nodes.get(&pid) else { return };
+        for child in &node.children {
+            self.post_order_from(*child, out);
+        }
+        out.push(&node.info);
+    }
+
     fn walk_from<'a>(&'a self, pid: u32, out: &mut Vec<&'a ProcessInfo>) {
         let Some(node) = self.nodes.get(&pid) else { return };
         out.push(&node.info);
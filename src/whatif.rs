@@ -0,0 +1,112 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::export::{self, Exporter, WhatIfEvent};
+use crate::platform::ProcessInfo;
+
+struct RuleStats {
+    action: &'static str,
+    hits: u64,
+    pids: BTreeSet<u32>,
+    names: BTreeSet<String>,
+    path: Option<String>,
+    first_seen: u64,
+    last_seen: u64,
+}
+
+/// Would-be enforcement actions collected during a `--what-if` window,
+/// keyed by the allowlist rule that would have let the process run.
+pub struct Summary {
+    window_secs: u64,
+    rules: BTreeMap<String, RuleStats>,
+}
+
+impl Summary {
+    pub fn new(window_secs: u64) -> Summary {
+        Summary {
+            window_secs,
+            rules: BTreeMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, info: &ProcessInfo, action: &'static str) {
+        let now = export::now_ts();
+        let stats = self.rules.entry(missing_rule(info)).or_insert_with(|| RuleStats {
+            action,
+            hits: 0,
+            pids: BTreeSet::new(),
+            names: BTreeSet::new(),
+            path: info.path.clone(),
+            first_seen: now,
+            last_seen: now,
+        });
+        // A quarantine verdict for the same rule is the stronger outcome.
+        if action == "would-quarantine" {
+            stats.action = action;
+        }
+        stats.hits += 1;
+        stats.pids.insert(info.pid);
+        stats.names.insert(info.name.clone());
+        stats.last_seen = now;
+    }
+
+    /// Shortens the reported window when the run was interrupted early.
+    pub fn end_after(&mut self, elapsed_secs: u64) {
+        self.window_secs = self.window_secs.min(elapsed_secs);
+    }
+
+    pub fn print(&self) {
+        let total: u64 = self.rules.values().map(|s| s.hits).sum();
+        println!(
+            "\n\x1b[1m\x1b[34m[WHAT-IF]\x1b[0m Ventana de {} s: \x1b[1m{}\x1b[0m acciones evitadas, {} reglas faltantes",
+            self.window_secs,
+            total,
+            self.rules.len()
+        );
+        for (rule, stats) in self.by_hits() {
+            let names: Vec<&str> = stats.names.iter().map(|n| n.as_str()).collect();
+            println!(
+                "  \x1b[31m{:>5}x\x1b[0m {:<16} \x1b[36m{}\x1b[0m \x1b[38;5;242m({}; {} PIDs)\x1b[0m",
+                stats.hits,
+                stats.action,
+                rule,
+                names.join(", "),
+                stats.pids.len()
+            );
+        }
+        println!();
+    }
+
+    pub fn export(&self, exporter: &mut Exporter) -> Result<(), String> {
+        let ts = export::now_ts();
+        for (rule, stats) in self.by_hits() {
+            let names: Vec<&str> = stats.names.iter().map(|n| n.as_str()).collect();
+            let names = names.join(",");
+            exporter.write_what_if(&WhatIfEvent {
+                ts,
+                rule,
+                action: stats.action,
+                hits: stats.hits,
+                pids: stats.pids.len() as u64,
+                names: &names,
+                path: stats.path.as_deref(),
+                first_seen: stats.first_seen,
+                last_seen: stats.last_seen,
+            })?;
+        }
+        exporter.flush()
+    }
+
+    fn by_hits(&self) -> Vec<(&str, &RuleStats)> {
+        let mut rules: Vec<(&str, &RuleStats)> = self.rules.iter().map(|(r, s)| (r.as_str(), s)).collect();
+        rules.sort_by(|a, b| b.1.hits.cmp(&a.1.hits).then_with(|| a.0.cmp(b.0)));
+        rules
+    }
+}
+
+/// The narrowest allowlist line that would have allowed `info`.
+pub fn missing_rule(info: &ProcessInfo) -> String {
+    match &info.path {
+        Some(path) => format!("path:{}", path),
+        None => format!("name:{}", info.name),
+    }
+}
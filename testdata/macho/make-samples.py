@@ -0,0 +1,191 @@
+#!/usr/bin/env python3
+"""Builds the signed Mach-O samples used by the tests in src/macho.rs.
+
+    python3 testdata/macho/make-samples.py
+
+Needs only python3 and the openssl CLI, so it runs on Linux. The keys and
+certificates are throwaway ones made in a temporary directory; signing
+times and serial numbers change on every run, the parsed fields do not.
+
+  signed-universal  x86_64 + arm64, each signed with a three-certificate
+                    Developer ID-style chain, team ABCDE12345, identifier
+                    dev.zen.sample, hardened runtime. The x86_64 slice holds
+                    its CMS in indefinite-length BER (as Apple's signer
+                    writes it), the arm64 slice in DER.
+  adhoc-arm64       linker-signed (ad-hoc) arm64 executable, no CMS.
+"""
+
+import hashlib
+import os
+import struct
+import subprocess
+import sys
+import tempfile
+
+HERE = os.path.dirname(os.path.abspath(__file__))
+TEAM = "ABCDE12345"
+IDENTIFIER = "dev.zen.sample"
+
+CPU_X86_64, CPU_ARM64 = 0x01000007, 0x0100000C
+PAGE = 0x1000
+
+
+def be(*values):
+    return struct.pack(">" + "I" * len(values), *values)
+
+
+def data(raw):
+    return be(len(raw)) + raw + b"\0" * (-len(raw) % 4)
+
+
+def designated_requirement():
+    # identifier "dev.zen.sample" and anchor apple generic
+    #   and certificate 1[field.1.2.840.113635.100.6.2.6] /* exists */
+    #   and certificate leaf[field.1.2.840.113635.100.6.1.13] /* exists */
+    #   and certificate leaf[subject.OU] = ABCDE12345
+    ident = be(2) + data(IDENTIFIER.encode())
+    generic = be(15)
+    intermediate = be(14, 1) + data(bytes.fromhex("2a864886f76364060206")) + be(0)
+    leaf = be(14, 0) + data(bytes.fromhex("2a864886f7636406010d")) + be(0)
+    team = be(11, 0) + data(b"subject.OU") + be(1) + data(TEAM.encode())
+    expr = team
+    for term in (leaf, intermediate, generic, ident):
+        expr = be(6) + term + expr
+    requirement = be(0xFADE0C00, 12 + len(expr), 1) + expr
+    return be(0xFADE0C01, 20 + len(requirement), 1, 3, 20) + requirement
+
+
+def code_directory(code, identifier, team, flags, requirements):
+    ident = identifier.encode() + b"\0"
+    team_bytes = team.encode() + b"\0" if team else b""
+    special = [hashlib.sha256(requirements).digest() if requirements else b"\0" * 32, b"\0" * 32]
+    pages = [hashlib.sha256(code[i : i + PAGE]).digest() for i in range(0, len(code), PAGE)]
+    header_len = 88
+    ident_off = header_len
+    team_off = ident_off + len(ident) if team else 0
+    hash_off = header_len + len(ident) + len(team_bytes) + 32 * len(special)
+    length = hash_off + 32 * len(pages)
+    header = (
+        be(0xFADE0C02, length, 0x20400, flags, hash_off, ident_off, len(special), len(pages), len(code))
+        + bytes([32, 2, 0, 12])
+        + be(0, 0, team_off, 0)
+        + struct.pack(">QQQQ", 0, 0, len(code), 1)
+    )
+    assert len(header) == header_len
+    return header + ident + team_bytes + b"".join(reversed(special)) + b"".join(pages)
+
+
+def superblob(blobs):
+    offset = 12 + 8 * len(blobs)
+    index, body = b"", b""
+    for slot, blob in blobs:
+        index += be(slot, offset + len(body))
+        body += blob
+    return be(0xFADE0CC0, offset + len(body), len(blobs)) + index + body
+
+
+def macho(cpu, signer):
+    """A one-page executable whose signature `signer(code)` builds."""
+    code = bytearray(PAGE)
+    code[0x100:0x104] = b"\xc0\x03\x5f\xd6" if cpu == CPU_ARM64 else b"\xc3\x90\x90\x90"
+    # The load commands are part of the signed code and record the
+    # signature's size: sign until that size settles.
+    size = 0
+    for _ in range(3):
+        commands = (
+            struct.pack("<II16sQQQQIIII", 0x19, 72, b"__TEXT", 0x100000000, PAGE, 0, PAGE, 5, 5, 0, 0)
+            + struct.pack("<II16sQQQQIIII", 0x19, 72, b"__LINKEDIT", 0x100000000 + PAGE, PAGE, PAGE, size, 1, 1, 0, 0)
+            + struct.pack("<IIII", 0x1D, 16, PAGE, size)
+        )
+        header = struct.pack("<IIIIIIII", 0xFEEDFACF, cpu, 3 if cpu == CPU_X86_64 else 0, 2, 3, len(commands), 0x200085, 0)
+        code[: len(header) + len(commands)] = header + commands
+        signature = signer(bytes(code))
+        if len(signature) == size:
+            return bytes(code) + signature
+        size = len(signature)
+    sys.exit("signature size did not settle")
+
+
+def openssl(*args, cwd):
+    subprocess.run(["openssl", *args], cwd=cwd, check=True, capture_output=True)
+
+
+def make_chain(tmp):
+    openssl("req", "-x509", "-newkey", "rsa:2048", "-nodes", "-keyout", "root.key", "-out", "root.pem",
+            "-days", "3650", "-subj", "/CN=Zen Test Root CA/O=Zen Test/C=US", cwd=tmp)
+    ca_ext = os.path.join(tmp, "ca.ext")
+    with open(ca_ext, "w") as f:
+        f.write("basicConstraints=critical,CA:true\nkeyUsage=critical,keyCertSign,cRLSign\n"
+                "1.2.840.113635.100.6.2.6=DER:0500\n")
+    leaf_ext = os.path.join(tmp, "leaf.ext")
+    with open(leaf_ext, "w") as f:
+        f.write("basicConstraints=critical,CA:false\nkeyUsage=critical,digitalSignature\n"
+                "extendedKeyUsage=codeSigning\n1.2.840.113635.100.6.1.13=DER:0500\n")
+    openssl("req", "-newkey", "rsa:2048", "-nodes", "-keyout", "ca.key", "-out", "ca.csr",
+            "-subj", "/CN=Zen Test Developer ID Certification Authority/OU=Zen Test Certification Authority/O=Zen Test/C=US", cwd=tmp)
+    openssl("x509", "-req", "-in", "ca.csr", "-CA", "root.pem", "-CAkey", "root.key", "-CAcreateserial",
+            "-days", "3650", "-extfile", ca_ext, "-out", "ca.pem", cwd=tmp)
+    openssl("req", "-newkey", "rsa:2048", "-nodes", "-keyout", "leaf.key", "-out", "leaf.csr",
+            "-subj", f"/UID={TEAM}/CN=Developer ID Application: Zen Test ({TEAM})/OU={TEAM}/O=Zen Test/C=US", cwd=tmp)
+    openssl("x509", "-req", "-in", "leaf.csr", "-CA", "ca.pem", "-CAkey", "ca.key", "-CAcreateserial",
+            "-days", "3650", "-extfile", leaf_ext, "-out", "leaf.pem", cwd=tmp)
+    with open(os.path.join(tmp, "chain.pem"), "w") as out:
+        for name in ("ca.pem", "root.pem"):
+            with open(os.path.join(tmp, name)) as f:
+                out.write(f.read())
+
+
+def cms(tmp, content, stream):
+    path = os.path.join(tmp, "cd.bin")
+    with open(path, "wb") as f:
+        f.write(content)
+    args = ["cms", "-sign", "-binary", "-md", "sha256", "-in", "cd.bin", "-signer", "leaf.pem",
+            "-inkey", "leaf.key", "-certfile", "chain.pem", "-outform", "DER", "-out", "cms.der"]
+    if stream:
+        args.append("-stream")
+    openssl(*args, cwd=tmp)
+    with open(os.path.join(tmp, "cms.der"), "rb") as f:
+        return f.read()
+
+
+def developer_id_signer(tmp, stream):
+    requirements = designated_requirement()
+
+    def sign(code):
+        cd = code_directory(code, IDENTIFIER, TEAM, 0x10000, requirements)
+        wrapper = cms(tmp, cd, stream)
+        return superblob([(0, cd), (2, requirements), (0x10000, be(0xFADE0B01, 8 + len(wrapper)) + wrapper)])
+
+    return sign
+
+
+def adhoc_signer(code):
+    return superblob([(0, code_directory(code, "sample-adhoc", None, 0x20002, None))])
+
+
+def fat(slices):
+    header = be(0xCAFEBABE, len(slices))
+    offset, body = PAGE, b""
+    for cpu, image in slices:
+        header += be(cpu, 3 if cpu == CPU_X86_64 else 0, offset, len(image), 12)
+        padded = image + b"\0" * (-len(image) % PAGE)
+        body += padded
+        offset += len(padded)
+    return header + b"\0" * (PAGE - len(header)) + body
+
+
+def main():
+    with tempfile.TemporaryDirectory() as tmp:
+        make_chain(tmp)
+        universal = fat([
+            (CPU_X86_64, macho(CPU_X86_64, developer_id_signer(tmp, stream=True))),
+            (CPU_ARM64, macho(CPU_ARM64, developer_id_signer(tmp, stream=False))),
+        ])
+    with open(os.path.join(HERE, "signed-universal"), "wb") as f:
+        f.write(universal)
+    with open(os.path.join(HERE, "adhoc-arm64"), "wb") as f:
+        f.write(macho(CPU_ARM64, adhoc_signer))
+
+
+if __name__ == "__main__":
+    main()